@@ -1,3 +1,3 @@
 use gpui::actions;
 
-actions!(app, [Quit]);
+actions!(app, [Quit, ExportAscii, ExportDot, ExportSvg]);
@@ -0,0 +1,96 @@
+use std::collections::{HashMap, HashSet};
+
+use git2::Oid;
+
+use crate::commit::CommitNode;
+use crate::edge::Edge;
+use crate::layout::GraphCell;
+
+/// Which parents of the selected commit its ancestry follows. Only matters
+/// for merges; older commits always follow every parent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ParentFollow {
+    #[default]
+    All,
+    /// index into the commit's parents, 0 being the first parent
+    Only(usize),
+}
+
+impl ParentFollow {
+    /// The choice after this one for a commit with `parents` parents: every
+    /// parent, then each parent on its own, then around again.
+    pub fn next(self, parents: usize) -> Self {
+        match self {
+            ParentFollow::All if parents > 1 => ParentFollow::Only(0),
+            ParentFollow::Only(index) if index + 1 < parents => ParentFollow::Only(index + 1),
+            _ => ParentFollow::All,
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            ParentFollow::All => "all parents".to_string(),
+            ParentFollow::Only(index) => format!("parent {}", index + 1),
+        }
+    }
+}
+
+/// `selected` with every ancestor and descendant among `nodes`. The drawn
+/// `edges` are followed rather than the git parents, so commits joined by an
+/// edge across left-out commits stay related, as the graph shows them. History
+/// that is not loaded yet is not followed.
+pub fn related(
+    nodes: &[CommitNode],
+    edges: &[Edge],
+    selected: Oid,
+    follow: ParentFollow,
+) -> HashSet<Oid> {
+    let at: HashMap<GraphCell, Oid> = nodes.iter().map(|n| (n.cell, n.oid)).collect();
+    let mut parents: HashMap<Oid, Vec<Oid>> = HashMap::new();
+    let mut children: HashMap<Oid, Vec<Oid>> = HashMap::new();
+    for edge in edges {
+        let (Some(child), Some(parent)) = (at.get(&edge.from), at.get(&edge.to)) else {
+            continue;
+        };
+        parents.entry(*child).or_default().push(*parent);
+        children.entry(*parent).or_default().push(*child);
+    }
+
+    let drawn = parents.get(&selected).cloned().unwrap_or_default();
+    let first = match follow {
+        ParentFollow::All => drawn,
+        ParentFollow::Only(index) => {
+            let own = nodes
+                .iter()
+                .find(|n| n.oid == selected)
+                .map(|n| n.parents.as_slice())
+                .unwrap_or_default();
+            match own.get(index) {
+                Some(parent) if drawn.contains(parent) => vec![*parent],
+                // a left-out parent is reached by one of the edges that ends
+                // at none of the other parents
+                Some(_) => drawn.into_iter().filter(|d| !own.contains(d)).collect(),
+                None => Vec::new(),
+            }
+        }
+    };
+
+    let mut related = HashSet::from([selected]);
+    walk(first, &mut related, |oid| {
+        parents.get(oid).cloned().unwrap_or_default()
+    });
+    walk(
+        children.get(&selected).cloned().unwrap_or_default(),
+        &mut related,
+        |oid| children.get(oid).cloned().unwrap_or_default(),
+    );
+    related
+}
+
+fn walk(mut stack: Vec<Oid>, seen: &mut HashSet<Oid>, next: impl Fn(&Oid) -> Vec<Oid>) {
+    while let Some(oid) = stack.pop() {
+        if seen.insert(oid) {
+            stack.extend(next(&oid));
+        }
+    }
+}
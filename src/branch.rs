@@ -1,27 +1,26 @@
 use std::cell::RefCell;
 use std::rc::Rc;
-use std::sync::mpsc::Receiver;
 
-use git2::Repository;
+use git2::{Branch, BranchType, Oid, Reference, Repository};
 use gpui::prelude::*;
 use gpui::{
-    AnyElement, Context, EventEmitter, InteractiveElement, IntoElement, MouseButton, ParentElement,
-    Render, SharedString, StatefulInteractiveElement, Styled, Window, div, px,
+    AnyElement, App, Context, EventEmitter, InteractiveElement, IntoElement, MouseButton,
+    ParentElement, Render, SharedString, StatefulInteractiveElement, Styled, Window, actions, div,
+    px,
 };
 
+use crate::job::Job;
 use crate::path_bar::RepoMode;
+use crate::widgets::{
+    self, COLOR_BG, COLOR_BORDER, COLOR_DIM_TEXT, COLOR_HEADING_BG, COLOR_HOVER_BG, COLOR_NO_REPO,
+    COLOR_TEXT, COLOR_WARNING_TEXT, NameField,
+};
+
+actions!(branch, [ConfirmBranchForm, CancelBranchForm]);
 
-const COLOR_HEADING_BG: u32 = 0x252525;
-const COLOR_BORDER: u32 = 0x333333;
-const COLOR_BG: u32 = 0x1E1E1E;
-const COLOR_TEXT: u32 = 0xCCCCCC;
 const COLOR_HEAD_BG: u32 = 0x2A3A2A;
 const COLOR_HEAD_TEXT: u32 = 0x4AE04A;
-const COLOR_HOVER_BG: u32 = 0x2A2A2A;
-const COLOR_NO_REPO: u32 = 0x666666;
 const COLOR_REMOTE_TEXT: u32 = 0x4A90D9;
-const COLOR_CHECKING_TEXT: u32 = 0xF39C12;
-const COLOR_LOADING_TEXT: u32 = 0x888888;
 
 #[derive(Clone, Debug)]
 pub struct BranchInfo {
@@ -35,6 +34,32 @@ pub struct BranchCheckedOut {
     pub name: String,
 }
 
+/// A branch was created, renamed or deleted.
+#[derive(Clone, Debug)]
+pub struct BranchesChanged;
+
+/// What the name input of the panel is open for.
+#[derive(Clone, Debug)]
+enum BranchForm {
+    /// a new branch at the selected commit, or at HEAD when `at` is `None`
+    Create {
+        at: Option<Oid>,
+        upstream: bool,
+    },
+    Rename {
+        from: String,
+    },
+}
+
+/// How a delete that was not forced went.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeleteOutcome {
+    Deleted,
+    /// the branch has commits that neither HEAD nor its upstream has, so it
+    /// was kept
+    Unmerged,
+}
+
 pub struct BranchReloadResult {
     pub branches: Vec<BranchInfo>,
 }
@@ -47,27 +72,45 @@ pub struct BranchPanel {
     repo_path: Option<String>,
     branches: Vec<BranchInfo>,
     mode: RepoMode,
-    pending_checkout_rx: Option<Receiver<Result<CheckoutResult, String>>>,
+    checkout_job: Option<Job>,
     checking_out: Option<String>,
     loading: bool,
+    name: NameField,
+    form: Option<BranchForm>,
+    /// commit selected in the graph, for new branches
+    selected_commit: Option<Oid>,
+    /// branch whose delete was refused as unmerged, waiting for a force
+    unmerged: Option<String>,
+    edit_job: Option<Job>,
+    error: Option<String>,
 }
 
 impl EventEmitter<BranchCheckedOut> for BranchPanel {}
+impl EventEmitter<BranchesChanged> for BranchPanel {}
 
 impl BranchPanel {
-    pub fn new(_repo: Rc<RefCell<Option<Repository>>>) -> Self {
+    pub fn new(_repo: Rc<RefCell<Option<Repository>>>, cx: &mut Context<Self>) -> Self {
+        let name = NameField::new("branch", validate_branch_name, cx);
         Self {
             repo_path: None,
             branches: Vec::new(),
             mode: RepoMode::Local,
-            pending_checkout_rx: None,
+            checkout_job: None,
             checking_out: None,
             loading: false,
+            name,
+            form: None,
+            selected_commit: None,
+            unmerged: None,
+            edit_job: None,
+            error: None,
         }
     }
 
     pub fn set_mode(&mut self, mode: RepoMode, cx: &mut Context<Self>) {
         self.mode = mode;
+        self.form = None;
+        self.unmerged = None;
         self.loading = true;
         self.branches.clear();
         cx.notify();
@@ -98,6 +141,13 @@ impl BranchPanel {
     }
 
     pub fn checkout(&mut self, name: &str, cx: &mut Context<Self>) {
+        let is_remote = self.branches.first().map(|b| b.is_remote).unwrap_or(false);
+        self.checkout_branch(name, is_remote, cx);
+    }
+
+    /// Check out `name` whether or not it is listed in the panel, e.g. from a
+    /// graph badge. Remote branches get a local branch created first.
+    pub fn checkout_branch(&mut self, name: &str, is_remote: bool, cx: &mut Context<Self>) {
         if self.checking_out.is_some() {
             return;
         }
@@ -106,8 +156,6 @@ impl BranchPanel {
             return;
         };
 
-        let is_remote = self.branches.first().map(|b| b.is_remote).unwrap_or(false);
-
         let branch_name = name.to_string();
         let local_name = if branch_name.contains('/') {
             branch_name
@@ -119,52 +167,165 @@ impl BranchPanel {
             branch_name.clone()
         };
 
-        let (tx, rx) = std::sync::mpsc::channel();
-        self.pending_checkout_rx = Some(rx);
         self.checking_out = Some(branch_name.clone());
-
-        std::thread::spawn(move || {
-            let result = if is_remote {
-                do_checkout_remote_bg(&repo_path, &branch_name)
-            } else {
-                do_checkout_bg(&repo_path, &branch_name)
-            };
-            let _ = tx.send(result.map(|_| CheckoutResult { local_name }));
-        });
+        self.checkout_job = Some(Job::spawn(
+            cx,
+            move || {
+                let result = if is_remote {
+                    do_checkout_remote_bg(&repo_path, &branch_name)
+                } else {
+                    do_checkout_bg(&repo_path, &branch_name)
+                };
+                result.map(|_| CheckoutResult { local_name })
+            },
+            Self::on_checkout_done,
+        ));
 
         cx.notify();
     }
 
-    fn poll_checkout(&mut self, cx: &mut Context<Self>) {
-        let Some(rx) = &self.pending_checkout_rx else {
-            return;
-        };
-        match rx.try_recv() {
-            Ok(Ok(result)) => {
-                self.pending_checkout_rx = None;
-                self.checking_out = None;
+    fn on_checkout_done(&mut self, result: Result<CheckoutResult, String>, cx: &mut Context<Self>) {
+        self.checkout_job = None;
+        self.checking_out = None;
+        match result {
+            Ok(result) => {
+                self.error = None;
                 cx.emit(BranchCheckedOut {
                     name: result.local_name,
-                });
-                cx.notify();
-            }
-            Ok(Err(msg)) => {
-                self.pending_checkout_rx = None;
-                self.checking_out = None;
-                eprintln!("checkout failed: {}", msg);
-                cx.notify();
-            }
-            Err(std::sync::mpsc::TryRecvError::Empty) => {
-                cx.notify();
-            }
-            Err(std::sync::mpsc::TryRecvError::Disconnected) => {
-                self.pending_checkout_rx = None;
-                self.checking_out = None;
-                cx.notify();
+                })
             }
+            Err(msg) => self.error = Some(format!("checkout failed: {}", msg)),
         }
     }
 
+    pub fn set_selected_commit(&mut self, oid: Option<Oid>, cx: &mut Context<Self>) {
+        self.selected_commit = oid;
+        cx.notify();
+    }
+
+    fn busy(&self) -> bool {
+        self.checkout_job.is_some() || self.edit_job.is_some()
+    }
+
+    /// Open the name input for a new branch at HEAD.
+    fn start_create(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.open_form(
+            BranchForm::Create {
+                at: None,
+                upstream: false,
+            },
+            "",
+            window,
+            cx,
+        );
+    }
+
+    fn start_rename(&mut self, name: &str, window: &mut Window, cx: &mut Context<Self>) {
+        let from = name.to_string();
+        self.open_form(BranchForm::Rename { from }, name, window, cx);
+    }
+
+    fn open_form(
+        &mut self,
+        form: BranchForm,
+        text: &str,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.form = Some(form);
+        self.unmerged = None;
+        self.error = None;
+        self.name.start(text, window, cx);
+        cx.notify();
+    }
+
+    fn close_form(&mut self, cx: &mut Context<Self>) {
+        self.form = None;
+        self.error = None;
+        cx.notify();
+    }
+
+    fn name_error(&self, cx: &App) -> Option<String> {
+        let renaming = match &self.form {
+            Some(BranchForm::Rename { from }) => Some(from.as_str()),
+            _ => None,
+        };
+        self.name.error(cx, |name| {
+            renaming != Some(name) && self.branches.iter().any(|b| !b.is_remote && b.name == name)
+        })
+    }
+
+    fn confirm_form(&mut self, cx: &mut Context<Self>) {
+        let (Some(form), Some(repo_path)) = (self.form.clone(), self.repo_path.clone()) else {
+            return;
+        };
+        // the form already shows why the name is refused
+        if self.busy() || self.name_error(cx).is_some() {
+            return;
+        }
+        let name = self.name.name(cx);
+        self.edit_job = Some(Job::spawn(
+            cx,
+            move || match form {
+                BranchForm::Create { at, upstream } => {
+                    create_branch_bg(&repo_path, &name, at, upstream)
+                }
+                BranchForm::Rename { from } => rename_branch_bg(&repo_path, &from, &name),
+            },
+            |this, result, cx| {
+                this.edit_job = None;
+                match result {
+                    Ok(()) => {
+                        this.form = None;
+                        this.error = None;
+                        cx.emit(BranchesChanged);
+                    }
+                    Err(msg) => this.error = Some(msg),
+                }
+            },
+        ));
+        cx.notify();
+    }
+
+    /// Delete `name`. Without `force` a branch that is not merged is kept
+    /// and the panel asks whether to force it.
+    fn delete(&mut self, name: &str, force: bool, cx: &mut Context<Self>) {
+        if self.busy() {
+            return;
+        }
+        let Some(repo_path) = self.repo_path.clone() else {
+            return;
+        };
+        let name = name.to_string();
+        self.form = None;
+        self.unmerged = None;
+        self.error = None;
+        self.edit_job = Some(Job::spawn(
+            cx,
+            {
+                let name = name.clone();
+                move || delete_branch_bg(&repo_path, &name, force)
+            },
+            move |this, result, cx| {
+                this.edit_job = None;
+                match result {
+                    Ok(DeleteOutcome::Deleted) => cx.emit(BranchesChanged),
+                    Ok(DeleteOutcome::Unmerged) => this.unmerged = Some(name),
+                    Err(msg) => this.error = Some(msg),
+                }
+            },
+        ));
+        cx.notify();
+    }
+
+    fn confirm(&mut self, _: &ConfirmBranchForm, _: &mut Window, cx: &mut Context<Self>) {
+        self.confirm_form(cx);
+    }
+
+    fn cancel(&mut self, _: &CancelBranchForm, _: &mut Window, cx: &mut Context<Self>) {
+        self.close_form(cx);
+    }
+
     pub fn current_branch(&self) -> Option<&str> {
         self.branches
             .iter()
@@ -177,6 +338,119 @@ impl BranchPanel {
     }
 }
 
+/// Why `name` cannot name a local branch, if it cannot.
+pub fn validate_branch_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("enter a branch name".to_string());
+    }
+    if name == "HEAD"
+        || name.starts_with('-')
+        || !Reference::is_valid_name(&format!("refs/heads/{}", name))
+    {
+        return Err(format!("'{}' is not a valid branch name", name));
+    }
+    Ok(())
+}
+
+/// Create the local branch `name` at `at`, or at HEAD, without checking it
+/// out. With `upstream` it tracks the branch of the same name on the
+/// default remote.
+pub fn create_branch_bg(
+    repo_path: &str,
+    name: &str,
+    at: Option<Oid>,
+    upstream: bool,
+) -> Result<(), String> {
+    validate_branch_name(name)?;
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    ensure_free(&repo, name)?;
+    let commit = match at {
+        Some(oid) => repo.find_commit(oid),
+        None => repo.head().and_then(|head| head.peel_to_commit()),
+    }
+    .map_err(|e| e.to_string())?;
+    repo.branch(name, &commit, false)
+        .map_err(|e| e.to_string())?;
+    if upstream {
+        set_upstream(&repo, name).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Rename the local branch `from` to `to`, carrying its reflog and config
+/// along; HEAD follows when it is the checked out branch.
+pub fn rename_branch_bg(repo_path: &str, from: &str, to: &str) -> Result<(), String> {
+    validate_branch_name(to)?;
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    if from == to {
+        return Ok(());
+    }
+    ensure_free(&repo, to)?;
+    let mut branch = repo
+        .find_branch(from, BranchType::Local)
+        .map_err(|e| e.to_string())?;
+    branch.rename(to, false).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Delete the local branch `name`. Unless `force` is set, a branch that
+/// neither HEAD nor its upstream contains is kept.
+pub fn delete_branch_bg(repo_path: &str, name: &str, force: bool) -> Result<DeleteOutcome, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let mut branch = repo
+        .find_branch(name, BranchType::Local)
+        .map_err(|e| e.to_string())?;
+    if branch.is_head() {
+        return Err(format!("'{}' is checked out", name));
+    }
+    if !force && !is_merged(&repo, &branch).map_err(|e| e.to_string())? {
+        return Ok(DeleteOutcome::Unmerged);
+    }
+    branch.delete().map_err(|e| e.to_string())?;
+    Ok(DeleteOutcome::Deleted)
+}
+
+fn ensure_free(repo: &Repository, name: &str) -> Result<(), String> {
+    match repo.find_branch(name, BranchType::Local) {
+        Ok(_) => Err(format!("a branch named '{}' already exists", name)),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Whether the tip of `branch` is in the history of HEAD or of its upstream.
+fn is_merged(repo: &Repository, branch: &Branch) -> Result<bool, git2::Error> {
+    let tip = branch.get().peel_to_commit()?.id();
+    let head = repo.head().and_then(|head| head.peel_to_commit()).ok();
+    let upstream = branch
+        .upstream()
+        .and_then(|upstream| upstream.get().peel_to_commit())
+        .ok();
+    for base in head.into_iter().chain(upstream).map(|commit| commit.id()) {
+        if base == tip || repo.graph_descendant_of(base, tip)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Track `name` on `origin`, or on the only other remote, the way
+/// `git push -u` would; the remote branch does not have to exist yet.
+fn set_upstream(repo: &Repository, name: &str) -> Result<(), git2::Error> {
+    let remotes = repo.remotes()?;
+    let remote = remotes
+        .iter()
+        .flatten()
+        .find(|remote| *remote == "origin")
+        .or_else(|| remotes.iter().flatten().next())
+        .ok_or_else(|| git2::Error::from_str("there is no remote to track"))?;
+    let mut config = repo.config()?;
+    config.set_str(&format!("branch.{}.remote", name), remote)?;
+    config.set_str(
+        &format!("branch.{}.merge", name),
+        &format!("refs/heads/{}", name),
+    )
+}
+
 fn do_checkout_bg(repo_path: &str, name: &str) -> Result<(), String> {
     let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
     let refname = format!("refs/heads/{}", name);
@@ -203,12 +477,134 @@ fn do_checkout_remote_bg(repo_path: &str, remote_name: &str) -> Result<(), Strin
 
 impl Render for BranchPanel {
     fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        self.poll_checkout(cx);
         self.render_panel(cx)
     }
 }
 
 impl BranchPanel {
+    fn render_form(&self, form: &BranchForm, cx: &mut Context<Self>) -> AnyElement {
+        let name_error = self.name_error(cx);
+        let enabled = !self.busy() && name_error.is_none();
+        let (title, action) = match form {
+            BranchForm::Create { at: None, .. } => ("New branch at HEAD".to_string(), "Create"),
+            BranchForm::Create { at: Some(oid), .. } => {
+                (format!("New branch at {:.7}", oid.to_string()), "Create")
+            }
+            BranchForm::Rename { from } => (format!("Rename '{}'", from), "Rename"),
+        };
+        let selected = self.selected_commit;
+
+        let options = match form {
+            BranchForm::Create { at, upstream } => {
+                let (at, upstream) = (*at, *upstream);
+                div()
+                    .flex()
+                    .flex_row()
+                    .gap_1()
+                    .when_some(selected, |el, oid| {
+                        let label = if at.is_some() {
+                            "[x] at selected"
+                        } else {
+                            "[ ] at selected"
+                        };
+                        el.child(widgets::button(
+                            "branch_at_selected",
+                            label,
+                            true,
+                            cx.listener(move |this, _event, _window, cx| {
+                                if let Some(BranchForm::Create { at, .. }) = &mut this.form {
+                                    *at = if at.is_some() { None } else { Some(oid) };
+                                }
+                                cx.notify();
+                            }),
+                        ))
+                    })
+                    .child(widgets::button(
+                        "branch_upstream",
+                        if upstream {
+                            "[x] set upstream"
+                        } else {
+                            "[ ] set upstream"
+                        },
+                        true,
+                        cx.listener(|this, _event, _window, cx| {
+                            if let Some(BranchForm::Create { upstream, .. }) = &mut this.form {
+                                *upstream = !*upstream;
+                            }
+                            cx.notify();
+                        }),
+                    ))
+                    .into_any()
+            }
+            BranchForm::Rename { .. } => div().into_any(),
+        };
+
+        div()
+            .w_full()
+            .px(px(10.0))
+            .py(px(4.0))
+            .flex()
+            .flex_col()
+            .gap_1()
+            .border_b_1()
+            .border_color(gpui::rgb(COLOR_BORDER))
+            .key_context("BranchForm")
+            .on_action(cx.listener(Self::confirm))
+            .on_action(cx.listener(Self::cancel))
+            .child(
+                div()
+                    .text_color(gpui::rgb(COLOR_DIM_TEXT))
+                    .text_size(px(10.0))
+                    .font_family("monospace")
+                    .child(title),
+            )
+            .child(self.name.render(name_error, cx))
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .items_center()
+                    .justify_between()
+                    .child(options)
+                    .child(
+                        div()
+                            .flex()
+                            .flex_row()
+                            .gap_1()
+                            .child(widgets::button(
+                                "branch_form_confirm",
+                                action,
+                                enabled,
+                                cx.listener(|this, _event, _window, cx| this.confirm_form(cx)),
+                            ))
+                            .child(widgets::button(
+                                "branch_form_cancel",
+                                "Cancel",
+                                true,
+                                cx.listener(|this, _event, _window, cx| this.close_form(cx)),
+                            )),
+                    ),
+            )
+            .into_any()
+    }
+
+    /// Prompt shown after a delete was refused because the branch is not
+    /// merged.
+    fn render_unmerged(&self, name: &str, cx: &mut Context<Self>) -> AnyElement {
+        let force_name = name.to_string();
+        widgets::confirm_prompt(
+            "branch_force_delete",
+            format!("'{}' is not fully merged", name),
+            "Delete anyway",
+            !self.busy(),
+            cx.listener(move |this, _event, _window, cx| this.delete(&force_name, true, cx)),
+            cx.listener(|this, _event, _window, cx| {
+                this.unmerged = None;
+                cx.notify();
+            }),
+        )
+    }
+
     fn render_panel(&self, cx: &mut Context<Self>) -> AnyElement {
         let has_repo = self.repo_path.is_some();
 
@@ -239,6 +635,7 @@ impl BranchPanel {
         let checking = self.checking_out.clone();
         let has_checkout = checking.is_some();
         let is_loading = self.loading;
+        let can_create = self.mode == RepoMode::Local && self.form.is_none();
 
         div()
             .size_full()
@@ -264,7 +661,7 @@ impl BranchPanel {
                     .when_some(checking, |el, name| {
                         el.child(
                             div()
-                                .text_color(gpui::rgb(COLOR_CHECKING_TEXT))
+                                .text_color(gpui::rgb(COLOR_WARNING_TEXT))
                                 .text_size(px(9.0))
                                 .font_family("monospace")
                                 .child(format!("⏳ {}", name)),
@@ -273,13 +670,30 @@ impl BranchPanel {
                     .when(is_loading && !has_checkout, |el| {
                         el.child(
                             div()
-                                .text_color(gpui::rgb(COLOR_LOADING_TEXT))
+                                .text_color(gpui::rgb(COLOR_DIM_TEXT))
                                 .text_size(px(9.0))
                                 .font_family("monospace")
                                 .child("⏳ loading..."),
                         )
+                    })
+                    .when(can_create, |el| {
+                        el.child(widgets::button(
+                            "branch_new",
+                            "New",
+                            !self.busy(),
+                            cx.listener(|this, _event, window, cx| this.start_create(window, cx)),
+                        ))
                     }),
             )
+            .when_some(self.form.clone(), |el, form| {
+                el.child(self.render_form(&form, cx))
+            })
+            .when_some(self.unmerged.clone(), |el, name| {
+                el.child(self.render_unmerged(&name, cx))
+            })
+            .when_some(self.error.clone(), |el, msg| {
+                el.child(widgets::error_row(msg))
+            })
             .child(
                 div()
                     .id("branch_list")
@@ -292,7 +706,7 @@ impl BranchPanel {
                                 .items_center()
                                 .justify_center()
                                 .py(px(12.0))
-                                .text_color(gpui::rgb(COLOR_LOADING_TEXT))
+                                .text_color(gpui::rgb(COLOR_DIM_TEXT))
                                 .text_size(px(11.0))
                                 .font_family("monospace")
                                 .child("Loading branches..."),
@@ -302,7 +716,7 @@ impl BranchPanel {
                         let name = b.name.clone();
                         let is_head = b.is_head;
                         let is_remote = b.is_remote;
-                        let is_busy = self.checking_out.is_some();
+                        let is_busy = self.busy();
 
                         let bg = if is_head {
                             gpui::rgb(COLOR_HEAD_BG)
@@ -337,11 +751,34 @@ impl BranchPanel {
                             .gap_2()
                             .child(
                                 div()
+                                    .flex_1()
                                     .text_color(text_color)
                                     .text_size(px(12.0))
                                     .font_family("monospace")
                                     .child(label),
                             )
+                            .when(!is_remote, |el| {
+                                let rename_name = name.clone();
+                                let delete_name = name.clone();
+                                el.child(widgets::button(
+                                    SharedString::from(format!("rename-{}", name)),
+                                    "rename",
+                                    !is_busy,
+                                    cx.listener(move |this, _event, window, cx| {
+                                        this.start_rename(&rename_name, window, cx)
+                                    }),
+                                ))
+                                .when(!is_head, |el| {
+                                    el.child(widgets::button(
+                                        SharedString::from(format!("delete-{}", name)),
+                                        "delete",
+                                        !is_busy,
+                                        cx.listener(move |this, _event, _window, cx| {
+                                            this.delete(&delete_name, false, cx)
+                                        }),
+                                    ))
+                                })
+                            })
                             .on_mouse_down(
                                 MouseButton::Left,
                                 cx.listener(move |this, _event, _window, cx| {
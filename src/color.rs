@@ -1,38 +1,70 @@
 use std::collections::HashMap;
 
+use git2::Oid;
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Picks palette slots by branch identity instead of by draw order, so a
+/// branch keeps its color across rows, pages and refreshes.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ColorManager {
-    count_color: usize,
-    map_color: HashMap<usize, usize>,
-    colors: Vec<u32>,
+    palette_len: usize,
+    // color promised to a commit by the child whose first parent it is, and
+    // whether that child's chain started at a named branch
+    reserved: HashMap<Oid, (usize, bool)>,
 }
 
 impl ColorManager {
-    pub fn new(colors: Vec<u32>) -> Self {
+    pub fn new(palette_len: usize) -> Self {
         ColorManager {
-            count_color: 0,
-            map_color: HashMap::new(),
-            colors,
+            palette_len: palette_len.max(1),
+            reserved: HashMap::new(),
         }
     }
 
-    pub fn get_color(&mut self, lane: &usize) -> usize {
-        self.count_color += 1;
-        let color = match self.map_color.get(lane) {
-            Some(color) => *color,
-            _ => {
-                if self.count_color < self.colors.len() {
-                    self.count_color
-                } else {
-                    self.count_color = 0;
-                    self.count_color
-                }
-            }
+    /// Stable slot for a branch name, the same on every run and platform.
+    pub fn color_for_name(&self, name: &str) -> usize {
+        self.slot(name.as_bytes())
+    }
+
+    /// Color of `oid`. A branch tip takes the slot of its name; any other
+    /// commit continues the color of the child whose first parent it is, and
+    /// a chain with neither starts from the hash of its newest commit.
+    ///
+    /// When several children share a first parent, a named chain wins over an
+    /// unnamed one and otherwise the first child in walk order keeps it.
+    pub fn get_color(&mut self, oid: &Oid, parents: &[Oid], branch: Option<&str>) -> usize {
+        let inherited = self.reserved.remove(oid);
+        let (color, named) = match (branch, inherited) {
+            (Some(name), _) => (self.color_for_name(name), true),
+            (None, Some(reserved)) => reserved,
+            (None, None) => (self.slot(oid.as_bytes()), false),
         };
-        self.map_color.insert(*lane, color);
+        if let Some(first) = parents.first() {
+            self.reserve(*first, color, named);
+        }
         color
     }
 
-    pub fn remove_lane_color(&mut self, lane: &usize) {
-        self.map_color.remove(lane);
+    /// Pass the color promised to a commit that is not drawn on to its first parent.
+    pub fn pass_through(&mut self, oid: &Oid, parents: &[Oid]) {
+        if let (Some((color, named)), Some(first)) = (self.reserved.remove(oid), parents.first()) {
+            self.reserve(*first, color, named);
+        }
+    }
+
+    fn reserve(&mut self, oid: Oid, color: usize, named: bool) {
+        let slot = self.reserved.entry(oid).or_insert((color, named));
+        if named && !slot.1 {
+            *slot = (color, named);
+        }
+    }
+
+    fn slot(&self, bytes: &[u8]) -> usize {
+        let hash = bytes.iter().fold(FNV_OFFSET, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+        });
+        (hash % self.palette_len as u64) as usize
     }
 }
@@ -0,0 +1,167 @@
+use chrono::{DateTime, FixedOffset, Local, TimeZone, Utc};
+
+const MINUTE: i64 = 60;
+const HOUR: i64 = 60 * MINUTE;
+const DAY: i64 = 24 * HOUR;
+const WEEK: i64 = 7 * DAY;
+const MONTH: i64 = 30 * DAY;
+const YEAR: i64 = 365 * DAY;
+
+pub const MIN_COLUMN_WIDTH: f32 = 40.0;
+pub const MAX_COLUMN_WIDTH: f32 = 400.0;
+const ABSOLUTE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// Extra columns drawn to the right of the commit message, left to right.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Column {
+    Author,
+    Date,
+    Sha,
+}
+
+impl Column {
+    pub const ALL: [Column; 3] = [Column::Author, Column::Date, Column::Sha];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Column::Author => "Author",
+            Column::Date => "Date",
+            Column::Sha => "SHA",
+        }
+    }
+}
+
+/// How the date column shows a commit time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DateStyle {
+    /// "3h ago"
+    #[default]
+    Relative,
+    /// absolute, in the timezone of this machine
+    Local,
+    /// absolute, in the timezone the commit was made in
+    Commit,
+}
+
+impl DateStyle {
+    pub fn next(self) -> Self {
+        match self {
+            DateStyle::Relative => DateStyle::Local,
+            DateStyle::Local => DateStyle::Commit,
+            DateStyle::Commit => DateStyle::Relative,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            DateStyle::Relative => "relative",
+            DateStyle::Local => "local",
+            DateStyle::Commit => "commit tz",
+        }
+    }
+
+    /// `time` in this style; relative dates count from `now`.
+    pub fn format(&self, time: git2::Time, now: DateTime<Utc>) -> String {
+        match self {
+            DateStyle::Relative => relative(now.timestamp() - time.seconds()),
+            DateStyle::Local => Local
+                .timestamp_opt(time.seconds(), 0)
+                .single()
+                .map(|date| date.format(ABSOLUTE_FORMAT).to_string())
+                .unwrap_or_default(),
+            DateStyle::Commit => FixedOffset::east_opt(time.offset_minutes() * 60)
+                .and_then(|zone| zone.timestamp_opt(time.seconds(), 0).single())
+                .map(|date| date.format(ABSOLUTE_FORMAT).to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Short age such as "5m ago" or "2y ago". Times in the future, e.g. from
+/// a skewed clock, read as "just now".
+pub fn relative(seconds: i64) -> String {
+    let (count, unit) = match seconds {
+        s if s < MINUTE => return "just now".to_string(),
+        s if s < HOUR => (s / MINUTE, "m"),
+        s if s < DAY => (s / HOUR, "h"),
+        s if s < WEEK => (s / DAY, "d"),
+        s if s < MONTH => (s / WEEK, "w"),
+        s if s < YEAR => (s / MONTH, "mo"),
+        s => (s / YEAR, "y"),
+    };
+    format!("{}{} ago", count, unit)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColumnState {
+    pub visible: bool,
+    pub width: f32,
+}
+
+impl ColumnState {
+    /// Columns start hidden: the graph shares a narrow sidebar, and every
+    /// column takes its width from the message on each row.
+    fn new(width: f32) -> Self {
+        Self {
+            visible: false,
+            width,
+        }
+    }
+}
+
+/// Visibility and width of the graph columns.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Columns {
+    pub author: ColumnState,
+    pub date: ColumnState,
+    pub sha: ColumnState,
+    pub date_style: DateStyle,
+}
+
+impl Default for Columns {
+    fn default() -> Self {
+        Self {
+            author: ColumnState::new(120.0),
+            date: ColumnState::new(100.0),
+            sha: ColumnState::new(60.0),
+            date_style: DateStyle::default(),
+        }
+    }
+}
+
+impl Columns {
+    pub fn get(&self, column: Column) -> ColumnState {
+        match column {
+            Column::Author => self.author,
+            Column::Date => self.date,
+            Column::Sha => self.sha,
+        }
+    }
+
+    fn get_mut(&mut self, column: Column) -> &mut ColumnState {
+        match column {
+            Column::Author => &mut self.author,
+            Column::Date => &mut self.date,
+            Column::Sha => &mut self.sha,
+        }
+    }
+
+    pub fn toggle(&mut self, column: Column) {
+        let state = self.get_mut(column);
+        state.visible = !state.visible;
+    }
+
+    pub fn set_width(&mut self, column: Column, width: f32) {
+        self.get_mut(column).width = width.clamp(MIN_COLUMN_WIDTH, MAX_COLUMN_WIDTH);
+    }
+
+    /// Visible columns, left to right.
+    pub fn visible(&self) -> impl Iterator<Item = Column> + '_ {
+        Column::ALL.into_iter().filter(|c| self.get(*c).visible)
+    }
+
+    /// Room the visible columns take at the right of each row.
+    pub fn total_width(&self) -> f32 {
+        self.visible().map(|c| self.get(c).width).sum()
+    }
+}
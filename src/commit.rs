@@ -1,14 +1,24 @@
 use git2::{Oid, Time};
-use gpui::{Pixels, Point};
+
+use crate::layout::GraphCell;
+use crate::refs::RefLabel;
+
 #[derive(Debug, Clone)]
 pub struct CommitNode {
     pub oid: Oid,
     pub message: String,
+    /// display name, or the email when the commit has no name
     pub author: String,
+    pub email: String,
     pub timestamp: Time,
     pub parents: Vec<Oid>,
-    pub position: Point<Pixels>,
+    pub cell: GraphCell,
     pub color: usize,
+    pub refs: Vec<RefLabel>,
+    /// `false` when the active search does not match and the row is dimmed.
+    pub matched: bool,
+    /// Index in the stash list when this row is a stash rather than a commit.
+    pub stash: Option<usize>,
 }
 
 impl CommitNode {
@@ -18,17 +28,41 @@ impl CommitNode {
         author: String,
         timestamp: Time,
         parents: Vec<Oid>,
-        position: Point<Pixels>,
+        cell: GraphCell,
         color: usize,
     ) -> Self {
         CommitNode {
             oid,
             message,
             author,
+            email: String::new(),
             timestamp,
             parents,
-            position,
+            cell,
             color,
+            refs: Vec::new(),
+            matched: true,
+            stash: None,
         }
     }
+
+    pub fn with_refs(mut self, refs: Vec<RefLabel>) -> Self {
+        self.refs = refs;
+        self
+    }
+
+    pub fn with_email(mut self, email: String) -> Self {
+        self.email = email;
+        self
+    }
+
+    pub fn with_matched(mut self, matched: bool) -> Self {
+        self.matched = matched;
+        self
+    }
+
+    pub fn with_stash(mut self, index: Option<usize>) -> Self {
+        self.stash = index;
+        self
+    }
 }
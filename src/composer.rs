@@ -0,0 +1,516 @@
+use std::path::Path;
+
+use git2::{Commit, Oid, Repository, RepositoryState};
+use gpui::prelude::*;
+use gpui::{
+    AnyElement, App, Context, Entity, EventEmitter, FocusHandle, Focusable, InteractiveElement,
+    IntoElement, MouseButton, ParentElement, Render, SharedString, StatefulInteractiveElement,
+    Styled, Window, actions, div, px,
+};
+
+use crate::details;
+use crate::job::Job;
+use crate::text_input::TextInput;
+use crate::widgets::{
+    COLOR_BG, COLOR_BORDER, COLOR_BUSY_TEXT, COLOR_BUTTON_BG, COLOR_BUTTON_HOVER_BG,
+    COLOR_DIM_TEXT, COLOR_ERROR_TEXT, COLOR_HEADING_BG, COLOR_INPUT_BG, COLOR_TEXT,
+    COLOR_WARNING_TEXT,
+};
+
+actions!(composer, [CommitStaged]);
+
+/// Subjects past this length start to get cut off in one line logs.
+pub const SUBJECT_SOFT_LIMIT: usize = 50;
+/// Subjects should never be longer than this.
+pub const SUBJECT_HARD_LIMIT: usize = 72;
+
+const COLOR_COMMIT_BG: u32 = 0x2A3A5A;
+const COLOR_COMMIT_HOVER_BG: u32 = 0x3A5A7A;
+const COLOR_COMMIT_TEXT: u32 = 0x4A90D9;
+
+/// How the first line of a message measures up to the 50/72 guide.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubjectLength {
+    Fits,
+    Long,
+    TooLong,
+}
+
+/// Characters in the subject line of `message`, and how that compares to
+/// the limits.
+pub fn subject_length(message: &str) -> (usize, SubjectLength) {
+    let count = message.lines().next().unwrap_or("").chars().count();
+    let length = if count > SUBJECT_HARD_LIMIT {
+        SubjectLength::TooLong
+    } else if count > SUBJECT_SOFT_LIMIT {
+        SubjectLength::Long
+    } else {
+        SubjectLength::Fits
+    };
+    (count, length)
+}
+
+/// `message` with a `Signed-off-by` trailer for `name` and `email` at the
+/// end, joining the trailer block when there already is one. A message that
+/// is already signed off by them is left as it is.
+pub fn sign_off(message: &str, name: &str, email: &str) -> String {
+    let message = message.trim_end();
+    let trailer = format!("Signed-off-by: {} <{}>", name, email);
+    if message.lines().any(|line| line.trim() == trailer) {
+        return message.to_string();
+    }
+    let body = message.split_once('\n').map(|(_, body)| body).unwrap_or("");
+    let (_, trailers) = details::split_trailers(body);
+    let separator = if trailers.is_empty() { "\n\n" } else { "\n" };
+    format!("{}{}{}", message, separator, trailer)
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CommitOptions {
+    /// replace HEAD instead of committing on top of it
+    pub amend: bool,
+    /// add a `Signed-off-by` trailer for the committer
+    pub sign_off: bool,
+}
+
+/// Hooks `git commit` runs that can check or rewrite the commit.
+const COMMIT_HOOKS: [&str; 3] = ["pre-commit", "prepare-commit-msg", "commit-msg"];
+
+/// Why `git commit` would do more than [`commit_bg`] can: sign the commit or
+/// run a hook. `None` when committing here gives the same commit.
+fn unsupported_setup(repo: &Repository) -> Option<String> {
+    let config = repo.config().ok()?;
+    if config.get_bool("commit.gpgsign").unwrap_or(false) {
+        return Some("commit.gpgsign is set, commit from the command line to sign".to_string());
+    }
+    // a relative core.hooksPath is taken from the top of the work tree
+    let hooks = match config.get_path("core.hooksPath") {
+        Ok(path) => repo.workdir().unwrap_or(repo.path()).join(path),
+        Err(_) => repo.commondir().join("hooks"),
+    };
+    let hook = COMMIT_HOOKS
+        .into_iter()
+        .find(|name| is_executable(&hooks.join(name)))?;
+    Some(format!(
+        "the {} hook would not run, commit from the command line",
+        hook
+    ))
+}
+
+/// Git only runs hooks that are executable files.
+fn is_executable(path: &Path) -> bool {
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        metadata.is_file()
+    }
+}
+
+/// Commit the index on top of HEAD, or in place of it when amending, and
+/// move the checked out branch to the new commit. The committer, and the
+/// author of a new commit, come from `user.name` and `user.email` in git
+/// config; an amended commit keeps its author.
+///
+/// The commit is written with git2, which neither runs hooks nor signs, so
+/// a repository with `commit.gpgsign` set or an executable `pre-commit`,
+/// `prepare-commit-msg` or `commit-msg` hook is refused rather than given
+/// a commit that skipped them.
+pub fn commit_bg(repo_path: &str, message: &str, options: CommitOptions) -> Result<Oid, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    if repo.state() != RepositoryState::Clean {
+        return Err("finish the merge or rebase in progress first".to_string());
+    }
+    if let Some(reason) = unsupported_setup(&repo) {
+        return Err(reason);
+    }
+    if message.trim().is_empty() {
+        return Err("the commit message is empty".to_string());
+    }
+    let signature = repo
+        .signature()
+        .map_err(|_| "set user.name and user.email in git config".to_string())?;
+    let message = if options.sign_off {
+        sign_off(
+            message,
+            signature.name().unwrap_or_default(),
+            signature.email().unwrap_or_default(),
+        )
+    } else {
+        message.to_string()
+    };
+    let message = git2::message_prettify(message, None).map_err(|e| e.to_string())?;
+
+    let mut index = repo.index().map_err(|e| e.to_string())?;
+    if index.has_conflicts() {
+        return Err("resolve the conflicts before committing".to_string());
+    }
+    let tree_id = index.write_tree().map_err(|e| e.to_string())?;
+    let tree = repo.find_tree(tree_id).map_err(|e| e.to_string())?;
+    let head = repo.head().and_then(|head| head.peel_to_commit()).ok();
+
+    if options.amend {
+        let head = head.ok_or_else(|| "there is no commit to amend".to_string())?;
+        return head
+            .amend(
+                Some("HEAD"),
+                None,
+                Some(&signature),
+                None,
+                Some(&message),
+                Some(&tree),
+            )
+            .map_err(|e| e.to_string());
+    }
+    if head.as_ref().is_some_and(|head| head.tree_id() == tree_id) {
+        return Err("nothing staged to commit".to_string());
+    }
+    let parents: Vec<&Commit> = head.iter().collect();
+    repo.commit(
+        Some("HEAD"),
+        &signature,
+        &signature,
+        &message,
+        &tree,
+        &parents,
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// `Name <email>` as commits will be signed, from git config.
+pub fn identity_bg(repo_path: &str) -> Result<String, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let signature = repo
+        .signature()
+        .map_err(|_| "set user.name and user.email in git config".to_string())?;
+    Ok(format!(
+        "{} <{}>",
+        signature.name().unwrap_or_default(),
+        signature.email().unwrap_or_default()
+    ))
+}
+
+/// Message of HEAD, to start an amend from.
+pub fn head_message_bg(repo_path: &str) -> Result<String, String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let head = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .map_err(|e| e.to_string())?;
+    Ok(head.message().unwrap_or_default().trim_end().to_string())
+}
+
+/// A commit was made; `oid` is already what HEAD points at.
+#[derive(Clone, Debug)]
+pub struct CommitCreated {
+    pub oid: Oid,
+}
+
+pub struct CommitComposer {
+    repo_path: Option<String>,
+    message: Entity<TextInput>,
+    focus_handle: FocusHandle,
+    amend: bool,
+    sign_off: bool,
+    /// who commits will be signed as, or why that is not set up
+    identity: Option<Result<String, String>>,
+    identity_job: Option<Job>,
+    amend_job: Option<Job>,
+    commit_job: Option<Job>,
+    error: Option<String>,
+}
+
+impl EventEmitter<CommitCreated> for CommitComposer {}
+
+impl CommitComposer {
+    pub fn new(cx: &mut Context<Self>) -> Self {
+        let message = cx.new(|cx| TextInput::multi_line("Commit message", cx));
+        cx.observe(&message, |_, _, cx| cx.notify()).detach();
+        Self {
+            repo_path: None,
+            message,
+            focus_handle: cx.focus_handle(),
+            amend: false,
+            sign_off: false,
+            identity: None,
+            identity_job: None,
+            amend_job: None,
+            commit_job: None,
+            error: None,
+        }
+    }
+
+    /// Point the composer at `path`, looking up the identity again when the
+    /// repository changed.
+    pub fn set_repo_path(&mut self, path: String, cx: &mut Context<Self>) {
+        if self.repo_path.as_deref() == Some(path.as_str()) {
+            return;
+        }
+        self.repo_path = Some(path.clone());
+        self.identity = None;
+        self.error = None;
+        self.identity_job = Some(Job::spawn(
+            cx,
+            move || identity_bg(&path),
+            |this, result, _cx| {
+                this.identity_job = None;
+                this.identity = Some(result);
+            },
+        ));
+        cx.notify();
+    }
+
+    fn toggle_amend(&mut self, cx: &mut Context<Self>) {
+        self.amend = !self.amend;
+        self.error = None;
+        // start from the message being amended unless one was typed already
+        if self.amend
+            && self.message.read(cx).is_empty()
+            && let Some(repo_path) = self.repo_path.clone()
+        {
+            self.amend_job = Some(Job::spawn(
+                cx,
+                move || head_message_bg(&repo_path),
+                Self::on_head_message,
+            ));
+        }
+        cx.notify();
+    }
+
+    fn on_head_message(&mut self, result: Result<String, String>, cx: &mut Context<Self>) {
+        self.amend_job = None;
+        match result {
+            Ok(text) if self.amend && self.message.read(cx).is_empty() => {
+                self.message
+                    .update(cx, |input, cx| input.set_text(&text, cx));
+            }
+            Ok(_) => {}
+            Err(msg) => self.error = Some(msg),
+        }
+    }
+
+    fn toggle_sign_off(&mut self, cx: &mut Context<Self>) {
+        self.sign_off = !self.sign_off;
+        cx.notify();
+    }
+
+    /// Commit the index in the background; `CommitCreated` follows.
+    pub fn commit(&mut self, cx: &mut Context<Self>) {
+        if self.commit_job.is_some() {
+            return;
+        }
+        let Some(repo_path) = self.repo_path.clone() else {
+            return;
+        };
+        let message = self.message.read(cx).text().to_string();
+        let options = CommitOptions {
+            amend: self.amend,
+            sign_off: self.sign_off,
+        };
+        self.error = None;
+        self.commit_job = Some(Job::spawn(
+            cx,
+            move || commit_bg(&repo_path, &message, options),
+            Self::on_commit_done,
+        ));
+        cx.notify();
+    }
+
+    fn on_commit_done(&mut self, result: Result<Oid, String>, cx: &mut Context<Self>) {
+        self.commit_job = None;
+        match result {
+            Ok(oid) => {
+                self.message.update(cx, |input, cx| input.clear(cx));
+                self.amend = false;
+                cx.emit(CommitCreated { oid });
+            }
+            Err(msg) => self.error = Some(msg),
+        }
+    }
+
+    fn commit_staged(&mut self, _: &CommitStaged, _: &mut Window, cx: &mut Context<Self>) {
+        self.commit(cx);
+    }
+}
+
+impl Focusable for CommitComposer {
+    fn focus_handle(&self, _: &App) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Render for CommitComposer {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .size_full()
+            .key_context("CommitComposer")
+            .track_focus(&self.focus_handle)
+            .on_action(cx.listener(Self::commit_staged))
+            .child(self.render_panel(cx))
+    }
+}
+
+impl CommitComposer {
+    fn render_toggle(
+        id: &'static str,
+        label: &'static str,
+        on: bool,
+        on_click: impl Fn(&gpui::MouseDownEvent, &mut Window, &mut App) + 'static,
+    ) -> impl IntoElement {
+        div()
+            .id(id)
+            .px(px(6.0))
+            .py(px(1.0))
+            .rounded(px(3.0))
+            .bg(gpui::rgb(COLOR_BUTTON_BG))
+            .hover(|s| s.bg(gpui::rgb(COLOR_BUTTON_HOVER_BG)))
+            .cursor_pointer()
+            .text_color(gpui::rgb(if on { COLOR_TEXT } else { COLOR_DIM_TEXT }))
+            .text_size(px(10.0))
+            .font_family("monospace")
+            .child(format!("[{}] {}", if on { "x" } else { " " }, label))
+            .on_mouse_down(MouseButton::Left, on_click)
+    }
+
+    fn render_panel(&self, cx: &mut Context<Self>) -> AnyElement {
+        let text = self.message.read(cx).text().to_string();
+        let (count, length) = subject_length(&text);
+        let length_color = match length {
+            SubjectLength::Fits => COLOR_DIM_TEXT,
+            SubjectLength::Long => COLOR_WARNING_TEXT,
+            SubjectLength::TooLong => COLOR_ERROR_TEXT,
+        };
+        let busy = self.commit_job.is_some() || self.repo_path.is_none();
+        let can_commit = !busy && !text.trim().is_empty();
+        let label = match (self.commit_job.is_some(), self.amend) {
+            (true, _) => "Committing...",
+            (false, true) => "Amend",
+            (false, false) => "Commit",
+        };
+        let identity: SharedString = match &self.identity {
+            Some(Ok(identity)) => identity.clone().into(),
+            Some(Err(msg)) => msg.clone().into(),
+            None => "".into(),
+        };
+        let identity_color = match &self.identity {
+            Some(Err(_)) => COLOR_ERROR_TEXT,
+            _ => COLOR_DIM_TEXT,
+        };
+
+        div()
+            .size_full()
+            .flex()
+            .flex_col()
+            .bg(gpui::rgb(COLOR_BG))
+            .child(
+                div()
+                    .w_full()
+                    .px(px(10.0))
+                    .py(px(6.0))
+                    .border_b_1()
+                    .border_color(gpui::rgb(COLOR_BORDER))
+                    .bg(gpui::rgb(COLOR_HEADING_BG))
+                    .text_color(gpui::rgb(COLOR_TEXT))
+                    .font_weight(gpui::FontWeight::BOLD)
+                    .text_size(px(12.0))
+                    .flex()
+                    .flex_row()
+                    .items_center()
+                    .justify_between()
+                    .child("Commit")
+                    .child(
+                        div()
+                            .text_color(gpui::rgb(length_color))
+                            .text_size(px(10.0))
+                            .font_family("monospace")
+                            .font_weight(gpui::FontWeight::NORMAL)
+                            .child(format!("subject {}/{}", count, SUBJECT_SOFT_LIMIT)),
+                    ),
+            )
+            .child(
+                div()
+                    .id("commit_message")
+                    .flex_1()
+                    .mx(px(6.0))
+                    .mt(px(6.0))
+                    .px(px(6.0))
+                    .py(px(4.0))
+                    .bg(gpui::rgb(COLOR_INPUT_BG))
+                    .border_1()
+                    .border_color(gpui::rgb(COLOR_BORDER))
+                    .rounded(px(4.0))
+                    .overflow_y_scroll()
+                    .text_size(px(11.0))
+                    .font_family("monospace")
+                    .child(self.message.clone()),
+            )
+            .child(
+                div()
+                    .w_full()
+                    .px(px(6.0))
+                    .py(px(4.0))
+                    .flex()
+                    .flex_row()
+                    .items_center()
+                    .gap_1()
+                    .child(Self::render_toggle(
+                        "toggle_amend",
+                        "Amend",
+                        self.amend,
+                        cx.listener(|this, _event, _window, cx| this.toggle_amend(cx)),
+                    ))
+                    .child(Self::render_toggle(
+                        "toggle_sign_off",
+                        "Sign off",
+                        self.sign_off,
+                        cx.listener(|this, _event, _window, cx| this.toggle_sign_off(cx)),
+                    ))
+                    .child(div().flex_1())
+                    .child(
+                        div()
+                            .id("btn_commit")
+                            .px(px(8.0))
+                            .py(px(1.0))
+                            .rounded(px(3.0))
+                            .bg(gpui::rgb(COLOR_COMMIT_BG))
+                            .text_size(px(10.0))
+                            .font_weight(gpui::FontWeight::BOLD)
+                            .when(can_commit, |el| {
+                                el.hover(|s| s.bg(gpui::rgb(COLOR_COMMIT_HOVER_BG)))
+                                    .cursor_pointer()
+                                    .text_color(gpui::rgb(COLOR_COMMIT_TEXT))
+                                    .on_mouse_down(
+                                        MouseButton::Left,
+                                        cx.listener(|this, _event, _window, cx| this.commit(cx)),
+                                    )
+                            })
+                            .when(!can_commit, |el| el.text_color(gpui::rgb(COLOR_BUSY_TEXT)))
+                            .child(label),
+                    ),
+            )
+            .child(
+                div()
+                    .w_full()
+                    .px(px(10.0))
+                    .pb(px(4.0))
+                    .text_size(px(10.0))
+                    .font_family("monospace")
+                    .overflow_hidden()
+                    .whitespace_nowrap()
+                    .text_ellipsis()
+                    .map(|el| match &self.error {
+                        Some(msg) => el
+                            .text_color(gpui::rgb(COLOR_ERROR_TEXT))
+                            .child(msg.clone()),
+                        None => el.text_color(gpui::rgb(identity_color)).child(identity),
+                    }),
+            )
+            .into_any()
+    }
+}
@@ -0,0 +1,111 @@
+use chrono::{FixedOffset, TimeZone};
+use git2::{Oid, Repository};
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S %z";
+
+/// Author or committer of a commit.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Identity {
+    pub name: String,
+    pub email: String,
+    pub time: git2::Time,
+}
+
+impl Identity {
+    fn of(signature: &git2::Signature) -> Self {
+        Self {
+            name: signature.name().unwrap_or_default().to_string(),
+            email: signature.email().unwrap_or_default().to_string(),
+            time: signature.when(),
+        }
+    }
+
+    /// When, in the timezone the identity recorded.
+    pub fn when(&self) -> String {
+        format_time(self.time)
+    }
+}
+
+/// `Key: value` line from the end of a commit message, e.g. `Signed-off-by`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Trailer {
+    pub key: String,
+    pub value: String,
+}
+
+/// Everything the details header shows about one commit.
+#[derive(Clone, Debug)]
+pub struct CommitDetails {
+    pub oid: Oid,
+    pub summary: String,
+    /// the message after the summary, without the trailers
+    pub body: String,
+    pub author: Identity,
+    pub committer: Identity,
+    pub parents: Vec<Oid>,
+    pub trailers: Vec<Trailer>,
+}
+
+pub fn load_bg(repo_path: String, oid: Oid) -> Result<CommitDetails, String> {
+    let repo = Repository::open(&repo_path).map_err(|e| e.message().to_string())?;
+    let commit = repo.find_commit(oid).map_err(|e| e.message().to_string())?;
+    let message = String::from_utf8_lossy(commit.message_bytes()).to_string();
+    let (summary, rest) = message
+        .trim_start()
+        .split_once('\n')
+        .unwrap_or((message.trim(), ""));
+    let (body, trailers) = split_trailers(rest);
+
+    Ok(CommitDetails {
+        oid,
+        summary: summary.trim().to_string(),
+        body,
+        author: Identity::of(&commit.author()),
+        committer: Identity::of(&commit.committer()),
+        parents: commit.parent_ids().collect(),
+        trailers,
+    })
+}
+
+/// `time` as `2026-01-01 14:00:00 +0200`, in its own timezone.
+pub fn format_time(time: git2::Time) -> String {
+    FixedOffset::east_opt(time.offset_minutes() * 60)
+        .and_then(|zone| zone.timestamp_opt(time.seconds(), 0).single())
+        .map(|date| date.format(TIME_FORMAT).to_string())
+        .unwrap_or_default()
+}
+
+/// Split the message body (everything after the summary line) into its text
+/// and the trailers of its last paragraph. The last paragraph only counts as
+/// trailers when every line of it is one, as `git interpret-trailers` has it;
+/// lines starting with whitespace continue the trailer above them.
+pub fn split_trailers(body: &str) -> (String, Vec<Trailer>) {
+    let body = body.trim();
+    let start = body.rfind("\n\n").map(|i| i + 2).unwrap_or(0);
+    let paragraph = &body[start..];
+
+    let mut trailers: Vec<Trailer> = Vec::new();
+    for line in paragraph.lines() {
+        if line.starts_with([' ', '\t'])
+            && let Some(last) = trailers.last_mut()
+        {
+            last.value.push(' ');
+            last.value.push_str(line.trim());
+            continue;
+        }
+        match parse_trailer(line) {
+            Some(trailer) => trailers.push(trailer),
+            None => return (body.trim().to_string(), Vec::new()),
+        }
+    }
+    (body[..start].trim().to_string(), trailers)
+}
+
+fn parse_trailer(line: &str) -> Option<Trailer> {
+    let (key, value) = line.split_once(':')?;
+    let valid = !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    valid.then(|| Trailer {
+        key: key.to_string(),
+        value: value.trim().to_string(),
+    })
+}
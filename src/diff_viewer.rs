@@ -1,5 +1,16 @@
+use std::collections::{HashSet, VecDeque};
+use std::fmt::Write;
+use std::ops::Range;
+use std::rc::Rc;
+
 use gpui::prelude::*;
-use gpui::{AnyElement, ParentElement, SharedString, Styled, div, px};
+use gpui::{
+    AnyElement, App, InteractiveElement, MouseButton, ParentElement, SharedString, Styled, Window,
+    div, px,
+};
+
+use crate::staging::HunkAction;
+use crate::widgets;
 
 const BG_ADDED: u32 = 0x1A3A1A;
 const BG_REMOVED: u32 = 0x3A1A1A;
@@ -13,8 +24,11 @@ const TEXT_HUNK: u32 = 0x6C9FD8;
 const TEXT_LINE_NO: u32 = 0x555555;
 const LINE_NO_W: f32 = 40.0;
 const BORDER: u32 = 0x333333;
+const BG_ADDED_SELECTED: u32 = 0x2A5A2A;
+const BG_REMOVED_SELECTED: u32 = 0x5A2A2A;
+const BORDER_SELECTED: u32 = 0x4A90D9;
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum DiffLineKind {
     Context,
     Added,
@@ -26,6 +40,8 @@ pub struct DiffLine {
     pub kind: DiffLineKind,
     pub content: String,
     pub line_no: Option<usize>,
+    /// the line is the last of its file and has no newline after it
+    pub no_newline: bool,
 }
 
 #[derive(Clone)]
@@ -39,23 +55,47 @@ pub enum SideBySideRow {
     },
 }
 
+impl SideBySideRow {
+    /// A row that adds or removes something, as opposed to context or a header.
+    pub fn is_change(&self) -> bool {
+        match self {
+            SideBySideRow::Hunk { .. } => false,
+            SideBySideRow::Line { left, right } => [left, right]
+                .into_iter()
+                .flatten()
+                .any(|line| line.kind != DiffLineKind::Context),
+        }
+    }
+}
+
+/// Line ranges from a `@@ -old_start,old_lines +new_start,new_lines @@` header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HunkRange {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+}
+
+const NO_NEWLINE_MARKER: &str = "\\ No newline at end of file";
+
 pub fn parse_diff(raw: &str) -> Vec<SideBySideRow> {
     let mut rows = Vec::new();
-    let mut pending_removes: Vec<DiffLine> = Vec::new();
+    let mut pending_removes: VecDeque<DiffLine> = VecDeque::new();
     let mut old_no: Option<usize> = None;
     let mut new_no: Option<usize> = None;
+    // lines left in the current hunk, when its header could be read
+    let mut remaining: Option<(usize, usize)> = None;
     let mut in_content = false;
 
-    for line in raw.lines() {
-        if line.starts_with("--- ") || line.starts_with("+++ ") {
-            continue;
-        }
+    // split on '\n' alone so a '\r' before it stays part of the line
+    for line in raw.strip_suffix('\n').unwrap_or(raw).split('\n') {
         if line.starts_with("@@") {
             flush_removes(&mut pending_removes, &mut rows);
-            if let Some((os, ns)) = parse_hunk_positions(line) {
-                old_no = Some(os);
-                new_no = Some(ns);
-            }
+            let range = parse_hunk_header(line);
+            old_no = range.map(|r| r.old_start);
+            new_no = range.map(|r| r.new_start);
+            remaining = range.map(|r| (r.old_lines, r.new_lines));
             rows.push(SideBySideRow::Hunk {
                 header: line.to_string(),
             });
@@ -63,37 +103,41 @@ pub fn parse_diff(raw: &str) -> Vec<SideBySideRow> {
             continue;
         }
 
+        // the marker can follow the last line of a hunk
+        if line.starts_with('\\') {
+            mark_no_newline(&mut pending_removes, &mut rows);
+            continue;
+        }
+
         if !in_content {
             continue;
         }
 
-        if line.starts_with('-') {
+        if let Some(rest) = line.strip_prefix('-') {
             let no = old_no;
             old_no = old_no.map(|n| n + 1);
-            pending_removes.push(DiffLine {
+            count_line(&mut remaining, true, false);
+            pending_removes.push_back(DiffLine {
                 kind: DiffLineKind::Removed,
-                content: line[1..].to_string(),
+                content: rest.to_string(),
                 line_no: no,
+                no_newline: false,
             });
-        } else if line.starts_with('+') {
+        } else if let Some(rest) = line.strip_prefix('+') {
             let no = new_no;
             new_no = new_no.map(|n| n + 1);
+            count_line(&mut remaining, false, true);
             let added = DiffLine {
                 kind: DiffLineKind::Added,
-                content: line[1..].to_string(),
+                content: rest.to_string(),
                 line_no: no,
+                no_newline: false,
             };
-            if let Some(removed) = pending_removes.pop() {
-                rows.push(SideBySideRow::Line {
-                    left: Some(removed),
-                    right: Some(added),
-                });
-            } else {
-                rows.push(SideBySideRow::Line {
-                    left: None,
-                    right: Some(added),
-                });
-            }
+            // removed and added lines pair up in order, first with first
+            rows.push(SideBySideRow::Line {
+                left: pending_removes.pop_front(),
+                right: Some(added),
+            });
         } else {
             flush_removes(&mut pending_removes, &mut rows);
             let content = line.strip_prefix(' ').unwrap_or(line);
@@ -101,26 +145,64 @@ pub fn parse_diff(raw: &str) -> Vec<SideBySideRow> {
             let n = new_no;
             old_no = o.map(|v| v + 1);
             new_no = n.map(|v| v + 1);
+            count_line(&mut remaining, true, true);
             rows.push(SideBySideRow::Line {
                 left: Some(DiffLine {
                     kind: DiffLineKind::Context,
                     content: content.to_string(),
                     line_no: o,
+                    no_newline: false,
                 }),
                 right: Some(DiffLine {
                     kind: DiffLineKind::Context,
                     content: content.to_string(),
                     line_no: n,
+                    no_newline: false,
                 }),
             });
         }
+
+        // past the end of the hunk only a marker or the next header follows
+        if remaining == Some((0, 0)) {
+            in_content = false;
+            remaining = None;
+        }
     }
 
     flush_removes(&mut pending_removes, &mut rows);
     rows
 }
 
-fn flush_removes(pending: &mut Vec<DiffLine>, rows: &mut Vec<SideBySideRow>) {
+fn count_line(remaining: &mut Option<(usize, usize)>, old: bool, new: bool) {
+    if let Some((old_left, new_left)) = remaining {
+        if old {
+            *old_left = old_left.saturating_sub(1);
+        }
+        if new {
+            *new_left = new_left.saturating_sub(1);
+        }
+    }
+}
+
+/// Flag the line a `\ No newline at end of file` marker follows.
+fn mark_no_newline(pending: &mut VecDeque<DiffLine>, rows: &mut [SideBySideRow]) {
+    if let Some(line) = pending.back_mut() {
+        line.no_newline = true;
+        return;
+    }
+    if let Some(SideBySideRow::Line { left, right }) = rows.last_mut() {
+        match (left, right) {
+            (_, Some(right)) if right.kind == DiffLineKind::Added => right.no_newline = true,
+            (left, right) => {
+                for line in [left, right].into_iter().flatten() {
+                    line.no_newline = true;
+                }
+            }
+        }
+    }
+}
+
+fn flush_removes(pending: &mut VecDeque<DiffLine>, rows: &mut Vec<SideBySideRow>) {
     for rl in pending.drain(..) {
         rows.push(SideBySideRow::Line {
             left: Some(rl),
@@ -129,46 +211,333 @@ fn flush_removes(pending: &mut Vec<DiffLine>, rows: &mut Vec<SideBySideRow>) {
     }
 }
 
-fn parse_hunk_positions(header: &str) -> Option<(usize, usize)> {
+pub fn parse_hunk_header(header: &str) -> Option<HunkRange> {
     let trimmed = header.trim_start_matches('@').trim();
-    let parts: Vec<&str> = trimmed.split_whitespace().collect();
-    if parts.len() < 2 {
+    let mut parts = trimmed.split_whitespace();
+    let (old_start, old_lines) = parse_range(parts.next()?.strip_prefix('-')?)?;
+    let (new_start, new_lines) = parse_range(parts.next()?.strip_prefix('+')?)?;
+    Some(HunkRange {
+        old_start,
+        old_lines,
+        new_start,
+        new_lines,
+    })
+}
+
+/// `start,lines`, where a missing count means one line.
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    match range.split_once(',') {
+        Some((start, lines)) => Some((start.parse().ok()?, lines.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+/// Rows of the hunk whose header is row `header`, header excluded.
+pub fn hunk_rows(rows: &[SideBySideRow], header: usize) -> Range<usize> {
+    let start = header + 1;
+    let end = rows
+        .iter()
+        .skip(start)
+        .position(|row| matches!(row, SideBySideRow::Hunk { .. }))
+        .map_or(rows.len(), |offset| start + offset);
+    start..end.max(start)
+}
+
+/// Whether a file diff adds or deletes the file, and the mode of the file
+/// it adds or deletes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum FileChange {
+    #[default]
+    Modified,
+    Added {
+        mode: String,
+    },
+    Deleted {
+        mode: String,
+    },
+}
+
+/// Read the `diff --git` header lines in front of the first hunk.
+pub fn parse_file_change(raw: &str) -> FileChange {
+    for line in raw.lines().take_while(|line| !line.starts_with("@@")) {
+        if let Some(mode) = line.strip_prefix("new file mode ") {
+            return FileChange::Added {
+                mode: mode.trim().to_string(),
+            };
+        }
+        if let Some(mode) = line.strip_prefix("deleted file mode ") {
+            return FileChange::Deleted {
+                mode: mode.trim().to_string(),
+            };
+        }
+    }
+    FileChange::Modified
+}
+
+/// A patch of `path` made of the changes in the selected rows, or `None`
+/// when no selected row changes anything. With `reverse` the patch undoes
+/// the diff instead of making it.
+///
+/// Rows that are not selected stay as they are on the side the patch is
+/// applied to: a removed line becomes context and an added line is left out.
+/// Line numbers are worked out again, so hunks keep applying after
+/// unselected changes in front of them were dropped.
+///
+/// `change` comes from the header of the diff. The patch only creates the
+/// file when the diff adds it, and only deletes it when the diff deletes it
+/// and no line is left.
+pub fn build_patch(
+    path: &str,
+    change: &FileChange,
+    rows: &[SideBySideRow],
+    selected: impl Fn(usize) -> bool,
+    reverse: bool,
+) -> Option<String> {
+    let mut hunks = String::new();
+    // lines the patch adds minus lines it removes, so far
+    let mut shift: isize = 0;
+    let mut changed = false;
+    // some line of the file is still there after the patch
+    let mut keeps_lines = false;
+
+    let headers: Vec<usize> = (0..rows.len())
+        .filter(|i| matches!(rows[*i], SideBySideRow::Hunk { .. }))
+        .collect();
+    for (n, &start) in headers.iter().enumerate() {
+        let SideBySideRow::Hunk { header } = &rows[start] else {
+            continue;
+        };
+        let range = parse_hunk_header(header)?;
+        let old_start = if reverse {
+            range.new_start
+        } else {
+            range.old_start
+        };
+        let end = headers.get(n + 1).copied().unwrap_or(rows.len());
+
+        let mut body = Vec::new();
+        let (mut old_count, mut new_count) = (0usize, 0usize);
+        let mut hunk_changed = false;
+        for (row, line_row) in rows.iter().enumerate().take(end).skip(start + 1) {
+            let SideBySideRow::Line { left, right } = line_row else {
+                continue;
+            };
+            let take = selected(row);
+            let (before, after) = if reverse {
+                (right, left)
+            } else {
+                (left, right)
+            };
+            let is_context = left
+                .as_ref()
+                .is_some_and(|line| line.kind == DiffLineKind::Context);
+            if is_context {
+                if let Some(line) = before {
+                    push_line(&mut body, ' ', line);
+                    old_count += 1;
+                    new_count += 1;
+                }
+                continue;
+            }
+            if let Some(line) = before {
+                if take {
+                    push_line(&mut body, '-', line);
+                    hunk_changed = true;
+                } else {
+                    push_line(&mut body, ' ', line);
+                    new_count += 1;
+                }
+                old_count += 1;
+            }
+            if let Some(line) = after
+                && take
+            {
+                push_line(&mut body, '+', line);
+                new_count += 1;
+                hunk_changed = true;
+            }
+        }
+        keeps_lines |= !hunk_changed || new_count > 0;
+        if !hunk_changed {
+            continue;
+        }
+        changed = true;
+
+        // a side without lines gives the line before the hunk as its start
+        let first_old = if old_count == 0 {
+            old_start + 1
+        } else {
+            old_start
+        };
+        let first_new = (first_old as isize + shift).max(1) as usize;
+        let new_start = if new_count == 0 {
+            first_new - 1
+        } else {
+            first_new
+        };
+        let _ = writeln!(
+            hunks,
+            "@@ -{},{} +{},{} @@",
+            old_start, old_count, new_start, new_count
+        );
+        for line in body {
+            hunks.push_str(&line);
+            hunks.push('\n');
+        }
+        shift += new_count as isize - old_count as isize;
+    }
+    if !changed {
         return None;
     }
-    let old_start: usize = parts[0]
-        .trim_start_matches('-')
-        .split(',')
-        .next()?
-        .parse()
-        .ok()?;
-    let new_start: usize = parts[1]
-        .trim_start_matches('+')
-        .split(',')
-        .next()?
-        .parse()
-        .ok()?;
-    Some((old_start, new_start))
+
+    let mut patch = format!("diff --git a/{path} b/{path}\n");
+    match (change, reverse) {
+        (FileChange::Added { mode }, false) | (FileChange::Deleted { mode }, true) => {
+            let _ = write!(
+                patch,
+                "new file mode {}\n--- /dev/null\n+++ b/{}\n",
+                mode, path
+            );
+        }
+        (FileChange::Deleted { mode }, false) | (FileChange::Added { mode }, true)
+            if !keeps_lines =>
+        {
+            let _ = write!(
+                patch,
+                "deleted file mode {}\n--- a/{}\n+++ /dev/null\n",
+                mode, path
+            );
+        }
+        _ => {
+            let _ = write!(patch, "--- a/{}\n+++ b/{}\n", path, path);
+        }
+    }
+    patch.push_str(&hunks);
+    Some(patch)
+}
+
+fn push_line(body: &mut Vec<String>, prefix: char, line: &DiffLine) {
+    body.push(format!("{}{}", prefix, line.content));
+    if line.no_newline {
+        body.push(NO_NEWLINE_MARKER.to_string());
+    }
 }
 
 pub fn is_binary_or_error(raw: &str) -> bool {
     raw.contains("binary file") || raw.contains("not found in diff")
 }
 
+/// Something done from the staging controls of a diff.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StagingEvent {
+    /// a changed row was clicked; `extend` selects every changed row from
+    /// the one clicked before
+    Select {
+        row: usize,
+        extend: bool,
+    },
+    /// run `action` on the hunk whose header is row `row`
+    Hunk {
+        row: usize,
+        action: HunkAction,
+    },
+    /// run `action` on the selected rows
+    Selection(HunkAction),
+    ClearSelection,
+}
+
+pub type StagingHandler = Rc<dyn Fn(&StagingEvent, &mut Window, &mut App)>;
+
+/// Hunk buttons and line selection for a diff against the index.
+pub struct StagingControls<'a> {
+    /// what the hunk and selection buttons offer, left to right
+    pub actions: &'static [HunkAction],
+    pub selected: &'a HashSet<usize>,
+    /// a patch is being applied; the buttons wait for it
+    pub busy: bool,
+    pub on_event: StagingHandler,
+}
+
 pub fn render_side_by_side(rows: &[SideBySideRow]) -> AnyElement {
+    render_rows(rows, None)
+}
+
+/// The side-by-side diff with buttons to stage, unstage or discard a hunk
+/// and clickable changed rows; shift-click selects a range of them.
+pub fn render_staging(rows: &[SideBySideRow], controls: &StagingControls) -> AnyElement {
+    render_rows(rows, Some(controls))
+}
+
+fn render_rows(rows: &[SideBySideRow], controls: Option<&StagingControls>) -> AnyElement {
     div()
         .w_full()
         .flex()
         .flex_col()
         .border_t_1()
         .border_color(gpui::rgb(BORDER))
+        .when_some(
+            controls.filter(|c| !c.selected.is_empty()),
+            |el, controls| el.child(render_selection_bar(controls)),
+        )
         .child(render_column_header())
         .children(rows.iter().enumerate().map(|(i, row)| match row {
-            SideBySideRow::Hunk { header } => render_hunk_row(header),
-            SideBySideRow::Line { left, right } => render_line_row(i, left, right),
+            SideBySideRow::Hunk { header } => render_hunk_row(i, header, controls),
+            SideBySideRow::Line { left, right } => {
+                render_line_row(i, left, right, row.is_change(), controls)
+            }
+        }))
+        .into_any()
+}
+
+fn render_selection_bar(controls: &StagingControls) -> AnyElement {
+    div()
+        .w_full()
+        .flex()
+        .flex_row()
+        .items_center()
+        .gap_2()
+        .px(px(8.0))
+        .py(px(3.0))
+        .bg(gpui::rgb(0x252525))
+        .border_b_1()
+        .border_color(gpui::rgb(BORDER))
+        .text_color(gpui::rgb(0x888888))
+        .text_size(px(11.0))
+        .child(format!("{} selected", controls.selected.len()))
+        .children(controls.actions.iter().map(|action| {
+            staging_button(
+                format!("selection-{}", action.label()),
+                format!("{} lines", action.label()),
+                StagingEvent::Selection(*action),
+                controls,
+            )
         }))
+        .child(staging_button(
+            "selection-clear".to_string(),
+            "Clear".to_string(),
+            StagingEvent::ClearSelection,
+            controls,
+        ))
         .into_any()
 }
 
+/// Button that hands `event` to the staging controls.
+fn staging_button(
+    id: String,
+    label: String,
+    event: StagingEvent,
+    controls: &StagingControls,
+) -> AnyElement {
+    let on_event = controls.on_event.clone();
+    widgets::button(
+        SharedString::from(id),
+        label,
+        !controls.busy,
+        move |_event, window, cx| on_event(&event, window, cx),
+    )
+    .into_any()
+}
+
 fn render_column_header() -> AnyElement {
     div()
         .w_full()
@@ -204,9 +573,13 @@ fn render_column_header() -> AnyElement {
         .into_any()
 }
 
-fn render_hunk_row(header: &str) -> AnyElement {
+fn render_hunk_row(index: usize, header: &str, controls: Option<&StagingControls>) -> AnyElement {
     div()
         .w_full()
+        .flex()
+        .flex_row()
+        .items_center()
+        .justify_between()
         .px(px(8.0))
         .py(px(2.0))
         .bg(gpui::rgb(BG_HUNK))
@@ -214,24 +587,67 @@ fn render_hunk_row(header: &str) -> AnyElement {
         .text_size(px(11.0))
         .font_family("monospace")
         .child(header.to_string())
+        .when_some(controls, |el, controls| {
+            el.child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .gap_1()
+                    .children(controls.actions.iter().map(|action| {
+                        staging_button(
+                            format!("hunk-{}-{}", index, action.label()),
+                            format!("{} hunk", action.label()),
+                            StagingEvent::Hunk {
+                                row: index,
+                                action: *action,
+                            },
+                            controls,
+                        )
+                    })),
+            )
+        })
         .into_any()
 }
 
-fn render_line_row(index: usize, left: &Option<DiffLine>, right: &Option<DiffLine>) -> AnyElement {
+fn render_line_row(
+    index: usize,
+    left: &Option<DiffLine>,
+    right: &Option<DiffLine>,
+    is_change: bool,
+    controls: Option<&StagingControls>,
+) -> AnyElement {
+    let selectable = controls.filter(|_| is_change);
+    let selected = selectable.is_some_and(|c| c.selected.contains(&index));
     div()
         .id(SharedString::from(format!("diff-row-{}", index)))
         .w_full()
         .flex()
         .flex_row()
-        .child(render_half(left, true))
-        .child(render_half(right, false))
+        .when(selected, |el| {
+            el.border_l_2().border_color(gpui::rgb(BORDER_SELECTED))
+        })
+        .when_some(selectable, |el, controls| {
+            let on_event = controls.on_event.clone();
+            el.cursor_pointer()
+                .on_mouse_down(MouseButton::Left, move |event, window, cx| {
+                    let event = StagingEvent::Select {
+                        row: index,
+                        extend: event.modifiers.shift,
+                    };
+                    on_event(&event, window, cx);
+                })
+        })
+        .child(render_half(left, true, selected))
+        .child(render_half(right, false, selected))
         .into_any()
 }
 
-fn render_half(line: &Option<DiffLine>, is_left: bool) -> AnyElement {
+fn render_half(line: &Option<DiffLine>, is_left: bool, selected: bool) -> AnyElement {
     match line {
         Some(dl) => {
             let bg = match dl.kind {
+                DiffLineKind::Added if selected => BG_ADDED_SELECTED,
+                DiffLineKind::Removed if selected => BG_REMOVED_SELECTED,
                 DiffLineKind::Added => BG_ADDED,
                 DiffLineKind::Removed => BG_REMOVED,
                 DiffLineKind::Context => BG_CONTEXT,
@@ -1,15 +1,45 @@
-use gpui::{Pixels, Point};
+use crate::layout::GraphCell;
 
+/// How an edge travels between a child and its parent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// child and parent share a lane
+    Straight,
+    /// the child's lane runs down and bends into the parent's lane at the parent row
+    MergeIn,
+    /// the edge leaves the child row sideways and runs down the parent's lane
+    ForkOut,
+}
+
+/// An edge from a child cell to one of its parents.
 #[derive(Debug, Clone)]
 pub struct Edge {
-    pub from: Point<Pixels>,
-    pub to: Point<Pixels>,
+    pub from: GraphCell,
+    pub to: GraphCell,
+    pub kind: EdgeKind,
     pub color: usize,
+    /// `true` when commits between the two ends were left out; drawn dashed.
+    pub elided: bool,
 }
 
 impl Edge {
-    pub fn new(from: Point<Pixels>, to: Point<Pixels>, color: usize) -> Self {
-        Self { from, to, color }
+    pub fn new(from: GraphCell, to: GraphCell, kind: EdgeKind, color: usize, elided: bool) -> Self {
+        Self {
+            from,
+            to,
+            kind,
+            color,
+            elided,
+        }
+    }
+
+    /// The grid point the bezier bends around, `None` for straight edges.
+    pub fn control(&self) -> Option<GraphCell> {
+        match self.kind {
+            EdgeKind::Straight => None,
+            EdgeKind::MergeIn => Some(GraphCell::new(self.to.row, self.from.lane)),
+            EdgeKind::ForkOut => Some(GraphCell::new(self.from.row, self.to.lane)),
+        }
     }
 }
 
@@ -23,8 +53,15 @@ impl EdgeManager {
         Self::default()
     }
 
-    pub fn add(&mut self, from: Point<Pixels>, to: Point<Pixels>, color: usize) {
-        self.edges.push(Edge::new(from, to, color));
+    pub fn add(
+        &mut self,
+        from: GraphCell,
+        to: GraphCell,
+        kind: EdgeKind,
+        color: usize,
+        elided: bool,
+    ) {
+        self.edges.push(Edge::new(from, to, kind, color, elided));
     }
 
     pub fn take_edges(&mut self) -> Vec<Edge> {
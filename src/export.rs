@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use git2::Oid;
+
+use crate::commit::CommitNode;
+use crate::edge::{Edge, EdgeKind};
+use crate::garph::{Garph, GraphData, GraphMetrics};
+use crate::layout::GraphCell;
+
+// the graph columns and the text are this far apart
+const TEXT_GAP: usize = 1;
+const SHORT_OID: usize = 7;
+
+// SVG colors and sizes, matching what `Garph::render` draws
+const SVG_BG: u32 = 0x282828;
+const SVG_TEXT: u32 = 0x969696;
+const SVG_FONT: &str = "monospace";
+const SVG_TEXT_SIZE: f32 = 10.0;
+const SVG_BADGE_SIZE: f32 = 9.0;
+// monospace glyphs are about this wide relative to the font size
+const SVG_CHAR_WIDTH: f32 = 0.6;
+const SVG_LABEL_PADDING: f32 = 10.0;
+const SVG_LABEL_WIDTH: f32 = 600.0;
+const SVG_MAX_MESSAGE_CHARS: usize = 80;
+
+/// File formats the graph can be saved as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Ascii,
+    Dot,
+    Svg,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Ascii => "txt",
+            ExportFormat::Dot => "dot",
+            ExportFormat::Svg => "svg",
+        }
+    }
+
+    /// Render `graph` in this format, coloring by `palette` where the format has colors.
+    pub fn render(&self, graph: &GraphData, palette: &[u32]) -> String {
+        match self {
+            ExportFormat::Ascii => to_ascii(graph),
+            ExportFormat::Dot => to_dot(graph, palette),
+            ExportFormat::Svg => to_svg(graph, palette),
+        }
+    }
+}
+
+/// `git log --graph --oneline --decorate` style text of the laid-out graph.
+/// Every lane takes two columns; each commit row is followed by a row of
+/// connectors leading to the next one.
+pub fn to_ascii(graph: &GraphData) -> String {
+    let width = graph.max_lane * 2 + 1;
+    let mut lines = vec![vec![' '; width]; graph.rows * 2];
+
+    // edges first so node markers win where they overlap
+    for edge in &graph.edges {
+        draw_edge(&mut lines, edge);
+    }
+    for node in &graph.nodes {
+        lines[node.cell.row * 2][node.cell.lane * 2] = '*';
+    }
+
+    let by_row: HashMap<usize, &CommitNode> = graph.nodes.iter().map(|n| (n.cell.row, n)).collect();
+    let mut out = String::new();
+    for (index, line) in lines.iter().enumerate() {
+        let mut text: String = line.iter().collect();
+        if index % 2 == 0
+            && let Some(node) = by_row.get(&(index / 2))
+        {
+            text.push_str(&" ".repeat(TEXT_GAP));
+            text.push_str(&node_label(node));
+        }
+        let text = text.trim_end();
+        // the connector row after the last commit is always empty
+        if text.is_empty() && index + 1 == lines.len() {
+            continue;
+        }
+        out.push_str(text);
+        out.push('\n');
+    }
+    out
+}
+
+fn draw_edge(lines: &mut [Vec<char>], edge: &Edge) {
+    let vertical = if edge.elided { ':' } else { '|' };
+    let (from, to) = (edge.from, edge.to);
+    // the lane the edge runs down, and the gap below `row` where it bends
+    let (lane, bend_row) = match edge.kind {
+        EdgeKind::Straight => (from.lane, None),
+        EdgeKind::MergeIn => (from.lane, Some(to.row.saturating_sub(1))),
+        EdgeKind::ForkOut => (to.lane, Some(from.row)),
+    };
+    // commit rows strictly between the two ends
+    for row in from.row + 1..to.row {
+        set(lines, row * 2, lane * 2, vertical);
+    }
+    // connector rows, except where the edge bends
+    for row in from.row..to.row {
+        if Some(row) != bend_row {
+            set(lines, row * 2 + 1, lane * 2, vertical);
+        }
+    }
+    if let Some(row) = bend_row {
+        draw_bend(&mut lines[row * 2 + 1], from.lane, to.lane);
+    }
+}
+
+/// One connector row that moves an edge from lane `from` to lane `to`.
+fn draw_bend(line: &mut [char], from: usize, to: usize) {
+    let (low, high, slash) = if from > to {
+        (to, from, '/')
+    } else {
+        (from, to, '\\')
+    };
+    if low == high {
+        return;
+    }
+    line[low * 2 + 1] = slash;
+    for lane in low + 1..high {
+        if line[lane * 2 + 1] == ' ' {
+            line[lane * 2 + 1] = '_';
+        }
+    }
+}
+
+fn set(lines: &mut [Vec<char>], line: usize, column: usize, c: char) {
+    if let Some(slot) = lines.get_mut(line).and_then(|l| l.get_mut(column))
+        && *slot == ' '
+    {
+        *slot = c;
+    }
+}
+
+/// Short oid, ref names and the first line of the message.
+fn node_label(node: &CommitNode) -> String {
+    let mut label = short_oid(&node.oid);
+    if !node.refs.is_empty() {
+        let names: Vec<&str> = node.refs.iter().map(|r| r.name.as_str()).collect();
+        let _ = write!(label, " ({})", names.join(", "));
+    }
+    let _ = write!(label, " {}", summary(&node.message));
+    label
+}
+
+/// Graphviz digraph of the laid-out graph, edges pointing from child to
+/// parent. Nodes of a lane share a `group` so dot keeps the lane straight.
+pub fn to_dot(graph: &GraphData, palette: &[u32]) -> String {
+    let color = |slot: usize| {
+        palette
+            .get(slot % palette.len().max(1))
+            .copied()
+            .unwrap_or(0)
+    };
+    let by_cell: HashMap<GraphCell, Oid> = graph.nodes.iter().map(|n| (n.cell, n.oid)).collect();
+
+    let mut out = String::new();
+    out.push_str("digraph commits {\n");
+    out.push_str("    rankdir=TB;\n");
+    out.push_str(
+        "    node [shape=box, style=\"rounded,filled\", fontname=\"monospace\", fontsize=10];\n",
+    );
+    out.push_str("    edge [arrowhead=none, penwidth=1.5];\n");
+    for node in &graph.nodes {
+        let _ = writeln!(
+            out,
+            "    \"{}\" [label=\"{}\", fillcolor=\"#{:06X}\", group=\"lane{}\"];",
+            node.oid,
+            escape(&node_label(node)),
+            color(node.color),
+            node.cell.lane,
+        );
+    }
+    for edge in &graph.edges {
+        let (Some(child), Some(parent)) = (by_cell.get(&edge.from), by_cell.get(&edge.to)) else {
+            continue;
+        };
+        let style = if edge.elided { ", style=dashed" } else { "" };
+        let _ = writeln!(
+            out,
+            "    \"{}\" -> \"{}\" [color=\"#{:06X}\"{}];",
+            child,
+            parent,
+            color(edge.color),
+            style,
+        );
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Standalone SVG of the graph as the canvas draws it at zoom 1: the same
+/// lanes, bezier edges, node dots, ref badges and messages. Built from the
+/// layout only, so the same graph always gives the same file.
+pub fn to_svg(graph: &GraphData, palette: &[u32]) -> String {
+    let color = |slot: usize| {
+        palette
+            .get(slot % palette.len().max(1))
+            .copied()
+            .unwrap_or(0)
+    };
+    let metrics = GraphMetrics::new(1.0, 0);
+    let text_left = f32::from(metrics.text_left(graph.max_lane)) + SVG_LABEL_PADDING;
+    let width = text_left + SVG_LABEL_WIDTH;
+    let height = f32::from(metrics.content_height(graph.rows));
+    let row_height = metrics.row_height();
+    let half = f32::from(metrics.node_size()) / 2.0;
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         viewBox=\"0 0 {width} {height}\" font-family=\"{SVG_FONT}\">"
+    );
+    let _ = writeln!(
+        out,
+        "  <rect width=\"100%\" height=\"100%\" fill=\"#{SVG_BG:06X}\"/>"
+    );
+
+    out.push_str("  <g fill=\"none\" stroke-width=\"1.5\">\n");
+    for edge in &graph.edges {
+        let start = metrics.anchor(edge.from);
+        let end = metrics.anchor(edge.to);
+        let (sx, sy, ex, ey) = (
+            f32::from(start.x),
+            f32::from(start.y),
+            f32::from(end.x),
+            f32::from(end.y),
+        );
+        let d = match edge.control() {
+            None => format!("M{sx} {sy} L{ex} {ey}"),
+            Some(ctrl) => {
+                let ctrl = metrics.anchor(ctrl);
+                let (cx, cy) = (f32::from(ctrl.x), f32::from(ctrl.y));
+                format!("M{sx} {sy} C{cx} {cy} {cx} {cy} {ex} {ey}")
+            }
+        };
+        let dash = if edge.elided {
+            " stroke-dasharray=\"3 3\""
+        } else {
+            ""
+        };
+        let _ = writeln!(
+            out,
+            "    <path d=\"{d}\" stroke=\"#{:06X}\"{dash}/>",
+            color(edge.color)
+        );
+    }
+    out.push_str("  </g>\n");
+
+    for node in &graph.nodes {
+        let x = f32::from(metrics.x(node.cell.lane));
+        let y = f32::from(metrics.y(node.cell.row));
+        let fill = color(node.color);
+        // stashes are hollow squares, as on screen
+        if node.stash.is_some() {
+            let _ = writeln!(
+                out,
+                "  <rect x=\"{x}\" y=\"{y}\" width=\"{size}\" height=\"{size}\" rx=\"2\" \
+                 fill=\"none\" stroke=\"#{fill:06X}\"/>",
+                size = half * 2.0
+            );
+        } else {
+            let _ = writeln!(
+                out,
+                "  <circle cx=\"{}\" cy=\"{}\" r=\"{half}\" fill=\"#{fill:06X}\"/>",
+                x + half,
+                y + half
+            );
+        }
+
+        let mut left = text_left;
+        let middle = y + row_height / 2.0;
+        for label in &node.refs {
+            let (bg, text) = Garph::badge_colors(label.kind);
+            let badge_width =
+                label.name.chars().count() as f32 * SVG_BADGE_SIZE * SVG_CHAR_WIDTH + 8.0;
+            let _ = writeln!(
+                out,
+                "  <rect x=\"{left}\" y=\"{}\" width=\"{badge_width}\" height=\"{}\" \
+                 rx=\"3\" fill=\"#{bg:06X}\"/>",
+                middle - SVG_BADGE_SIZE * 0.75,
+                SVG_BADGE_SIZE * 1.5
+            );
+            let _ = writeln!(
+                out,
+                "  <text x=\"{}\" y=\"{middle}\" font-size=\"{SVG_BADGE_SIZE}\" \
+                 dominant-baseline=\"central\" fill=\"#{text:06X}\">{}</text>",
+                left + 4.0,
+                escape_xml(&label.name)
+            );
+            left += badge_width + 4.0;
+        }
+        let _ = writeln!(
+            out,
+            "  <text x=\"{left}\" y=\"{middle}\" font-size=\"{SVG_TEXT_SIZE}\" \
+             dominant-baseline=\"central\" fill=\"#{SVG_TEXT:06X}\">{}</text>",
+            escape_xml(&truncate(summary(&node.message), SVG_MAX_MESSAGE_CHARS))
+        );
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        text.to_string()
+    } else {
+        format!("{}...", text.chars().take(max_chars).collect::<String>())
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+pub fn short_oid(oid: &Oid) -> String {
+    oid.to_string()[..SHORT_OID].to_string()
+}
+
+fn summary(message: &str) -> &str {
+    message.lines().next().unwrap_or("").trim()
+}
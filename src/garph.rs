@@ -1,34 +1,61 @@
+use chrono::{DateTime, Utc};
 use git2::{Oid, Repository};
 use gpui::prelude::FluentBuilder;
 use gpui::{
-    Context, EventEmitter, InteractiveElement, IntoElement, MouseButton, ParentElement,
-    PathBuilder, Pixels, Point, Render, StatefulInteractiveElement, Styled, Window, canvas, div,
+    App, Context, CursorStyle, EventEmitter, FocusHandle, Focusable, InteractiveElement,
+    IntoElement, MouseButton, MouseDownEvent, MouseMoveEvent, ParentElement, PathBuilder, Pixels,
+    Point, Render, ScrollHandle, StatefulInteractiveElement, Styled, Window, actions, canvas, div,
     px,
 };
 
-use crate::color::ColorManager;
+use crate::ancestry::{self, ParentFollow};
+use crate::columns::{Column, Columns};
 use crate::commit::CommitNode;
-use crate::edge::{Edge, EdgeManager};
-use crate::history_oid::{HistoryOid, HistoryOidManager};
-use crate::lane::LaneManager;
+use crate::edge::Edge;
+use crate::export;
+use crate::job::Job;
+use crate::layout::{Frontier, GraphCell, GraphLayout, GraphMode};
+use crate::query::{CommitQuery, QueryMode};
+use crate::refs::{RefIndex, RefKind, RefLabel, RefSelection};
+use crate::stash::{StashEntry, StashIndex};
 use std::cell::RefCell;
+use std::collections::HashSet;
 use std::rc::Rc;
-use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+actions!(
+    garph,
+    [
+        SelectNext,
+        SelectPrev,
+        SelectPageDown,
+        SelectPageUp,
+        SelectFirst,
+        SelectLast,
+        SelectParent,
+        FollowNextParent,
+        OpenSelected,
+    ]
+);
 
 const START_X: f32 = 30.0;
 const LANE_WIDTH: f32 = 15.0;
 const TEXT_GAP: f32 = 20.0;
 const COMMIT_HEIGHT: f32 = 20.0;
-const SIZE: Pixels = px(10.0);
+const NODE_SIZE: f32 = 10.0;
+// edges attach slightly below the node center
+const EDGE_ANCHOR_Y: f32 = 6.0;
 const GAP_ROW: f32 = 40.0;
-const LIMIT_ROW: usize = 100;
+const MIN_ZOOM: f32 = 0.5;
+// rows that don't match the search in highlight mode
+const DIMMED_OPACITY: f32 = 0.3;
+const MAX_ZOOM: f32 = 2.0;
+const PAGE_SIZE: usize = 100;
+// start loading the next page when the viewport is this close to the bottom
+const LOAD_MORE_MARGIN: f32 = 400.0;
 const MAX_FILE_SIZE_BYTES: usize = 10 * 1024 * 1024; // 10 MB
 
-// Diff limits to prevent memory exhaustion
-const MAX_FILES_TO_SHOW: usize = 10;
-const MAX_LINES_PER_FILE: usize = 25;
-const MAX_TOTAL_LINES: usize = 100;
-
 pub const GIT_RED: u32 = 0xE64D3F;
 pub const GIT_YELLOW: u32 = 0xF1C40F;
 pub const GIT_GREEN: u32 = 0x2ECC71;
@@ -36,6 +63,26 @@ pub const GIT_BLUE: u32 = 0x3498DB;
 pub const GIT_PURPLE: u32 = 0x9B59B6;
 pub const VEC_COLORS: &[u32] = &[GIT_PURPLE, GIT_BLUE, GIT_RED, GIT_YELLOW, GIT_GREEN];
 
+const BADGE_HEAD_BG: u32 = 0x5A2A24;
+const BADGE_HEAD_TEXT: u32 = GIT_RED;
+const BADGE_LOCAL_BG: u32 = 0x2A3A2A;
+const BADGE_LOCAL_TEXT: u32 = 0x4AE04A;
+const BADGE_REMOTE_BG: u32 = 0x23344A;
+const BADGE_REMOTE_TEXT: u32 = 0x4A90D9;
+const BADGE_TAG_BG: u32 = 0x4A4020;
+const BADGE_TAG_TEXT: u32 = GIT_YELLOW;
+const BADGE_STASH_BG: u32 = 0x3A3A3A;
+const BADGE_STASH_TEXT: u32 = 0xBBBBBB;
+const SELECTED_ROW_BG: u32 = 0x3A4A5A;
+
+const COLUMN_HEADER_HEIGHT: f32 = 20.0;
+const COLUMN_TEXT: u32 = 0x777777;
+const COLUMN_PADDING: f32 = 6.0;
+const RESIZE_HANDLE_WIDTH: f32 = 4.0;
+
+const WORKTREE_COLOR: u32 = 0x888888;
+const WORKTREE_TEXT: u32 = 0xCCCCCC;
+
 const BINARY_CHECK_BYTES: usize = 8000;
 
 pub fn compute_file_diff_bg(
@@ -43,8 +90,6 @@ pub fn compute_file_diff_bg(
     commit_oid: Oid,
     file_path: String,
 ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-    const MAX_FILE_DIFF_LINES: usize = 200;
-
     let repo = Repository::open(&repo_path)?;
     let commit = repo.find_commit(commit_oid)?;
     let commit_tree = commit.tree()?;
@@ -65,9 +110,97 @@ pub fn compute_file_diff_bg(
         Err(_) => repo.diff_tree_to_tree(None, Some(&commit_tree), None)?,
     };
 
+    format_file_diff(&diff, &file_path)
+}
+
+/// Diff of one file between HEAD and the working tree, staged and unstaged together.
+pub fn compute_worktree_file_diff_bg(
+    repo_path: String,
+    file_path: String,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let repo = Repository::open(&repo_path)?;
+    let mut opts = worktree_diff_options();
+    opts.pathspec(&file_path);
+    let diff = worktree_diff(&repo, &mut opts)?;
+    format_file_diff(&diff, &file_path)
+}
+
+/// Files that differ between HEAD and the working tree, untracked files included.
+pub fn get_worktree_changes_bg(
+    repo_path: String,
+) -> Result<Vec<ChangedFile>, Box<dyn std::error::Error + Send + Sync>> {
+    let repo = Repository::open(&repo_path)?;
+    let diff = worktree_diff(&repo, &mut worktree_diff_options())?;
+    changed_files_of(&diff)
+}
+
+fn worktree_diff_options() -> git2::DiffOptions {
+    let mut opts = git2::DiffOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true);
+    opts
+}
+
+fn worktree_diff<'r>(
+    repo: &'r Repository,
+    opts: &mut git2::DiffOptions,
+) -> Result<git2::Diff<'r>, git2::Error> {
+    // an unborn HEAD diffs against the empty tree
+    let head_tree = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+    repo.diff_tree_to_workdir_with_index(head_tree.as_ref(), Some(opts))
+}
+
+fn changed_files_of(
+    diff: &git2::Diff,
+) -> Result<Vec<ChangedFile>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut files = Vec::new();
+    diff.foreach(
+        &mut |delta, _| {
+            let path = delta
+                .new_file()
+                .path()
+                .or(delta.old_file().path())
+                .and_then(|p| p.to_str())
+                .unwrap_or("unknown")
+                .to_string();
+
+            let old_oid = {
+                let id = delta.old_file().id();
+                if id.is_zero() { None } else { Some(id) }
+            };
+            let new_oid = {
+                let id = delta.new_file().id();
+                if id.is_zero() { None } else { Some(id) }
+            };
+
+            files.push(ChangedFile {
+                path,
+                status: delta.status(),
+                old_oid,
+                new_oid,
+            });
+            true
+        },
+        None,
+        None,
+        None,
+    )?;
+    Ok(files)
+}
+
+/// Render the part of `diff` that touches `file_path` in the text format
+/// `diff_viewer::parse_diff` reads.
+fn format_file_diff(
+    diff: &git2::Diff,
+    file_path: &str,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    const MAX_FILE_DIFF_LINES: usize = 200;
+
     let diff_lines = RefCell::new(Vec::new());
     let line_count = RefCell::new(0usize);
     let file_found = RefCell::new(false);
+    let binary = RefCell::new(false);
 
     diff.foreach(
         &mut |delta, _| {
@@ -85,7 +218,7 @@ pub fn compute_file_diff_bg(
             *file_found.borrow_mut() = true;
 
             match delta.status() {
-                git2::Delta::Added => diff_lines
+                git2::Delta::Added | git2::Delta::Untracked => diff_lines
                     .borrow_mut()
                     .push(format!("+++ a/{}", current_path)),
                 git2::Delta::Deleted => diff_lines
@@ -114,14 +247,27 @@ pub fn compute_file_diff_bg(
             }
             true
         },
-        None,
+        Some(&mut |delta: git2::DiffDelta, _: git2::DiffBinary| {
+            let current_path = delta
+                .new_file()
+                .path()
+                .or(delta.old_file().path())
+                .and_then(|p| p.to_str())
+                .unwrap_or("");
+            if current_path == file_path {
+                *binary.borrow_mut() = true;
+            }
+            true
+        }),
         Some(&mut |_: git2::DiffDelta, hunk: git2::DiffHunk| {
             if *line_count.borrow() >= MAX_FILE_DIFF_LINES {
                 return false;
             }
-            diff_lines
-                .borrow_mut()
-                .push(format!("@@ {} @@", String::from_utf8_lossy(hunk.header())));
+            diff_lines.borrow_mut().push(
+                String::from_utf8_lossy(hunk.header())
+                    .trim_end()
+                    .to_string(),
+            );
             true
         }),
         Some(
@@ -153,6 +299,10 @@ pub fn compute_file_diff_bg(
         return Ok(format!("File '{}' not found in diff", file_path));
     }
 
+    if binary.into_inner() {
+        return Ok(format!("{} is a binary file", file_path));
+    }
+
     let final_result = if line_count.into_inner() >= MAX_FILE_DIFF_LINES {
         let mut truncated = result;
         truncated.push(String::new());
@@ -168,6 +318,94 @@ pub fn compute_file_diff_bg(
     Ok(final_result.join("\n"))
 }
 
+/// Files that differ between the trees of `old` and `new`.
+pub fn get_range_changes_bg(
+    repo_path: String,
+    old: Oid,
+    new: Oid,
+) -> Result<Vec<ChangedFile>, Box<dyn std::error::Error + Send + Sync>> {
+    let repo = Repository::open(&repo_path)?;
+    let diff = range_diff(&repo, old, new, None)?;
+    changed_files_of(&diff)
+}
+
+/// Diff of one file between the trees of `old` and `new`.
+pub fn compute_range_file_diff_bg(
+    repo_path: String,
+    old: Oid,
+    new: Oid,
+    file_path: String,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let repo = Repository::open(&repo_path)?;
+    let mut opts = git2::DiffOptions::new();
+    opts.pathspec(&file_path);
+    let diff = range_diff(&repo, old, new, Some(&mut opts))?;
+    format_file_diff(&diff, &file_path)
+}
+
+/// Files whose staged version differs from HEAD when `staged`, otherwise
+/// files whose working tree version differs from the staged one.
+pub fn get_index_changes_bg(
+    repo_path: String,
+    staged: bool,
+) -> Result<Vec<ChangedFile>, Box<dyn std::error::Error + Send + Sync>> {
+    let repo = Repository::open(&repo_path)?;
+    let diff = index_diff(&repo, staged, &mut git2::DiffOptions::new())?;
+    changed_files_of(&diff)
+}
+
+/// Complete patch of one file from `get_index_changes_bg`. Nothing is
+/// trimmed or cut off, since hunks of it are applied back to the repository.
+pub fn compute_index_file_diff_bg(
+    repo_path: String,
+    staged: bool,
+    file_path: String,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let repo = Repository::open(&repo_path)?;
+    let mut opts = git2::DiffOptions::new();
+    opts.pathspec(&file_path).disable_pathspec_match(true);
+    let diff = index_diff(&repo, staged, &mut opts)?;
+    for index in 0..diff.deltas().len() {
+        let Some(mut patch) = git2::Patch::from_diff(&diff, index)? else {
+            continue;
+        };
+        if patch.delta().flags().is_binary() {
+            return Ok(format!("{} is a binary file", file_path));
+        }
+        let buf = patch.to_buf()?;
+        return Ok(String::from_utf8_lossy(&buf).to_string());
+    }
+    Ok(format!("File '{}' not found in diff", file_path))
+}
+
+fn index_diff<'r>(
+    repo: &'r Repository,
+    staged: bool,
+    opts: &mut git2::DiffOptions,
+) -> Result<git2::Diff<'r>, git2::Error> {
+    if staged {
+        // an unborn HEAD diffs against the empty tree
+        let head_tree = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+        repo.diff_tree_to_index(head_tree.as_ref(), None, Some(opts))
+    } else {
+        opts.include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .show_untracked_content(true);
+        repo.diff_index_to_workdir(None, Some(opts))
+    }
+}
+
+fn range_diff<'r>(
+    repo: &'r Repository,
+    old: Oid,
+    new: Oid,
+    opts: Option<&mut git2::DiffOptions>,
+) -> Result<git2::Diff<'r>, git2::Error> {
+    let old_tree = repo.find_commit(old)?.tree()?;
+    let new_tree = repo.find_commit(new)?.tree()?;
+    repo.diff_tree_to_tree(Some(&old_tree), Some(&new_tree), opts)
+}
+
 pub fn collect_paths_bg(
     repo_path: String,
 ) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
@@ -212,39 +450,7 @@ pub fn get_changed_files_bg(
     let commit_tree = commit.tree()?;
     let diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&commit_tree), None)?;
 
-    diff.foreach(
-        &mut |delta, _| {
-            let path = delta
-                .new_file()
-                .path()
-                .or(delta.old_file().path())
-                .and_then(|p| p.to_str())
-                .unwrap_or("unknown")
-                .to_string();
-
-            let old_oid = {
-                let id = delta.old_file().id();
-                if id.is_zero() { None } else { Some(id) }
-            };
-            let new_oid = {
-                let id = delta.new_file().id();
-                if id.is_zero() { None } else { Some(id) }
-            };
-
-            files.push(ChangedFile {
-                path,
-                status: delta.status(),
-                old_oid,
-                new_oid,
-            });
-            true
-        },
-        None,
-        None,
-        None,
-    )?;
-
-    Ok(files)
+    changed_files_of(&diff)
 }
 
 fn is_binary_check(repo: &Repository, commit: &git2::Commit, file_path: &str) -> bool {
@@ -281,196 +487,693 @@ fn file_size(repo: &Repository, commit: &git2::Commit, file_path: &str) -> Optio
     Some(blob.size())
 }
 
-fn recompute_bg(repo_path: String, search_path: Option<String>) -> GraphData {
-    let Ok(repo) = Repository::open(&repo_path) else {
-        return GraphData {
-            nodes: Vec::new(),
-            edges: Vec::new(),
-            max_lane: 0,
-            content_height: px(0.0),
-        };
-    };
-
-    let Ok(mut revwalk) = repo.revwalk() else {
-        return GraphData {
-            nodes: Vec::new(),
-            edges: Vec::new(),
-            max_lane: 0,
-            content_height: px(0.0),
-        };
-    };
-
-    revwalk
-        .set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)
-        .ok();
-    revwalk.push_head().ok();
-
-    let mut nodes = Vec::new();
-    let mut lane_manager = LaneManager::new();
-    let mut edge_manager = EdgeManager::new();
-    let mut color_manager = ColorManager::new(VEC_COLORS.to_vec());
-    let mut history_oids_manager = HistoryOidManager::new();
-    let mut max_lane = 0;
+/// Shared flag a background walk polls, so a newer request can stop it early.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
 
-    let oids: Vec<Oid> = revwalk.take(LIMIT_ROW * 5).filter_map(|o| o.ok()).collect();
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-    for oid in &oids {
-        let Ok(commit) = repo.find_commit(*oid) else {
-            continue;
-        };
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
 
-        if let Some(ref sp) = search_path {
-            let parent = commit.parent(0).ok();
-            let old_tree = parent.as_ref().and_then(|p| p.tree().ok());
-            let new_tree = commit.tree().ok();
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
 
-            let mut opts = git2::DiffOptions::new();
-            opts.pathspec(sp);
+/// Layout state carried from one page of the graph to the next, so lanes,
+/// pending edges and colors continue exactly where the previous page stopped.
+pub struct LayoutState {
+    // the recompute this state belongs to, pages from older ones are dropped
+    generation: u64,
+    layout: GraphLayout,
+    resume_after: Option<Oid>,
+    // commits taken from the revwalk so far, drawn or not
+    walked: usize,
+    // set by the first page, later pages carry on where it points
+    cursor: Option<WalkCursor>,
+    // built on the first page and reused so later pages badge the same refs
+    tips: Vec<Oid>,
+    ref_index: Option<RefIndex>,
+    stash_index: Option<StashIndex>,
+}
 
-            let diff =
-                repo.diff_tree_to_tree(old_tree.as_ref(), new_tree.as_ref(), Some(&mut opts));
+impl Default for LayoutState {
+    fn default() -> Self {
+        Self::new(VEC_COLORS.len(), 0)
+    }
+}
 
-            let Ok(diff) = diff else { continue };
-            if diff.deltas().len() == 0 {
-                continue;
-            }
+impl LayoutState {
+    pub fn new(palette_len: usize, generation: u64) -> Self {
+        Self {
+            generation,
+            layout: GraphLayout::new(palette_len),
+            resume_after: None,
+            walked: 0,
+            cursor: None,
+            tips: Vec::new(),
+            ref_index: None,
+            stash_index: None,
         }
+    }
 
-        if nodes.len() >= LIMIT_ROW {
-            break;
-        }
+    /// Whether `oid` is still ahead in the walk, on a page not loaded yet.
+    fn walks_later(&self, oid: &Oid) -> bool {
+        self.cursor
+            .as_ref()
+            .is_some_and(|cursor| cursor.oids[cursor.next..].contains(oid))
+    }
 
-        let parents: Vec<Oid> = commit.parents().map(|p| p.id()).collect();
-        let lane = lane_manager.assign_commit(oid, &parents);
-        let color = color_manager.get_color(&lane);
+    fn checkpoint(&self) -> Option<Checkpoint> {
+        Some(Checkpoint {
+            after: self.resume_after?,
+            walked: self.walked,
+            frontier: self.layout.frontier(),
+        })
+    }
+}
 
-        let index = nodes.len();
-        let pos = Point::new(
-            (START_X + (lane as f32) * LANE_WIDTH).into(),
-            (COMMIT_HEIGHT * index as f32).into(),
-        );
+/// Where the layout stood at the end of a page, kept so a refresh can tell
+/// where its new prefix joins the rows already laid out.
+#[derive(Clone, Debug)]
+struct Checkpoint {
+    after: Oid,
+    walked: usize,
+    frontier: Frontier,
+}
 
-        if lane > max_lane {
-            max_lane = lane;
-        }
+/// The commits of one walk in revwalk order and how far the pages have got
+/// through them. The topological sort reads all of history before it yields
+/// the first commit anyway, so the order is kept instead of walking it again
+/// up to the resume point for every page.
+#[derive(Clone, Debug, Default)]
+struct WalkCursor {
+    oids: Arc<[Oid]>,
+    next: usize,
+}
 
-        let current_edge_point = Point::new(pos.x + SIZE / 2.0, pos.y + SIZE / 2.0);
-
-        if let Some(history_oids) = history_oids_manager.get(oid) {
-            for history in history_oids {
-                if history.edge_point.x > current_edge_point.x {
-                    edge_manager.add(history.edge_point, current_edge_point, history.color);
-                    if history.lane > 0 {
-                        color_manager.remove_lane_color(&history.lane);
-                    }
-                } else if history.edge_point.x < current_edge_point.x {
-                    edge_manager.add(current_edge_point, history.edge_point, color);
-                } else {
-                    edge_manager.add(history.edge_point, current_edge_point, history.color);
-                }
+impl WalkCursor {
+    /// `None` when the walk can't be set up or `cancel` is set while the
+    /// commits are collected.
+    fn new(
+        repo: &Repository,
+        options: &WalkOptions,
+        tips: &[Oid],
+        cancel: &CancelToken,
+    ) -> Option<Self> {
+        let mut oids = Vec::new();
+        for oid in revwalk_for(repo, options, tips)?.flatten() {
+            if cancel.is_cancelled() {
+                return None;
             }
+            oids.push(oid);
         }
-
-        for parent in &parents {
-            history_oids_manager
-                .add_history(*parent, HistoryOid::new(current_edge_point, color, lane));
-        }
-
-        nodes.push(CommitNode::new(
-            *oid,
-            commit.message().unwrap_or_default().to_string(),
-            commit.author().email().unwrap_or_default().to_string(),
-            commit.time(),
-            parents,
-            pos,
-            color,
-        ));
-    }
-
-    let edges = edge_manager.take_edges();
-    let content_height = px(nodes.len() as f32 * COMMIT_HEIGHT + GAP_ROW);
-
-    GraphData {
-        nodes,
-        edges,
-        max_lane,
-        content_height,
+        Some(Self {
+            oids: oids.into(),
+            next: 0,
+        })
     }
 }
 
-#[derive(Clone)]
-pub struct CommitSelected {
-    pub oid: Oid,
-    pub message: String,
-    pub author: String,
-    pub timestamp: git2::Time,
-    pub parents: Vec<Oid>,
+/// What a recompute needs to know about the commits it walks.
+#[derive(Clone, Debug)]
+struct WalkOptions {
+    query: CommitQuery,
+    query_mode: QueryMode,
+    graph_mode: GraphMode,
+    ref_selection: RefSelection,
 }
 
-#[derive(Clone, Debug)]
-pub struct ChangedFile {
-    pub path: String,
-    pub status: git2::Delta,
-    pub old_oid: Option<git2::Oid>,
-    pub new_oid: Option<git2::Oid>,
+/// The old layout a refresh tries to keep.
+struct RefreshBase {
+    // rows already laid out, the prefix walk may go as far as these
+    rows: usize,
+    tips: Vec<Oid>,
+    ref_index: RefIndex,
+    checkpoints: Vec<Checkpoint>,
 }
 
-pub struct RepoPathChanged {
-    pub path: String,
+/// New commits laid out above the old rows from `checkpoint` on, which
+/// continue unchanged `added` commits further down the walk.
+struct Prefix {
+    data: GraphData,
+    state: LayoutState,
+    checkpoint: usize,
+    added: usize,
 }
 
-pub struct GraphData {
-    pub nodes: Vec<CommitNode>,
-    pub edges: Vec<Edge>,
-    pub max_lane: usize,
-    pub content_height: Pixels,
+/// A result sent from a background walk to the graph.
+enum GraphUpdate {
+    /// the next page of the current layout
+    Page(GraphData, LayoutState),
+    /// a first page that replaces everything laid out so far
+    Rebuilt(GraphData, LayoutState),
+    Prefix(Prefix),
+    /// only labels moved, the rows stay where they are
+    Relabel(LayoutState),
 }
 
-impl Clone for Garph {
-    fn clone(&self) -> Self {
-        Self {
-            repo: self.repo.clone(),
-            repo_path: self.repo_path.clone(),
-            search_path: self.search_path.clone(),
-            nodes: self.nodes.clone(),
-            edges: self.edges.clone(),
-            content_height: self.content_height,
-            max_lane: self.max_lane,
-            dirty: self.dirty,
-            pending_graph_rx: None,
+impl GraphUpdate {
+    fn generation(&self) -> u64 {
+        match self {
+            GraphUpdate::Page(_, state)
+            | GraphUpdate::Rebuilt(_, state)
+            | GraphUpdate::Relabel(state) => state.generation,
+            GraphUpdate::Prefix(prefix) => prefix.state.generation,
         }
     }
 }
 
-pub struct Garph {
-    repo: Rc<RefCell<Option<Repository>>>,
-    repo_path: Option<String>,
-    search_path: Option<String>,
-    nodes: Vec<CommitNode>,
-    edges: Vec<Edge>,
-    content_height: Pixels,
-    max_lane: usize,
-    pub dirty: bool,
-    pending_graph_rx: Option<Receiver<GraphData>>,
+fn empty_page(state: &LayoutState) -> GraphData {
+    GraphData {
+        nodes: Vec::new(),
+        edges: Vec::new(),
+        max_lane: state.layout.max_lane(),
+        rows: state.layout.rows(),
+        has_more: false,
+    }
 }
 
-impl Garph {
-    pub fn new(repo: Option<Repository>) -> Self {
-        Self {
-            repo: Rc::new(RefCell::new(repo)),
-            repo_path: None,
-            search_path: None,
-            nodes: Vec::new(),
-            edges: Vec::new(),
-            content_height: px(0.0),
-            max_lane: 0,
-            dirty: true,
-            pending_graph_rx: None,
+fn revwalk_for<'r>(
+    repo: &'r Repository,
+    options: &WalkOptions,
+    tips: &[Oid],
+) -> Option<git2::Revwalk<'r>> {
+    let mut revwalk = repo.revwalk().ok()?;
+    revwalk
+        .set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)
+        .ok();
+    if options.graph_mode == GraphMode::FirstParent {
+        revwalk.simplify_first_parent().ok();
+    }
+    for tip in tips {
+        revwalk.push(*tip).ok();
+    }
+    Some(revwalk)
+}
+
+/// Lay out commits from the state's cursor until `page_size` rows are placed
+/// or `stop` returns `true` after a commit. The flag tells which of the two
+/// happened.
+fn walk_page(
+    repo: &Repository,
+    options: &WalkOptions,
+    state: &mut LayoutState,
+    cancel: &CancelToken,
+    page_size: usize,
+    mut stop: impl FnMut(&Oid, &LayoutState) -> bool,
+) -> Option<(GraphData, bool)> {
+    let WalkOptions {
+        query,
+        query_mode,
+        graph_mode,
+        ..
+    } = options;
+    let ref_index = state
+        .ref_index
+        .take()
+        .unwrap_or_else(|| RefIndex::build(repo));
+    let stash_index = state
+        .stash_index
+        .take()
+        .unwrap_or_else(|| StashIndex::build(repo));
+
+    let mut cursor = state.cursor.take().unwrap_or_default();
+
+    let mut nodes = Vec::new();
+    let mut edges = Vec::new();
+    let mut has_more = false;
+    let mut stopped = false;
+
+    let oids = cursor.oids.clone();
+    for (scanned, &oid) in oids[cursor.next..].iter().enumerate() {
+        if cancel.is_cancelled() {
+            return None;
+        }
+        if nodes.len() >= page_size || scanned >= page_size * 5 {
+            has_more = true;
+            break;
+        }
+        cursor.next += 1;
+        state.resume_after = Some(oid);
+        state.walked += 1;
+
+        // the index and untracked commits of a stash are reached but not drawn
+        if !stash_index.is_helper(&oid)
+            && let Ok(commit) = repo.find_commit(oid)
+        {
+            let parents: Vec<Oid> = commit.parents().map(|p| p.id()).collect();
+            let stash = stash_index.get(&oid).map(|entry| entry.index);
+            // a stash hangs off its base commit only
+            let linked = if stash.is_some() {
+                GraphMode::FirstParent.parents(&parents)
+            } else {
+                graph_mode.parents(&parents)
+            };
+            let matched = query.is_empty() || query.matches(repo, &commit);
+            let shown = graph_mode.shows(!ref_index.get(&oid).is_empty(), parents.len());
+
+            if (!matched && *query_mode == QueryMode::Filter) || !shown {
+                state.layout.skip(oid, linked);
+            } else {
+                let placed = state.layout.place(oid, linked, ref_index.branch(&oid));
+                edges.extend(placed.edges);
+
+                nodes.push(
+                    CommitNode::new(
+                        oid,
+                        commit.message().unwrap_or_default().to_string(),
+                        author_name(&commit.author()),
+                        commit.time(),
+                        parents,
+                        placed.cell,
+                        placed.color,
+                    )
+                    .with_refs(ref_index.get(&oid).to_vec())
+                    .with_email(commit.author().email().unwrap_or_default().to_string())
+                    .with_matched(matched)
+                    .with_stash(stash),
+                );
+            }
+        }
+
+        if stop(&oid, state) {
+            stopped = true;
+            break;
         }
     }
+    state.cursor = Some(cursor);
+    state.ref_index = Some(ref_index);
+    state.stash_index = Some(stash_index);
+
+    Some((
+        GraphData {
+            nodes,
+            edges,
+            max_lane: state.layout.max_lane(),
+            rows: state.layout.rows(),
+            has_more,
+        },
+        stopped,
+    ))
+}
 
-    pub fn repo(&self) -> Rc<RefCell<Option<Repository>>> {
-        self.repo.clone()
+/// Display name of a signature, the email when the name is empty.
+fn author_name(author: &git2::Signature) -> String {
+    match author.name().map(str::trim) {
+        Some(name) if !name.is_empty() => name.to_string(),
+        _ => author.email().unwrap_or_default().to_string(),
+    }
+}
+
+fn load_page_bg(
+    repo_path: String,
+    options: WalkOptions,
+    mut state: LayoutState,
+    cancel: &CancelToken,
+) -> Option<GraphUpdate> {
+    let first = state.cursor.is_none();
+    let update = |data, state| {
+        if first {
+            GraphUpdate::Rebuilt(data, state)
+        } else {
+            GraphUpdate::Page(data, state)
+        }
+    };
+
+    let Ok(repo) = Repository::open(&repo_path) else {
+        return Some(update(empty_page(&state), state));
+    };
+    if first {
+        state.tips = options.ref_selection.tips(&repo);
+        let Some(cursor) = WalkCursor::new(&repo, &options, &state.tips, cancel) else {
+            if cancel.is_cancelled() {
+                return None;
+            }
+            return Some(update(empty_page(&state), state));
+        };
+        state.cursor = Some(cursor);
+    }
+
+    let (data, _) = walk_page(&repo, &options, &mut state, cancel, PAGE_SIZE, |_, _| false)?;
+    Some(update(data, state))
+}
+
+/// Commits reachable from `tips` but not from `old_tips`, or `None` when some
+/// of the old history is gone (a reset, a deleted branch) and the rows laid
+/// out for it can't be kept.
+fn new_commits(
+    repo: &Repository,
+    options: &WalkOptions,
+    old_tips: &[Oid],
+    tips: &[Oid],
+) -> Option<HashSet<Oid>> {
+    let moved: Vec<&Oid> = tips.iter().filter(|t| !old_tips.contains(t)).collect();
+    for old in old_tips.iter().filter(|t| !tips.contains(t)) {
+        let kept = moved
+            .iter()
+            .any(|tip| repo.graph_descendant_of(**tip, *old).unwrap_or(false));
+        if !kept {
+            return None;
+        }
+    }
+
+    let mut revwalk = revwalk_for(repo, options, tips)?;
+    for old in old_tips {
+        revwalk.hide(*old).ok()?;
+    }
+    Some(revwalk.filter_map(|o| o.ok()).collect())
+}
+
+/// Bring the graph up to date with the refs without walking all of history
+/// again. Only commits above the first point where the new layout joins the
+/// old one are laid out; when no such point is found within the rows
+/// already loaded the result is an ordinary first page.
+fn refresh_bg(
+    repo_path: String,
+    options: WalkOptions,
+    base: RefreshBase,
+    mut state: LayoutState,
+    cancel: &CancelToken,
+) -> Option<GraphUpdate> {
+    let Ok(repo) = Repository::open(&repo_path) else {
+        return Some(GraphUpdate::Rebuilt(empty_page(&state), state));
+    };
+    state.tips = options.ref_selection.tips(&repo);
+    let ref_index = RefIndex::build(&repo);
+    let decorations = options.graph_mode == GraphMode::SimplifyByDecoration;
+    let mut relabeled = ref_index.layout_changes(&base.ref_index, decorations);
+    state.ref_index = Some(ref_index);
+    state.stash_index = Some(StashIndex::build(&repo));
+
+    if state.tips == base.tips && relabeled.is_empty() {
+        return Some(GraphUpdate::Relabel(state));
+    }
+
+    let cursor = WalkCursor::new(&repo, &options, &state.tips, cancel)?;
+    // refs on commits outside the walk lay out no row, e.g. remote branches
+    // that a fetch moved while only local branches are shown
+    if !relabeled.is_empty() {
+        let walked: HashSet<&Oid> = cursor.oids.iter().collect();
+        relabeled.retain(|oid| walked.contains(oid));
+    }
+    state.cursor = Some(cursor);
+    if state.tips == base.tips && relabeled.is_empty() {
+        return Some(GraphUpdate::Relabel(state));
+    }
+
+    let added = new_commits(&repo, &options, &base.tips, &state.tips);
+
+    let Some(added) = added else {
+        let (data, _) = walk_page(&repo, &options, &mut state, cancel, PAGE_SIZE, |_, _| false)?;
+        return Some(GraphUpdate::Rebuilt(data, state));
+    };
+
+    let mut added_seen = 0;
+    let mut relabeled_seen = 0;
+    let mut joined = None;
+    let (data, stopped) = walk_page(
+        &repo,
+        &options,
+        &mut state,
+        cancel,
+        base.rows.max(PAGE_SIZE) + added.len(),
+        |oid, state| {
+            if relabeled.contains(oid) {
+                relabeled_seen += 1;
+            }
+            if added.contains(oid) {
+                added_seen += 1;
+                return false;
+            }
+            if added_seen < added.len() || relabeled_seen < relabeled.len() {
+                return false;
+            }
+            let old_walked = state.walked - added_seen;
+            joined = base.checkpoints.iter().position(|cp| {
+                cp.after == *oid && cp.walked == old_walked && state.layout.continues(&cp.frontier)
+            });
+            joined.is_some()
+        },
+    )?;
+
+    match joined {
+        Some(checkpoint) if stopped => Some(GraphUpdate::Prefix(Prefix {
+            data,
+            state,
+            checkpoint,
+            added: added.len(),
+        })),
+        _ => Some(GraphUpdate::Rebuilt(data, state)),
+    }
+}
+
+/// Converts grid cells to pixels at render time, scaled by the zoom level.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct GraphMetrics {
+    zoom: f32,
+    // rows drawn above the first commit, e.g. the uncommitted changes row
+    row_offset: usize,
+}
+
+impl GraphMetrics {
+    pub(crate) fn new(zoom: f32, row_offset: usize) -> Self {
+        Self { zoom, row_offset }
+    }
+
+    pub(crate) fn row_height(&self) -> f32 {
+        COMMIT_HEIGHT * self.zoom
+    }
+
+    pub(crate) fn node_size(&self) -> Pixels {
+        px(NODE_SIZE * self.zoom)
+    }
+
+    pub(crate) fn x(&self, lane: usize) -> Pixels {
+        px((START_X + lane as f32 * LANE_WIDTH) * self.zoom)
+    }
+
+    pub(crate) fn y(&self, row: usize) -> Pixels {
+        px((row + self.row_offset) as f32 * self.row_height())
+    }
+
+    /// Point where edges attach to the node drawn in `cell`.
+    pub(crate) fn anchor(&self, cell: GraphCell) -> Point<Pixels> {
+        let half = self.node_size() / 2.0;
+        Point::new(
+            self.x(cell.lane) + half,
+            self.y(cell.row) + half + px(EDGE_ANCHOR_Y * self.zoom),
+        )
+    }
+
+    /// Anchor of the uncommitted changes node, which sits in the top row.
+    fn worktree_anchor(&self, lane: usize) -> Point<Pixels> {
+        let half = self.node_size() / 2.0;
+        Point::new(self.x(lane) + half, half + px(EDGE_ANCHOR_Y * self.zoom))
+    }
+
+    pub(crate) fn text_left(&self, max_lane: usize) -> Pixels {
+        self.x(max_lane) + px(TEXT_GAP * self.zoom)
+    }
+
+    pub(crate) fn content_height(&self, rows: usize) -> Pixels {
+        self.y(rows) + px(GAP_ROW)
+    }
+
+    /// Commit rows intersecting the pixel range `top..=bottom`.
+    fn rows_between(&self, top: Pixels, bottom: Pixels) -> (usize, usize) {
+        let first = (f32::from(top) / self.row_height()).floor().max(0.0) as usize;
+        let last = (f32::from(bottom) / self.row_height()).ceil().max(0.0) as usize;
+        (
+            first.saturating_sub(self.row_offset),
+            last.saturating_sub(self.row_offset),
+        )
+    }
+}
+
+#[derive(Clone)]
+pub struct CommitSelected {
+    pub oid: Oid,
+    pub message: String,
+    pub author: String,
+    pub timestamp: git2::Time,
+    pub parents: Vec<Oid>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ChangedFile {
+    pub path: String,
+    pub status: git2::Delta,
+    pub old_oid: Option<git2::Oid>,
+    pub new_oid: Option<git2::Oid>,
+}
+
+pub struct RepoPathChanged {
+    pub path: String,
+}
+
+/// The uncommitted changes row above HEAD was clicked.
+#[derive(Clone, Debug)]
+pub struct WorkingTreeSelected;
+
+/// A stash row was clicked.
+#[derive(Clone, Debug)]
+pub struct StashSelected {
+    pub entry: StashEntry,
+}
+
+/// A second commit was ctrl-clicked while another was selected. `old` is
+/// the one further down the graph.
+#[derive(Clone, Debug)]
+pub struct RangeSelected {
+    pub old: Oid,
+    pub new: Oid,
+}
+
+/// A branch badge on a graph row was clicked.
+#[derive(Clone, Debug)]
+pub struct RefBadgeClicked {
+    pub name: String,
+    pub kind: RefKind,
+}
+
+pub struct GraphData {
+    pub nodes: Vec<CommitNode>,
+    pub edges: Vec<Edge>,
+    pub max_lane: usize,
+    /// Rows laid out so far, including earlier pages.
+    pub rows: usize,
+    /// `true` when the revwalk stopped at the page limit and older history remains.
+    pub has_more: bool,
+}
+
+impl Clone for Garph {
+    fn clone(&self) -> Self {
+        Self {
+            repo: self.repo.clone(),
+            repo_path: self.repo_path.clone(),
+            query: self.query.clone(),
+            query_mode: self.query_mode,
+            graph_mode: self.graph_mode,
+            ref_selection: self.ref_selection,
+            nodes: self.nodes.clone(),
+            edges: self.edges.clone(),
+            rows: self.rows,
+            max_lane: self.max_lane,
+            zoom: self.zoom,
+            palette: self.palette.clone(),
+            uncommitted: self.uncommitted,
+            head_cell: self.head_cell,
+            has_more: self.has_more,
+            dirty: self.dirty,
+            generation: self.generation,
+            cancel: CancelToken::new(),
+            layout_state: None,
+            scroll_handle: ScrollHandle::new(),
+            graph_job: None,
+            checkpoints: Vec::new(),
+            focus_handle: self.focus_handle.clone(),
+            selected: self.selected,
+            range_end: self.range_end,
+            pending_open: None,
+            seeking: None,
+            follow: self.follow,
+            related: self.related.clone(),
+            columns: self.columns,
+            resizing: None,
+        }
+    }
+}
+
+pub struct Garph {
+    repo: Rc<RefCell<Option<Repository>>>,
+    repo_path: Option<String>,
+    query: CommitQuery,
+    query_mode: QueryMode,
+    graph_mode: GraphMode,
+    ref_selection: RefSelection,
+    nodes: Vec<CommitNode>,
+    edges: Vec<Edge>,
+    rows: usize,
+    max_lane: usize,
+    zoom: f32,
+    palette: Vec<u32>,
+    // changed files in the working tree, drawn as a row above HEAD when non-zero
+    uncommitted: usize,
+    head_cell: Option<GraphCell>,
+    has_more: bool,
+    pub dirty: bool,
+    // bumped on every recompute so pages from an older walk are ignored
+    generation: u64,
+    // stops the walk of the current generation once a newer one starts
+    cancel: CancelToken,
+    layout_state: Option<LayoutState>,
+    scroll_handle: ScrollHandle,
+    graph_job: Option<Job>,
+    // layout snapshots at the end of each page, newest last
+    checkpoints: Vec<Checkpoint>,
+    focus_handle: FocusHandle,
+    // keyboard cursor, kept by oid so it survives refreshes
+    selected: Option<Oid>,
+    // the other end of a ctrl-click range, `selected` being the first
+    range_end: Option<Oid>,
+    // commit to open once the refresh in flight has laid it out
+    pending_open: Option<Oid>,
+    // commit further down the history that pages are loaded until it shows
+    seeking: Option<Oid>,
+    // parents of the selected merge its ancestry runs through
+    follow: ParentFollow,
+    // ancestors and descendants of the selection, everything else is dimmed
+    related: Option<HashSet<Oid>>,
+    columns: Columns,
+    // column being resized, with the mouse x and the width when the drag started
+    resizing: Option<(Column, Pixels, f32)>,
+}
+
+impl Garph {
+    pub fn new(repo: Option<Repository>, cx: &mut Context<Self>) -> Self {
+        Self {
+            repo: Rc::new(RefCell::new(repo)),
+            repo_path: None,
+            query: CommitQuery::default(),
+            query_mode: QueryMode::default(),
+            graph_mode: GraphMode::default(),
+            ref_selection: RefSelection::default(),
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            rows: 0,
+            max_lane: 0,
+            zoom: 1.0,
+            palette: VEC_COLORS.to_vec(),
+            uncommitted: 0,
+            head_cell: None,
+            has_more: false,
+            dirty: true,
+            generation: 0,
+            cancel: CancelToken::new(),
+            layout_state: None,
+            scroll_handle: ScrollHandle::new(),
+            graph_job: None,
+            checkpoints: Vec::new(),
+            focus_handle: cx.focus_handle(),
+            selected: None,
+            range_end: None,
+            pending_open: None,
+            seeking: None,
+            follow: ParentFollow::All,
+            related: None,
+            columns: Columns::default(),
+            resizing: None,
+        }
+    }
+
+    pub fn repo(&self) -> Rc<RefCell<Option<Repository>>> {
+        self.repo.clone()
     }
 
     pub fn update_repo(
@@ -481,8 +1184,10 @@ impl Garph {
         let repo = git2::Repository::open(path)?;
         *self.repo.borrow_mut() = Some(repo);
         self.repo_path = Some(path.to_string());
+        self.set_selected(None);
+        self.range_end = None;
         self.dirty = true;
-        self.spawn_recompute();
+        self.spawn_recompute(cx);
         cx.emit(RepoPathChanged {
             path: path.to_string(),
         });
@@ -490,9 +1195,17 @@ impl Garph {
         Ok(())
     }
 
-    pub fn mark_dirty(&mut self) {
+    /// Refs or history changed on disk, e.g. after a checkout.
+    pub fn mark_dirty(&mut self, cx: &mut Context<Self>) {
         self.dirty = true;
-        self.spawn_recompute();
+        self.spawn_refresh(cx);
+    }
+
+    /// Refresh after `oid` was just created, e.g. by a commit, and open it
+    /// once the refresh brings it in.
+    pub fn mark_dirty_and_open(&mut self, oid: Oid, cx: &mut Context<Self>) {
+        self.pending_open = Some(oid);
+        self.mark_dirty(cx);
     }
 
     pub fn repo_path(&self) -> Option<&str> {
@@ -503,625 +1216,576 @@ impl Garph {
         self.nodes.len()
     }
 
-    pub fn set_search_path(&mut self, path: Option<String>) {
-        self.search_path = path;
-        self.dirty = true;
-        self.spawn_recompute();
+    pub fn query(&self) -> &CommitQuery {
+        &self.query
     }
 
-    fn spawn_recompute(&mut self) {
-        let Some(repo_path) = self.repo_path.clone() else {
-            return;
-        };
-        let search_path = self.search_path.clone();
-        let (tx, rx) = std::sync::mpsc::channel();
-        self.pending_graph_rx = Some(rx);
-        self.nodes.clear();
-        self.edges.clear();
-
-        std::thread::spawn(move || {
-            let data = recompute_bg(repo_path, search_path);
-            let _ = tx.send(data);
-        });
+    pub fn query_mode(&self) -> QueryMode {
+        self.query_mode
     }
 
-    fn poll_graph(&mut self) {
-        if let Some(rx) = &self.pending_graph_rx
-            && let Ok(data) = rx.try_recv()
-        {
-            self.nodes = data.nodes;
-            self.edges = data.edges;
-            self.max_lane = data.max_lane;
-            self.content_height = data.content_height;
-            self.dirty = false;
-            self.pending_graph_rx = None;
+    /// Filter or highlight the graph by `query`; an empty query shows everything.
+    pub fn set_query(&mut self, query: CommitQuery, mode: QueryMode, cx: &mut Context<Self>) {
+        if self.query == query && self.query_mode == mode {
+            return;
         }
+        self.query = query;
+        self.query_mode = mode;
+        self.dirty = true;
+        self.spawn_recompute(cx);
     }
 
-    pub fn compute_commit_diff(
-        &self,
-        oid: &git2::Oid,
-    ) -> Result<String, Box<dyn std::error::Error>> {
-        let repo = self.repo.borrow();
-        let repo = repo.as_ref().ok_or("No repository loaded")?;
-
-        let commit = repo.find_commit(*oid)?;
-        let parents: Vec<git2::Commit> = commit.parents().collect();
-
-        if parents.is_empty() {
-            // Initial commit - show all files as added
-            let tree = commit.tree()?;
-            let mut diff_lines = vec![format!("Initial commit - {} files added", tree.len())];
-            diff_lines.push("".to_string());
-
-            for entry in tree.iter() {
-                let name = entry.name().unwrap_or_default();
-                if Self::is_binary_file(repo, &commit, name) {
-                    diff_lines.push(format!(
-                        "+++ a/{} (binary file, size: {})",
-                        name,
-                        Self::get_file_size(repo, &commit, name).unwrap_or(0)
-                    ));
-                } else {
-                    diff_lines.push(format!("+++ a/{}", name));
-                }
-            }
+    pub fn graph_mode(&self) -> GraphMode {
+        self.graph_mode
+    }
 
-            return Ok(diff_lines.join("\n"));
+    pub fn set_graph_mode(&mut self, graph_mode: GraphMode, cx: &mut Context<Self>) {
+        if self.graph_mode == graph_mode {
+            return;
         }
+        self.graph_mode = graph_mode;
+        self.dirty = true;
+        self.spawn_recompute(cx);
+    }
 
-        // For now, just show diff with the first parent
-        // For merge commits, we could show diff with all parents
-        let parent = &parents[0];
+    pub fn ref_selection(&self) -> RefSelection {
+        self.ref_selection
+    }
 
-        // Get the tree for this commit and its parent
-        let commit_tree = commit.tree()?;
-        let parent_tree = parent.tree()?;
+    pub fn set_ref_selection(&mut self, ref_selection: RefSelection, cx: &mut Context<Self>) {
+        if self.ref_selection == ref_selection {
+            return;
+        }
+        self.ref_selection = ref_selection;
+        self.dirty = true;
+        self.spawn_recompute(cx);
+    }
 
-        // Compute the diff
-        let diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&commit_tree), None)?;
+    pub fn has_more(&self) -> bool {
+        self.has_more
+    }
 
-        // Format the diff as a string with limits to prevent memory exhaustion
-        let file_count = std::cell::RefCell::new(0usize);
-        let line_count = std::cell::RefCell::new(0usize);
-        let current_file_lines = std::cell::RefCell::new(0usize);
-        let diff_lines = RefCell::new(Vec::new());
-        let mut diff_stats = Vec::new();
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
 
-        diff.foreach(
-            &mut |delta, _| {
-                let mut file_count = file_count.borrow_mut();
-                if *file_count >= MAX_FILES_TO_SHOW {
-                    return false;
-                }
+    pub fn set_zoom(&mut self, zoom: f32, cx: &mut Context<Self>) {
+        self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        cx.notify();
+    }
 
-                let file_path = delta
-                    .new_file()
-                    .path()
-                    .or(delta.old_file().path())
-                    .and_then(|p| p.to_str())
-                    .unwrap_or("unknown");
-
-                // Check if file is binary or too large
-                let is_binary = Self::is_binary_file(repo, &commit, file_path);
-
-                match delta.status() {
-                    git2::Delta::Added => {
-                        if is_binary {
-                            let size = Self::get_file_size(repo, &commit, file_path).unwrap_or(0);
-                            diff_stats
-                                .push(format!("+++ a/{} (binary file, size: {})", file_path, size));
-                        } else {
-                            diff_stats.push(format!("+++ a/{}", file_path));
-                        }
-                    }
-                    git2::Delta::Deleted => {
-                        let is_binary_old = Self::is_binary_file(repo, parent, file_path);
-                        if is_binary_old {
-                            let size = Self::get_file_size(repo, parent, file_path).unwrap_or(0);
-                            diff_stats
-                                .push(format!("--- a/{} (binary file, size: {})", file_path, size));
-                        } else {
-                            diff_stats.push(format!("--- a/{}", file_path));
-                        }
-                    }
-                    git2::Delta::Modified => {
-                        if is_binary {
-                            let size = Self::get_file_size(repo, &commit, file_path).unwrap_or(0);
-                            diff_stats.push(format!("--- a/{} (binary file)", file_path));
-                            diff_stats
-                                .push(format!("+++ b/{} (binary file, size: {})", file_path, size));
-                        } else {
-                            diff_stats.push(format!("--- a/{}", file_path));
-                            diff_stats.push(format!("+++ b/{}", file_path));
-                        }
-                    }
-                    git2::Delta::Renamed => {
-                        let old_path = delta
-                            .old_file()
-                            .path()
-                            .and_then(|p| p.to_str())
-                            .unwrap_or("unknown");
-                        let is_binary_old = Self::is_binary_file(repo, parent, old_path);
-                        if is_binary_old {
-                            diff_stats.push(format!("--- a/{} (binary file)", old_path));
-                        } else {
-                            diff_stats.push(format!("--- a/{}", old_path));
-                        }
-                        if is_binary {
-                            let size = Self::get_file_size(repo, &commit, file_path).unwrap_or(0);
-                            diff_stats
-                                .push(format!("+++ b/{} (binary file, size: {})", file_path, size));
-                        } else {
-                            diff_stats.push(format!("+++ b/{}", file_path));
-                        }
-                    }
-                    git2::Delta::Copied => {
-                        let old_path = delta
-                            .old_file()
-                            .path()
-                            .and_then(|p| p.to_str())
-                            .unwrap_or("unknown");
-                        let is_binary_old = Self::is_binary_file(repo, parent, old_path);
-                        if is_binary_old {
-                            diff_stats.push(format!("--- a/{} (binary file)", old_path));
-                        } else {
-                            diff_stats.push(format!("--- a/{}", old_path));
-                        }
-                        if is_binary {
-                            let size = Self::get_file_size(repo, &commit, file_path).unwrap_or(0);
-                            diff_stats
-                                .push(format!("+++ b/{} (binary file, size: {})", file_path, size));
-                        } else {
-                            diff_stats.push(format!("+++ b/{}", file_path));
-                        }
-                    }
-                    _ => {}
-                }
-                *file_count += 1;
-                *current_file_lines.borrow_mut() = 0;
-                true
-            },
-            None,
-            Some(&mut |_: git2::DiffDelta<'_>, hunk: git2::DiffHunk<'_>| {
-                let mut line_count = line_count.borrow_mut();
-                if *line_count >= MAX_TOTAL_LINES {
-                    return false;
-                }
-                *line_count += 1;
-                diff_lines
-                    .borrow_mut()
-                    .push(format!("@@ {} @@", String::from_utf8_lossy(hunk.header())));
-                true
-            }),
-            Some(&mut |_: git2::DiffDelta<'_>,
-                       _: Option<git2::DiffHunk<'_>>,
-                       line: git2::DiffLine<'_>| {
-                let mut line_count = line_count.borrow_mut();
-                let mut current_file_lines = current_file_lines.borrow_mut();
-
-                if *line_count >= MAX_TOTAL_LINES || *current_file_lines >= MAX_LINES_PER_FILE {
-                    return false;
-                }
+    /// The graph as laid out so far, for exports. It follows the active query
+    /// and ref selection, since those decided what was walked.
+    pub fn snapshot(&self) -> GraphData {
+        GraphData {
+            nodes: self.nodes.clone(),
+            edges: self.edges.clone(),
+            max_lane: self.max_lane,
+            rows: self.rows,
+            has_more: self.has_more,
+        }
+    }
 
-                let line_content = String::from_utf8_lossy(line.content());
-                let origin = line.origin();
-                let prefix = match origin {
-                    '+' => "+",
-                    '-' => "-",
-                    ' ' => " ",
-                    _ => " ",
-                };
-                diff_lines
-                    .borrow_mut()
-                    .push(format!("{}{}", prefix, line_content.trim_end()));
+    pub fn palette(&self) -> &[u32] {
+        &self.palette
+    }
 
-                *line_count += 1;
-                *current_file_lines += 1;
-                true
-            }),
-        )?;
-
-        // Combine stats and lines
-        let mut result = Vec::new();
-        result.extend(diff_stats);
-        if !result.is_empty() {
-            result.push("".to_string());
+    /// Replace the lane colors. Slots are picked by hashing branch names, so
+    /// a palette of a different length reshuffles colors and needs a relayout.
+    pub fn set_palette(&mut self, palette: Vec<u32>, cx: &mut Context<Self>) {
+        if palette.is_empty() || palette == self.palette {
+            return;
         }
+        let relayout = palette.len() != self.palette.len();
+        self.palette = palette;
+        if relayout {
+            self.dirty = true;
+            self.spawn_recompute(cx);
+        }
+        cx.notify();
+    }
 
-        let final_lines = diff_lines.into_inner();
-        result.extend(final_lines);
-
-        // Add truncation message if limits were hit
-        let file_count = file_count.into_inner();
-        let line_count = line_count.into_inner();
+    fn color(&self, slot: usize) -> u32 {
+        self.palette[slot % self.palette.len()]
+    }
 
-        if file_count >= MAX_FILES_TO_SHOW {
-            result.push("".to_string());
-            result.push(format!(
-                "... (showing first {} files, diff truncated)",
-                MAX_FILES_TO_SHOW
-            ));
+    /// Show an "Uncommitted changes" row above HEAD while `count` files differ.
+    pub fn set_uncommitted_count(&mut self, count: usize, cx: &mut Context<Self>) {
+        if self.uncommitted != count {
+            self.uncommitted = count;
+            cx.notify();
         }
+    }
 
-        if line_count >= MAX_TOTAL_LINES {
-            result.push("".to_string());
-            result.push(format!(
-                "... (showing first {} lines, diff truncated)",
-                MAX_TOTAL_LINES
-            ));
-        }
+    pub fn uncommitted_count(&self) -> usize {
+        self.uncommitted
+    }
 
-        Ok(result.join("\n"))
+    fn metrics(&self) -> GraphMetrics {
+        let row_offset = if self.uncommitted > 0 { 1 } else { 0 };
+        GraphMetrics::new(self.zoom, row_offset)
     }
 
-    pub fn compute_diff_between_commits(
-        &self,
-        old_oid: &git2::Oid,
-        new_oid: &git2::Oid,
-    ) -> Result<String, Box<dyn std::error::Error>> {
-        let repo = self.repo.borrow();
-        let repo = repo.as_ref().ok_or("No repository loaded")?;
+    fn clear_graph(&mut self) {
+        self.nodes.clear();
+        self.edges.clear();
+        self.checkpoints.clear();
+        self.max_lane = 0;
+        self.rows = 0;
+        self.head_cell = None;
+        self.has_more = false;
+    }
 
-        let old_commit = repo.find_commit(*old_oid)?;
-        let new_commit = repo.find_commit(*new_oid)?;
+    /// Start a new generation; the walk of the previous one stops at its
+    /// next commit so only one walk per repo runs at a time.
+    fn next_generation(&mut self) -> LayoutState {
+        self.cancel.cancel();
+        self.cancel = CancelToken::new();
+        self.generation += 1;
+        LayoutState::new(self.palette.len(), self.generation)
+    }
 
-        let old_tree = old_commit.tree()?;
-        let new_tree = new_commit.tree()?;
+    fn spawn_recompute(&mut self, cx: &mut Context<Self>) {
+        self.seeking = None;
+        self.clear_graph();
+        let state = self.next_generation();
+        self.spawn_page(state, cx);
+    }
 
-        let diff = repo.diff_tree_to_tree(Some(&old_tree), Some(&new_tree), None)?;
+    /// Update the graph after refs moved, keeping the rows that are still
+    /// valid on screen. Falls back to a full recompute while nothing is
+    /// laid out yet or a page is still loading.
+    fn spawn_refresh(&mut self, cx: &mut Context<Self>) {
+        let base = match &self.layout_state {
+            Some(state) if self.graph_job.is_none() => {
+                state.ref_index.clone().map(|ref_index| RefreshBase {
+                    rows: self.rows,
+                    tips: state.tips.clone(),
+                    ref_index,
+                    checkpoints: self.checkpoints.clone(),
+                })
+            }
+            _ => None,
+        };
+        let (Some(base), Some(repo_path)) = (base, self.repo_path.clone()) else {
+            self.spawn_recompute(cx);
+            return;
+        };
+        let state = self.next_generation();
+        let options = self.walk_options();
+        let cancel = self.cancel.clone();
+
+        self.graph_job = Some(Job::spawn(
+            cx,
+            move || refresh_bg(repo_path, options, base, state, &cancel),
+            Self::on_graph_update,
+        ));
+    }
 
-        // Format the diff as a string with limits to prevent memory exhaustion
-        let file_count = std::cell::RefCell::new(0usize);
-        let line_count = std::cell::RefCell::new(0usize);
-        let current_file_lines = std::cell::RefCell::new(0usize);
-        let diff_lines = RefCell::new(Vec::new());
-        let mut diff_stats = Vec::new();
+    /// Load the next page of history, continuing from the current layout state.
+    pub fn load_more(&mut self, cx: &mut Context<Self>) {
+        if self.graph_job.is_some() || !self.has_more {
+            return;
+        }
+        if let Some(state) = self.layout_state.take() {
+            self.spawn_page(state, cx);
+        }
+    }
 
-        diff.foreach(
-            &mut |delta, _| {
-                let mut file_count = file_count.borrow_mut();
-                if *file_count >= MAX_FILES_TO_SHOW {
-                    return false;
-                }
+    fn walk_options(&self) -> WalkOptions {
+        WalkOptions {
+            query: self.query.clone(),
+            query_mode: self.query_mode,
+            graph_mode: self.graph_mode,
+            ref_selection: self.ref_selection,
+        }
+    }
 
-                let file_path = delta
-                    .new_file()
-                    .path()
-                    .or(delta.old_file().path())
-                    .and_then(|p| p.to_str())
-                    .unwrap_or("unknown")
-                    .to_string();
-
-                match delta.status() {
-                    git2::Delta::Added => diff_stats.push(format!("+++ a/{}", file_path)),
-                    git2::Delta::Deleted => diff_stats.push(format!("--- a/{}", file_path)),
-                    git2::Delta::Modified => {
-                        diff_stats.push(format!("--- a/{}", file_path));
-                        diff_stats.push(format!("+++ b/{}", file_path));
-                    }
-                    git2::Delta::Renamed => {
-                        diff_stats.push(format!(
-                            "--- a/{}",
-                            delta
-                                .old_file()
-                                .path()
-                                .and_then(|p| p.to_str())
-                                .unwrap_or("unknown")
-                        ));
-                        diff_stats.push(format!("+++ b/{}", file_path));
-                    }
-                    git2::Delta::Copied => {
-                        diff_stats.push(format!(
-                            "--- a/{}",
-                            delta
-                                .old_file()
-                                .path()
-                                .and_then(|p| p.to_str())
-                                .unwrap_or("unknown")
-                        ));
-                        diff_stats.push(format!("+++ b/{}", file_path));
-                    }
-                    _ => {}
-                }
-                *file_count += 1;
-                *current_file_lines.borrow_mut() = 0;
-                true
-            },
-            None,
-            Some(&mut |_: git2::DiffDelta<'_>, hunk: git2::DiffHunk<'_>| {
-                let mut line_count = line_count.borrow_mut();
-                if *line_count >= MAX_TOTAL_LINES {
-                    return false;
-                }
-                *line_count += 1;
-                diff_lines
-                    .borrow_mut()
-                    .push(format!("@@ {} @@", String::from_utf8_lossy(hunk.header())));
-                true
-            }),
-            Some(&mut |_: git2::DiffDelta<'_>,
-                       _: Option<git2::DiffHunk<'_>>,
-                       line: git2::DiffLine<'_>| {
-                let mut line_count = line_count.borrow_mut();
-                let mut current_file_lines = current_file_lines.borrow_mut();
-
-                if *line_count >= MAX_TOTAL_LINES || *current_file_lines >= MAX_LINES_PER_FILE {
-                    return false;
-                }
+    fn spawn_page(&mut self, state: LayoutState, cx: &mut Context<Self>) {
+        let Some(repo_path) = self.repo_path.clone() else {
+            return;
+        };
+        let options = self.walk_options();
+        let cancel = self.cancel.clone();
+        self.layout_state = None;
+
+        self.graph_job = Some(Job::spawn(
+            cx,
+            move || load_page_bg(repo_path, options, state, &cancel),
+            Self::on_graph_update,
+        ));
+    }
 
-                let line_content = String::from_utf8_lossy(line.content());
-                let origin = line.origin();
-                let prefix = match origin {
-                    '+' => "+",
-                    '-' => "-",
-                    ' ' => " ",
-                    _ => " ",
-                };
-                diff_lines
-                    .borrow_mut()
-                    .push(format!("{}{}", prefix, line_content.trim_end()));
+    /// `update` is `None` when the walk was cancelled.
+    fn on_graph_update(&mut self, update: Option<GraphUpdate>, cx: &mut Context<Self>) {
+        self.graph_job = None;
+        let Some(update) = update else {
+            return;
+        };
+        if update.generation() != self.generation {
+            return;
+        }
+        match update {
+            GraphUpdate::Page(data, state) => self.apply_page(data, state),
+            GraphUpdate::Rebuilt(data, state) => {
+                self.clear_graph();
+                self.apply_page(data, state);
+            }
+            GraphUpdate::Prefix(prefix) => self.apply_prefix(prefix),
+            GraphUpdate::Relabel(fresh) => {
+                if let Some(state) = &mut self.layout_state {
+                    state.generation = fresh.generation;
+                    state.tips = fresh.tips;
+                    state.ref_index = fresh.ref_index;
+                    state.stash_index = fresh.stash_index;
+                }
+                self.relabel();
+            }
+        }
+        self.update_ancestry();
+        self.dirty = false;
+        // the first update of the refresh has the top of history, or the
+        // commit is filtered out and there is nothing to open
+        if let Some(oid) = self.pending_open.take() {
+            self.open_oid(oid, cx);
+        }
+        if let Some(oid) = self.seeking.take() {
+            self.seek_oid(oid, cx);
+        }
+    }
 
-                *line_count += 1;
-                *current_file_lines += 1;
-                true
-            }),
-        )?;
-
-        // Combine stats and lines
-        let mut result = Vec::new();
-        result.extend(diff_stats);
-        if !result.is_empty() {
-            result.push("".to_string());
+    fn apply_page(&mut self, data: GraphData, state: LayoutState) {
+        self.checkpoints.extend(state.checkpoint());
+        self.nodes.extend(data.nodes);
+        self.edges.extend(data.edges);
+        if self.head_cell.is_none() {
+            self.head_cell = Self::head_cell_of(&self.nodes);
         }
+        self.max_lane = self.max_lane.max(data.max_lane);
+        self.rows = data.rows;
+        self.has_more = data.has_more;
+        self.layout_state = Some(state);
+    }
 
-        let final_lines = diff_lines.into_inner();
-        result.extend(final_lines);
+    /// Put the freshly laid out prefix on top and move the rows it joins
+    /// down below it, along with the state later pages continue from.
+    fn apply_prefix(&mut self, prefix: Prefix) {
+        let (Some(mut rest), Some(joined)) = (
+            self.layout_state.take(),
+            self.checkpoints.get(prefix.checkpoint).cloned(),
+        ) else {
+            return;
+        };
+        let keep_from = joined.frontier.rows();
+        let shift = prefix.state.layout.rows() - keep_from;
+
+        let kept_nodes: Vec<CommitNode> = self
+            .nodes
+            .drain(..)
+            .filter(|n| n.cell.row >= keep_from)
+            .map(|mut n| {
+                n.cell = n.cell.shifted(shift);
+                n
+            })
+            .collect();
+        let kept_edges: Vec<Edge> = self
+            .edges
+            .drain(..)
+            .filter(|e| e.to.row >= keep_from)
+            .map(|mut e| {
+                e.from = e.from.shifted(shift);
+                e.to = e.to.shifted(shift);
+                e
+            })
+            .collect();
+        self.nodes = prefix.data.nodes;
+        self.nodes.extend(kept_nodes);
+        self.edges = prefix.data.edges;
+        self.edges.extend(kept_edges);
+        self.rows += shift;
+        self.max_lane = self.max_lane.max(prefix.data.max_lane);
+
+        self.checkpoints.drain(..prefix.checkpoint);
+        for checkpoint in &mut self.checkpoints {
+            checkpoint.frontier.shift_rows(shift);
+            checkpoint.walked += prefix.added;
+        }
 
-        // Add truncation message if limits were hit
-        let file_count = file_count.into_inner();
-        let line_count = line_count.into_inner();
+        rest.layout.shift_rows(shift);
+        rest.walked += prefix.added;
+        rest.generation = prefix.state.generation;
+        rest.tips = prefix.state.tips;
+        rest.ref_index = prefix.state.ref_index;
+        rest.stash_index = prefix.state.stash_index;
+        self.layout_state = Some(rest);
+        self.relabel();
+    }
 
-        if file_count >= MAX_FILES_TO_SHOW {
-            result.push("".to_string());
-            result.push(format!(
-                "... (showing first {} files, diff truncated)",
-                MAX_FILES_TO_SHOW
-            ));
+    /// Badge every row with the refs of the current layout state.
+    fn relabel(&mut self) {
+        let Some(state) = &self.layout_state else {
+            return;
+        };
+        let (Some(ref_index), Some(stash_index)) = (&state.ref_index, &state.stash_index) else {
+            return;
+        };
+        for node in &mut self.nodes {
+            node.refs = ref_index.get(&node.oid).to_vec();
+            node.stash = stash_index.get(&node.oid).map(|entry| entry.index);
         }
+        self.head_cell = Self::head_cell_of(&self.nodes);
+    }
 
-        if line_count >= MAX_TOTAL_LINES {
-            result.push("".to_string());
-            result.push(format!(
-                "... (showing first {} lines, diff truncated)",
-                MAX_TOTAL_LINES
-            ));
-        }
+    fn head_cell_of(nodes: &[CommitNode]) -> Option<GraphCell> {
+        nodes
+            .iter()
+            .find(|n| n.refs.iter().any(|r| r.kind == RefKind::Head))
+            .map(|n| n.cell)
+    }
 
-        Ok(result.join("\n"))
+    fn visible_range(&self) -> (Pixels, Pixels) {
+        let viewport = self.scroll_handle.bounds().size.height;
+        let scrolled = -self.scroll_handle.offset().y;
+        let margin = px(LOAD_MORE_MARGIN);
+        (scrolled - margin, scrolled + viewport + margin)
     }
 
-    pub fn get_changed_files(
-        &self,
-        oid: &git2::Oid,
-    ) -> Result<Vec<ChangedFile>, Box<dyn std::error::Error>> {
-        let repo = self.repo.borrow();
-        let repo = repo.as_ref().ok_or("No repository loaded")?;
+    fn near_bottom(&self) -> bool {
+        let viewport = self.scroll_handle.bounds().size.height;
+        let scrolled = -self.scroll_handle.offset().y;
+        scrolled + viewport + px(LOAD_MORE_MARGIN) >= self.metrics().content_height(self.rows)
+    }
 
-        let commit = repo.find_commit(*oid)?;
-        let parents: Vec<git2::Commit> = commit.parents().collect();
+    fn selected_index(&self) -> Option<usize> {
+        let selected = self.selected?;
+        self.nodes.iter().position(|n| n.oid == selected)
+    }
 
-        let mut changed_files = Vec::new();
+    /// Rows that fit in the viewport, at least one.
+    fn page_rows(&self) -> usize {
+        let viewport = f32::from(self.scroll_handle.bounds().size.height);
+        ((viewport / self.metrics().row_height()) as usize).max(1)
+    }
 
-        if parents.is_empty() {
-            // Initial commit - get all files as added
-            let tree = commit.tree()?;
-            for entry in tree.iter() {
-                if let Some(name) = entry.name() {
-                    changed_files.push(ChangedFile {
-                        path: name.to_string(),
-                        status: git2::Delta::Added,
-                        old_oid: None,
-                        new_oid: Some(*oid),
-                    });
-                }
-            }
-        } else {
-            // Get diff with first parent
-            let parent = &parents[0];
-            let commit_tree = commit.tree()?;
-            let parent_tree = parent.tree()?;
-            let diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&commit_tree), None)?;
+    /// Move the cursor by `delta` rows, clamped to the loaded history. With
+    /// nothing selected yet the cursor starts on the first row.
+    fn move_selection(&mut self, delta: isize, cx: &mut Context<Self>) {
+        if self.nodes.is_empty() {
+            return;
+        }
+        let index = match self.selected_index() {
+            Some(index) => index.saturating_add_signed(delta),
+            None => 0,
+        };
+        self.select_index(index.min(self.nodes.len() - 1), cx);
+    }
 
-            diff.foreach(
-                &mut |delta, _| {
-                    let file_path = delta
-                        .new_file()
-                        .path()
-                        .or(delta.old_file().path())
-                        .and_then(|p| p.to_str())
-                        .unwrap_or("unknown")
-                        .to_string();
-
-                    let old_oid = {
-                        let id = delta.old_file().id();
-                        if id.is_zero() { None } else { Some(id) }
-                    };
-
-                    let new_oid = {
-                        let id = delta.new_file().id();
-                        if id.is_zero() { None } else { Some(id) }
-                    };
-
-                    changed_files.push(ChangedFile {
-                        path: file_path,
-                        status: delta.status(),
-                        old_oid,
-                        new_oid,
-                    });
-                    true
-                },
-                None,
-                None,
-                None,
-            )?;
+    fn set_selected(&mut self, oid: Option<Oid>) {
+        if self.selected != oid {
+            self.follow = ParentFollow::All;
         }
+        self.selected = oid;
+        self.update_ancestry();
+    }
 
-        Ok(changed_files)
+    fn update_ancestry(&mut self) {
+        self.related = self
+            .selected
+            .map(|oid| ancestry::related(&self.nodes, &self.edges, oid, self.follow));
     }
 
-    pub fn compute_file_diff(
-        &self,
-        commit_oid: &git2::Oid,
-        file_path: &str,
-    ) -> Result<String, Box<dyn std::error::Error>> {
-        const MAX_FILE_DIFF_LINES: usize = 200;
+    /// Follow the next parent of the selected merge, or all of them again.
+    fn follow_next_parent(&mut self, cx: &mut Context<Self>) {
+        let Some(index) = self.selected_index() else {
+            return;
+        };
+        self.follow = self.follow.next(self.nodes[index].parents.len());
+        self.update_ancestry();
+        cx.notify();
+    }
 
-        let repo = self.repo.borrow();
-        let repo = repo.as_ref().ok_or("No repository loaded")?;
+    fn select_index(&mut self, index: usize, cx: &mut Context<Self>) {
+        let Some(node) = self.nodes.get(index) else {
+            return;
+        };
+        let (oid, row) = (node.oid, node.cell.row);
+        self.set_selected(Some(oid));
+        self.scroll_to_row(row);
+        cx.notify();
+    }
 
-        let commit = repo.find_commit(*commit_oid)?;
-        let commit_tree = commit.tree()?;
+    /// Scroll just enough to show `row`; older pages load as the view nears the bottom.
+    fn scroll_to_row(&self, row: usize) {
+        let metrics = self.metrics();
+        let top = metrics.y(row);
+        let bottom = top + px(metrics.row_height());
+        let viewport = self.scroll_handle.bounds().size.height;
+        let mut offset = self.scroll_handle.offset();
+        let scrolled = -offset.y;
+        if top < scrolled {
+            offset.y = -top;
+        } else if bottom > scrolled + viewport {
+            offset.y = viewport - bottom;
+        } else {
+            return;
+        }
+        self.scroll_handle.set_offset(offset);
+    }
 
-        // Check if file is binary or too large
-        if Self::is_binary_file(repo, &commit, file_path) {
-            let size = Self::get_file_size(repo, &commit, file_path).unwrap_or(0);
-            return Ok(format!(
-                "{} is a binary file\nSize: {} bytes",
-                file_path, size
-            ));
+    /// Show the files of the row at `index`, as a click on it does.
+    fn open_index(&mut self, index: usize, cx: &mut Context<Self>) {
+        let Some(node) = self.nodes.get(index) else {
+            return;
+        };
+        let node = node.clone();
+        self.seeking = None;
+        self.set_selected(Some(node.oid));
+        self.range_end = None;
+        if let Some(index) = node.stash {
+            cx.emit(StashSelected {
+                entry: StashEntry {
+                    index,
+                    oid: node.oid,
+                    message: node.message.clone(),
+                },
+            });
+        } else {
+            cx.emit(CommitSelected {
+                oid: node.oid,
+                message: node.message.clone(),
+                author: node.author.clone(),
+                timestamp: node.timestamp,
+                parents: node.parents.clone(),
+            });
         }
+        cx.notify();
+    }
 
-        let diff = match commit.parent(0) {
-            Ok(parent) => {
-                // Has parent - compute diff with parent
-                let parent_tree = parent.tree()?;
-                repo.diff_tree_to_tree(Some(&parent_tree), Some(&commit_tree), None)?
-            }
-            Err(_) => {
-                // Initial commit - compute diff with empty tree
-                repo.diff_tree_to_tree(None, Some(&commit_tree), None)?
-            }
+    /// Select, scroll to and open the loaded commit `oid`. False when it is
+    /// not part of the loaded history.
+    pub fn open_oid(&mut self, oid: Oid, cx: &mut Context<Self>) -> bool {
+        let Some(index) = self.nodes.iter().position(|n| n.oid == oid) else {
+            return false;
         };
+        self.scroll_to_row(self.nodes[index].cell.row);
+        self.open_index(index, cx);
+        true
+    }
 
-        let diff_lines = RefCell::new(Vec::new());
-        let line_count = RefCell::new(0usize);
-        let file_found = RefCell::new(false);
+    /// Open the row of `oid`, loading more history until it is laid out.
+    /// `false` when the row is not there yet; it is opened once its page
+    /// comes in, unless something else is opened first.
+    pub fn seek_oid(&mut self, oid: Oid, cx: &mut Context<Self>) -> bool {
+        if self.open_oid(oid, cx) {
+            return true;
+        }
+        let ahead = match &self.layout_state {
+            Some(state) => state.walks_later(&oid),
+            // a page is loading, look again once it is in
+            None => self.graph_job.is_some(),
+        };
+        if ahead {
+            self.seeking = Some(oid);
+            self.load_more(cx);
+        }
+        false
+    }
 
-        let target_path = file_path.to_string();
+    /// Diff the row at `index` against the selected one, or open it when
+    /// nothing else is selected.
+    fn open_range(&mut self, index: usize, cx: &mut Context<Self>) {
+        let Some(other) = self.selected_index().filter(|other| *other != index) else {
+            self.open_index(index, cx);
+            return;
+        };
+        let (a, b) = (&self.nodes[index], &self.nodes[other]);
+        let (old, new) = if a.cell.row > b.cell.row {
+            (a.oid, b.oid)
+        } else {
+            (b.oid, a.oid)
+        };
+        self.range_end = Some(a.oid);
+        cx.emit(RangeSelected { old, new });
+        cx.notify();
+    }
 
-        let result = diff.foreach(
-            &mut |delta, _| {
-                let current_path = match delta.new_file().path().or(delta.old_file().path()) {
-                    Some(path) => match path.to_str() {
-                        Some(s) => s.to_string(),
-                        None => return true, // Skip files with invalid UTF-8 paths
-                    },
-                    None => return true, // Skip files with no path
-                };
+    fn select_next(&mut self, _: &SelectNext, _: &mut Window, cx: &mut Context<Self>) {
+        self.move_selection(1, cx);
+    }
 
-                // Only process diff if this is the target file
-                if current_path != target_path {
-                    return true;
-                }
+    fn select_prev(&mut self, _: &SelectPrev, _: &mut Window, cx: &mut Context<Self>) {
+        self.move_selection(-1, cx);
+    }
 
-                *file_found.borrow_mut() = true;
+    fn select_page_down(&mut self, _: &SelectPageDown, _: &mut Window, cx: &mut Context<Self>) {
+        self.move_selection(self.page_rows() as isize, cx);
+    }
 
-                match delta.status() {
-                    git2::Delta::Added => diff_lines
-                        .borrow_mut()
-                        .push(format!("+++ a/{}", current_path)),
-                    git2::Delta::Deleted => diff_lines
-                        .borrow_mut()
-                        .push(format!("--- a/{}", current_path)),
-                    git2::Delta::Modified => {
-                        diff_lines
-                            .borrow_mut()
-                            .push(format!("--- a/{}", current_path));
-                        diff_lines
-                            .borrow_mut()
-                            .push(format!("+++ b/{}", current_path));
-                    }
-                    git2::Delta::Renamed => {
-                        let old_path = match delta.old_file().path() {
-                            Some(path) => match path.to_str() {
-                                Some(s) => s.to_string(),
-                                None => current_path.clone(), // Fallback to current path
-                            },
-                            None => current_path.clone(), // Fallback to current path
-                        };
-                        diff_lines.borrow_mut().push(format!("--- a/{}", old_path));
-                        diff_lines
-                            .borrow_mut()
-                            .push(format!("+++ b/{}", current_path));
-                    }
-                    _ => {}
-                }
-                true
-            },
-            None,
-            Some(&mut |_: git2::DiffDelta<'_>, hunk: git2::DiffHunk<'_>| {
-                let line_count = line_count.borrow_mut();
-                if *line_count >= MAX_FILE_DIFF_LINES {
-                    return false;
-                }
-                diff_lines
-                    .borrow_mut()
-                    .push(format!("@@ {} @@", String::from_utf8_lossy(hunk.header())));
-                true
-            }),
-            Some(&mut |_: git2::DiffDelta<'_>,
-                       _: Option<git2::DiffHunk<'_>>,
-                       line: git2::DiffLine<'_>| {
-                let mut line_count = line_count.borrow_mut();
-                if *line_count >= MAX_FILE_DIFF_LINES {
-                    return false;
-                }
+    fn select_page_up(&mut self, _: &SelectPageUp, _: &mut Window, cx: &mut Context<Self>) {
+        self.move_selection(-(self.page_rows() as isize), cx);
+    }
 
-                let line_content = String::from_utf8_lossy(line.content());
-                let origin = line.origin();
-                let prefix = match origin {
-                    '+' => "+",
-                    '-' => "-",
-                    ' ' => " ",
-                    _ => " ",
-                };
-                diff_lines
-                    .borrow_mut()
-                    .push(format!("{}{}", prefix, line_content.trim_end()));
+    fn select_first(&mut self, _: &SelectFirst, _: &mut Window, cx: &mut Context<Self>) {
+        self.select_index(0, cx);
+    }
 
-                *line_count += 1;
-                true
-            }),
-        );
+    fn select_last(&mut self, _: &SelectLast, _: &mut Window, cx: &mut Context<Self>) {
+        if let Some(last) = self.nodes.len().checked_sub(1) {
+            self.select_index(last, cx);
+        }
+    }
 
-        result.map_err(|e| format!("Failed to compute diff for file {}: {}", file_path, e))?;
+    /// Jump to the first parent, if it is drawn in the loaded history.
+    fn select_parent(&mut self, _: &SelectParent, _: &mut Window, cx: &mut Context<Self>) {
+        let parent = self
+            .selected_index()
+            .and_then(|index| self.nodes[index].parents.first().copied());
+        if let Some(index) = parent.and_then(|p| self.nodes.iter().position(|n| n.oid == p)) {
+            self.select_index(index, cx);
+        }
+    }
 
-        let result = diff_lines.into_inner();
-        let found = file_found.into_inner();
+    fn on_follow_next_parent(
+        &mut self,
+        _: &FollowNextParent,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.follow_next_parent(cx);
+    }
 
-        // If file was not found in diff, return informative message
-        if !found {
-            return Ok(format!(
-                "File '{}' not found in diff\nThis may be because:\n- The file path has changed\n- The file was renamed\n- The file is in a subdirectory",
-                file_path
-            ));
+    fn open_selected(&mut self, _: &OpenSelected, _: &mut Window, cx: &mut Context<Self>) {
+        if let Some(index) = self.selected_index() {
+            self.open_index(index, cx);
         }
+    }
 
-        // Add truncation message if limit was hit
-        let final_result = if line_count.into_inner() >= MAX_FILE_DIFF_LINES {
-            let mut truncated = result;
-            truncated.push("".to_string());
-            truncated.push(format!(
-                "... (showing first {} lines, diff truncated)",
-                MAX_FILE_DIFF_LINES
-            ));
-            truncated
-        } else {
-            result
-        };
+    pub fn columns(&self) -> &Columns {
+        &self.columns
+    }
+
+    pub fn toggle_column(&mut self, column: Column, cx: &mut Context<Self>) {
+        self.columns.toggle(column);
+        cx.notify();
+    }
+
+    pub fn cycle_date_style(&mut self, cx: &mut Context<Self>) {
+        self.columns.date_style = self.columns.date_style.next();
+        cx.notify();
+    }
 
-        Ok(final_result.join("\n"))
+    fn on_column_drag(&mut self, event: &MouseMoveEvent, _: &mut Window, cx: &mut Context<Self>) {
+        let Some((column, start_x, start_width)) = self.resizing else {
+            return;
+        };
+        if event.pressed_button != Some(MouseButton::Left) {
+            self.resizing = None;
+            return;
+        }
+        // columns hang off the right edge, so dragging left widens them
+        let delta = f32::from(start_x - event.position.x);
+        self.columns.set_width(column, start_width + delta);
+        cx.notify();
     }
 
     /* ---------------- view helpers ---------------- */
@@ -1138,49 +1802,227 @@ impl Garph {
         }
     }
 
-    fn is_binary_file(repo: &git2::Repository, commit: &git2::Commit, file_path: &str) -> bool {
-        let tree = match commit.tree() {
-            Ok(t) => t,
-            Err(_) => return false,
-        };
+    pub(crate) fn badge_colors(kind: RefKind) -> (u32, u32) {
+        match kind {
+            RefKind::Head => (BADGE_HEAD_BG, BADGE_HEAD_TEXT),
+            RefKind::Local => (BADGE_LOCAL_BG, BADGE_LOCAL_TEXT),
+            RefKind::Remote => (BADGE_REMOTE_BG, BADGE_REMOTE_TEXT),
+            RefKind::Tag => (BADGE_TAG_BG, BADGE_TAG_TEXT),
+            RefKind::Stash => (BADGE_STASH_BG, BADGE_STASH_TEXT),
+        }
+    }
 
-        let entry = match tree.get_path(std::path::Path::new(file_path)) {
-            Ok(e) => e,
-            Err(_) => return false,
-        };
+    fn render_badge(label: &RefLabel, cx: &Context<Self>) -> impl IntoElement {
+        let (bg, text) = Self::badge_colors(label.kind);
+        let name = label.name.clone();
+        let kind = label.kind;
+        let checkable = matches!(kind, RefKind::Local | RefKind::Remote);
 
-        let object = match entry.to_object(repo) {
-            Ok(o) => o,
-            Err(_) => return false,
-        };
+        div()
+            .flex_none()
+            .px(px(4.0))
+            .rounded(px(3.0))
+            .bg(gpui::rgb(bg))
+            .text_color(gpui::rgb(text))
+            .text_size(px(9.0))
+            .when(kind == RefKind::Head, |el| {
+                el.font_weight(gpui::FontWeight::BOLD)
+            })
+            .when(checkable, |el| {
+                el.cursor_pointer().on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(move |_this, _event, _window, cx| {
+                        // the row underneath would select the commit otherwise
+                        cx.stop_propagation();
+                        cx.emit(RefBadgeClicked {
+                            name: name.clone(),
+                            kind,
+                        });
+                    }),
+                )
+            })
+            .child(label.name.clone())
+    }
 
-        match object.as_blob() {
-            Some(blob) => {
-                // Check file size
-                if blob.size() > MAX_FILE_SIZE_BYTES {
-                    return true;
-                }
+    /// Column toggles on the left; titles with resize handles above the
+    /// columns on the right.
+    fn render_column_header(&self, cx: &Context<Self>) -> impl IntoElement {
+        let columns = self.columns;
+        div()
+            .flex_none()
+            .h(px(COLUMN_HEADER_HEIGHT))
+            .flex()
+            .flex_row()
+            .items_center()
+            .bg(gpui::rgb(0x1E1E1E))
+            .border_b_1()
+            .border_color(gpui::rgb(0x333333))
+            .text_size(px(10.0))
+            .text_color(gpui::rgb(COLUMN_TEXT))
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .gap_2()
+                    .px(px(COLUMN_PADDING))
+                    .children(Column::ALL.into_iter().map(|column| {
+                        let visible = columns.get(column).visible;
+                        div()
+                            .cursor_pointer()
+                            .when(visible, |el| el.text_color(gpui::rgb(0xCCCCCC)))
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(move |this, _event, _window, cx| {
+                                    this.toggle_column(column, cx);
+                                }),
+                            )
+                            .child(format!(
+                                "{} {}",
+                                if visible { "[x]" } else { "[ ]" },
+                                column.label()
+                            ))
+                    })),
+            )
+            .child(div().flex_1())
+            .children(columns.visible().map(|column| {
+                let width = columns.get(column).width;
+                let title = match column {
+                    Column::Date => format!("Date ({})", columns.date_style.label()),
+                    _ => column.label().to_string(),
+                };
+                div()
+                    .flex_none()
+                    .relative()
+                    .h_full()
+                    .w(px(width))
+                    .px(px(COLUMN_PADDING))
+                    .flex()
+                    .items_center()
+                    .overflow_hidden()
+                    .whitespace_nowrap()
+                    .when(column == Column::Date, |el| {
+                        el.cursor_pointer().on_mouse_down(
+                            MouseButton::Left,
+                            cx.listener(|this, _event, _window, cx| this.cycle_date_style(cx)),
+                        )
+                    })
+                    .child(title)
+                    .child(
+                        div()
+                            .absolute()
+                            .left(px(0.0))
+                            .top(px(0.0))
+                            .h_full()
+                            .w(px(RESIZE_HANDLE_WIDTH))
+                            .border_l_1()
+                            .border_color(gpui::rgb(0x333333))
+                            .cursor(CursorStyle::ResizeLeftRight)
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(move |this, event: &MouseDownEvent, _window, cx| {
+                                    cx.stop_propagation();
+                                    this.resizing = Some((column, event.position.x, width));
+                                }),
+                            ),
+                    )
+            }))
+    }
 
-                // Check if binary by looking for null bytes
-                let content = blob.content();
-                // Check first 8000 bytes for null bytes (git's heuristic)
-                let check_bytes = std::cmp::min(content.len(), 8000);
-                content[..check_bytes].contains(&0)
-            }
-            None => false,
-        }
+    /// The visible columns of one row, right-aligned.
+    fn render_columns(node: &CommitNode, columns: Columns, now: DateTime<Utc>) -> impl IntoElement {
+        div()
+            .absolute()
+            .top(px(0.0))
+            .right(px(0.0))
+            .h_full()
+            .flex()
+            .flex_row()
+            .items_center()
+            .text_size(px(10.0))
+            .text_color(gpui::rgb(COLUMN_TEXT))
+            .children(columns.visible().map(|column| {
+                let text = match column {
+                    Column::Author => node.author.clone(),
+                    Column::Date => columns.date_style.format(node.timestamp, now),
+                    Column::Sha => export::short_oid(&node.oid),
+                };
+                div()
+                    .flex_none()
+                    .w(px(columns.get(column).width))
+                    .px(px(COLUMN_PADDING))
+                    .overflow_hidden()
+                    .whitespace_nowrap()
+                    .child(text)
+            }))
     }
 
-    fn get_file_size(
-        repo: &git2::Repository,
-        commit: &git2::Commit,
-        file_path: &str,
-    ) -> Option<usize> {
-        let tree = commit.tree().ok()?;
-        let entry = tree.get_path(std::path::Path::new(file_path)).ok()?;
-        let object = entry.to_object(repo).ok()?;
-        let blob = object.as_blob()?;
-        Some(blob.size())
+    /// Pill on a selected merge that picks which parents its ancestry follows.
+    fn render_follow_toggle(follow: ParentFollow, cx: &Context<Self>) -> impl IntoElement {
+        div()
+            .flex_none()
+            .px(px(4.0))
+            .rounded(px(3.0))
+            .border_1()
+            .border_color(gpui::rgb(SELECTED_ROW_BG))
+            .text_color(gpui::rgb(0xCCCCCC))
+            .text_size(px(9.0))
+            .cursor_pointer()
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|this, _event, _window, cx| {
+                    cx.stop_propagation();
+                    this.follow_next_parent(cx);
+                }),
+            )
+            .child(format!("follow {}", follow.label()))
+    }
+
+    fn render_worktree_row(
+        count: usize,
+        lane: usize,
+        metrics: GraphMetrics,
+        max_lane: usize,
+        cx: &Context<Self>,
+    ) -> impl IntoElement {
+        div()
+            .absolute()
+            .top(px(0.0))
+            .left(px(0.0))
+            .right(px(0.0))
+            .h(px(metrics.row_height()))
+            .group("worktree-row")
+            .hover(|style| style.bg(gpui::hsla(0.0, 0.0, 0.22, 0.3)))
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|_this, _event, _window, cx| cx.emit(WorkingTreeSelected)),
+            )
+            .child(
+                div()
+                    .absolute()
+                    .left(metrics.x(lane))
+                    .size(metrics.node_size())
+                    .border_1()
+                    .border_color(gpui::rgb(WORKTREE_COLOR))
+                    .rounded(px(5.0)),
+            )
+            .child(
+                div()
+                    .absolute()
+                    .left(metrics.text_left(max_lane))
+                    .px(px(10.0))
+                    .py(px(5.0))
+                    .whitespace_nowrap()
+                    .text_color(gpui::rgb(WORKTREE_TEXT))
+                    .text_size(px(10.0))
+                    .italic()
+                    .child(format!("Uncommitted changes ({})", count)),
+            )
+    }
+}
+
+impl Focusable for Garph {
+    fn focus_handle(&self, _: &App) -> FocusHandle {
+        self.focus_handle.clone()
     }
 }
 
@@ -1188,26 +2030,80 @@ impl EventEmitter<CommitSelected> for Garph {}
 
 impl EventEmitter<RepoPathChanged> for Garph {}
 
+impl EventEmitter<RefBadgeClicked> for Garph {}
+
+impl EventEmitter<WorkingTreeSelected> for Garph {}
+
+impl EventEmitter<StashSelected> for Garph {}
+
+impl EventEmitter<RangeSelected> for Garph {}
+
 impl Render for Garph {
     fn render(&mut self, _w: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        self.poll_graph();
-        if self.pending_graph_rx.is_some() {
-            cx.notify();
+        if self.has_more && self.near_bottom() {
+            self.load_more(cx);
         }
 
         let has_repo = self.repo.borrow().is_some();
-        let nodes = self.nodes.clone();
-        let edges = self.edges.clone();
-        let height = self.content_height;
+        // only build rows and edges near the viewport, history can grow without bound
+        let metrics = self.metrics();
+        let (top, bottom) = self.visible_range();
+        let (first_row, last_row) = metrics.rows_between(top, bottom);
+        // paired with their index in `self.nodes`, which clicks select by
+        let nodes: Vec<(usize, CommitNode)> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| n.cell.row >= first_row && n.cell.row <= last_row)
+            .map(|(index, n)| (index, n.clone()))
+            .collect();
+        let edges: Vec<Edge> = self
+            .edges
+            .iter()
+            .filter(|e| e.from.row <= last_row && e.to.row >= first_row)
+            .cloned()
+            .collect();
+        let height = metrics.content_height(self.rows);
+        let related_rows: Option<HashSet<usize>> = self.related.as_ref().map(|related| {
+            self.nodes
+                .iter()
+                .filter(|n| related.contains(&n.oid))
+                .map(|n| n.cell.row)
+                .collect()
+        });
+        let edge_colors: Vec<gpui::Rgba> = edges
+            .iter()
+            .map(|e| {
+                let mut color = gpui::rgb(self.color(e.color));
+                let on_path = related_rows
+                    .as_ref()
+                    .is_none_or(|rows| rows.contains(&e.from.row) && rows.contains(&e.to.row));
+                if !on_path {
+                    color.a = DIMMED_OPACITY;
+                }
+                color
+            })
+            .collect();
         let max_lane = self.max_lane;
-
-        div()
-            .size_full()
+        let uncommitted = self.uncommitted;
+        let head_cell = self.head_cell;
+        let selected = self.selected;
+        let range_end = self.range_end;
+        let related = self.related.clone();
+        let follow = self.follow;
+
+        let columns = self.columns;
+        let columns_width = px(columns.total_width());
+        let now = Utc::now();
+
+        let graph = div()
+            .flex_1()
             .relative()
             .flex()
             .flex_col()
             .id("garph")
             .overflow_scroll()
+            .track_scroll(&self.scroll_handle)
             .bg(gpui::rgb(0x282828))
             .when(!has_repo, |div1| {
                 div1.child(
@@ -1251,31 +2147,38 @@ impl Render for Garph {
                             move |_, _, _| {},
                             move |bounds, _, window, _| {
                                 let offset = bounds.origin;
-                                for e in &edges {
+                                if uncommitted > 0
+                                    && let Some(head) = head_cell
+                                {
+                                    let mut path = PathBuilder::stroke(px(1.5))
+                                        .dash_array(&[px(3.0), px(3.0)]);
+                                    path.move_to(metrics.worktree_anchor(head.lane) + offset);
+                                    path.line_to(metrics.anchor(head) + offset);
+                                    if let Ok(p) = path.build() {
+                                        window.paint_path(p, gpui::rgb(WORKTREE_COLOR));
+                                    }
+                                }
+                                for (e, color) in edges.iter().zip(&edge_colors) {
                                     let mut path = PathBuilder::stroke(px(1.5));
-                                    let size_node = Point::new(px(0.0), px(6.0));
-                                    let start = e.from + offset + size_node;
-                                    let end = e.to + offset + size_node;
+                                    if e.elided {
+                                        path = path.dash_array(&[px(3.0), px(3.0)]);
+                                    }
+                                    let start = metrics.anchor(e.from) + offset;
+                                    let end = metrics.anchor(e.to) + offset;
 
                                     path.move_to(start);
-                                    let same_lane = (start.x - end.x).abs() < px(0.5);
-                                    // straight line
-                                    if same_lane {
-                                        path.line_to(end);
-                                    } else if start.x > end.x {
-                                        let ctrl1 = Point::new(start.x, end.y);
-                                        let ctrl2 = Point::new(start.x, end.y);
-                                        // curve is feak when line too short or long
-                                        path.cubic_bezier_to(end, ctrl1, ctrl2);
-                                    } else if start.x < end.x {
-                                        let ctrl1 = Point::new(end.x, start.y);
-                                        let ctrl2 = Point::new(end.x, start.y);
-                                        // curve is feak when line too short or long
-                                        path.cubic_bezier_to(end, ctrl1, ctrl2);
+                                    match e.control() {
+                                        // straight line
+                                        None => path.line_to(end),
+                                        Some(ctrl) => {
+                                            let ctrl = metrics.anchor(ctrl) + offset;
+                                            // curve is feak when line too short or long
+                                            path.cubic_bezier_to(end, ctrl, ctrl);
+                                        }
                                     }
                                     if let Ok(p) = path.build() {
                                         // window.paint_path(p, gpui::white());
-                                        window.paint_path(p, gpui::rgb(VEC_COLORS[e.color]));
+                                        window.paint_path(p, *color);
                                     }
                                 }
                             },
@@ -1283,56 +2186,80 @@ impl Render for Garph {
                         .absolute()
                         .size_full(),
                     )
+                    .when(uncommitted > 0, |el| {
+                        let lane = head_cell.map(|c| c.lane).unwrap_or(0);
+                        el.child(Self::render_worktree_row(
+                            uncommitted,
+                            lane,
+                            metrics,
+                            max_lane,
+                            cx,
+                        ))
+                    })
                     // combined rows (node + text)
-                    .child(div().children(nodes.iter().map(|n| {
+                    .child(div().children(nodes.iter().map(|(index, n)| {
+                        let index = *index;
                         let message = Self::clean_message(&n.message);
-                        let oid = n.oid;
-                        let message_text = n.message.clone();
                         let truncated_message = Self::truncate_message(&message, 80);
-                        let author_text = n.author.clone();
-                        let timestamp = n.timestamp;
-                        let parents = n.parents.clone();
+                        let stash = n.stash;
+                        let node_color = self.color(n.color);
+                        let badges: Vec<_> =
+                            n.refs.iter().map(|r| Self::render_badge(r, cx)).collect();
+                        let selected_merge = selected == Some(n.oid) && n.parents.len() > 1;
 
                         // Calculate text position based on max lane to ensure no overlap
-                        let container_text_left =
-                            START_X + (max_lane as f32) * LANE_WIDTH + TEXT_GAP;
+                        let container_text_left = metrics.text_left(max_lane);
 
                         div()
                             .absolute()
-                            .top(n.position.y)
+                            .top(metrics.y(n.cell.row))
                             .left(px(0.0))
                             .right(px(0.0))
-                            .h(px(COMMIT_HEIGHT))
+                            .h(px(metrics.row_height()))
+                            .when(
+                                !n.matched || related.as_ref().is_some_and(|r| !r.contains(&n.oid)),
+                                |el| el.opacity(DIMMED_OPACITY),
+                            )
                             .group("commit-row")
                             .hover(|style| style.bg(gpui::hsla(0.0, 0.0, 0.22, 0.3)))
+                            .when(selected == Some(n.oid) || range_end == Some(n.oid), |el| {
+                                el.bg(gpui::rgb(SELECTED_ROW_BG))
+                            })
                             .on_mouse_down(
                                 MouseButton::Left,
-                                cx.listener(move |_this, _event, _window, cx| {
-                                    cx.emit(CommitSelected {
-                                        oid,
-                                        message: message_text.clone(),
-                                        author: author_text.clone(),
-                                        timestamp,
-                                        parents: parents.clone(),
-                                    });
+                                cx.listener(move |this, event: &MouseDownEvent, _window, cx| {
+                                    if event.modifiers.secondary() {
+                                        this.open_range(index, cx);
+                                    } else {
+                                        this.open_index(index, cx);
+                                    }
                                 }),
                             )
                             // node (independent absolute positioning)
                             .child(
                                 div()
                                     .absolute()
-                                    .left(n.position.x)
-                                    .size(SIZE)
-                                    .bg(gpui::rgb(VEC_COLORS[n.color]))
+                                    .left(metrics.x(n.cell.lane))
+                                    .size(metrics.node_size())
                                     .border_color(gpui::black())
-                                    .rounded(px(5.0))
-                                    .group_hover("commit-row", |style| style.size(SIZE + px(20.0))),
+                                    // stashes are hollow squares so they don't read as commits
+                                    .map(|node| match stash {
+                                        Some(_) => node
+                                            .border_1()
+                                            .border_color(gpui::rgb(node_color))
+                                            .rounded(px(2.0)),
+                                        None => node.bg(gpui::rgb(node_color)).rounded(px(5.0)),
+                                    })
+                                    .group_hover("commit-row", |style| {
+                                        style.size(metrics.node_size() + px(20.0))
+                                    }),
                             )
                             // text (independent absolute positioning)
                             .child(
                                 div()
                                     .absolute()
-                                    .left(px(container_text_left))
+                                    .left(container_text_left)
+                                    .right(columns_width)
                                     .px(px(10.0))
                                     .py(px(5.0))
                                     .max_w(px(600.0))
@@ -1341,10 +2268,43 @@ impl Render for Garph {
                                     .rounded(px(4.0))
                                     .text_color(gpui::rgb(0x969696))
                                     .text_size(px(10.0))
-                                    .line_clamp(1)
-                                    .child(truncated_message.to_string()),
+                                    .flex()
+                                    .flex_row()
+                                    .items_center()
+                                    .gap_1()
+                                    .when(selected_merge, |el| {
+                                        el.child(Self::render_follow_toggle(follow, cx))
+                                    })
+                                    .children(badges)
+                                    .child(
+                                        div().line_clamp(1).child(truncated_message.to_string()),
+                                    ),
                             )
+                            .child(Self::render_columns(n, columns, now))
                     }))),
+            );
+
+        div()
+            .key_context("Garph")
+            .track_focus(&self.focus_handle)
+            .on_action(cx.listener(Self::select_next))
+            .on_action(cx.listener(Self::select_prev))
+            .on_action(cx.listener(Self::select_page_down))
+            .on_action(cx.listener(Self::select_page_up))
+            .on_action(cx.listener(Self::select_first))
+            .on_action(cx.listener(Self::select_last))
+            .on_action(cx.listener(Self::select_parent))
+            .on_action(cx.listener(Self::on_follow_next_parent))
+            .on_action(cx.listener(Self::open_selected))
+            .on_mouse_move(cx.listener(Self::on_column_drag))
+            .on_mouse_up(
+                MouseButton::Left,
+                cx.listener(|this, _event, _window, _cx| this.resizing = None),
             )
+            .size_full()
+            .flex()
+            .flex_col()
+            .when(has_repo, |el| el.child(self.render_column_header(cx)))
+            .child(graph)
     }
 }
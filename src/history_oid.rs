@@ -1,24 +1,29 @@
 use std::collections::HashMap;
 
 use git2::Oid;
-use gpui::{Pixels, Point};
 
+use crate::layout::GraphCell;
+
+/// An edge waiting for its parent commit to be placed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct HistoryOid {
-    pub edge_point: Point<Pixels>,
+    pub cell: GraphCell,
     pub color: usize,
-    pub lane: usize,
+    /// set once the edge has been passed through a commit that is not drawn
+    pub elided: bool,
 }
 
 impl HistoryOid {
-    pub fn new(point: Point<Pixels>, color: usize, lane: usize) -> Self {
+    pub fn new(cell: GraphCell, color: usize) -> Self {
         Self {
-            edge_point: point,
+            cell,
             color,
-            lane,
+            elided: false,
         }
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct HistoryOidManager {
     pub history_oid: HashMap<Oid, Vec<HistoryOid>>,
 }
@@ -41,4 +46,25 @@ impl HistoryOidManager {
     pub fn get(&self, oid: &Oid) -> Option<&[HistoryOid]> {
         self.history_oid.get(oid).map(|oids| oids.as_slice())
     }
+    /// remove and return the pending edges of a commit once it has been placed
+    pub fn take(&mut self, oid: &Oid) -> Option<Vec<HistoryOid>> {
+        self.history_oid.remove(oid)
+    }
+    /// move the pending edges of a commit that is left out onto its parents
+    pub fn forward(&mut self, oid: &Oid, parents: &[Oid]) {
+        let Some(pending) = self.take(oid) else {
+            return;
+        };
+        for parent in parents {
+            let waiting = self.history_oid.entry(*parent).or_default();
+            for history in &pending {
+                if !waiting.iter().any(|h| h.cell == history.cell) {
+                    waiting.push(HistoryOid {
+                        elided: true,
+                        ..*history
+                    });
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,35 @@
+use gpui::{AppContext, Context, Task};
+
+/// Blocking work run on the background executor. When it finishes, its
+/// result is handed to the entity that started it and the entity is
+/// notified, so nothing has to poll for it on every frame.
+///
+/// Dropping a job, e.g. by replacing it with a newer one, discards its
+/// result; the work itself runs to the end unless it checks a cancel token.
+pub struct Job {
+    _task: Task<()>,
+}
+
+impl Job {
+    pub fn spawn<T, R>(
+        cx: &mut Context<T>,
+        work: impl FnOnce() -> R + Send + 'static,
+        done: impl FnOnce(&mut T, R, &mut Context<T>) + 'static,
+    ) -> Self
+    where
+        T: 'static,
+        R: Send + 'static,
+    {
+        let result = cx.background_spawn(async move { work() });
+        let task = cx.spawn(async move |this, cx| {
+            let result = result.await;
+            // the entity may be gone by now, its result goes with it
+            this.update(cx, |this, cx| {
+                done(this, result, cx);
+                cx.notify();
+            })
+            .ok();
+        });
+        Job { _task: task }
+    }
+}
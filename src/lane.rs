@@ -63,4 +63,37 @@ impl LaneManager {
         // return lane of node
         lane
     }
+
+    /// Hand the lane of a commit that is not drawn over to its parents, so
+    /// whatever waited on it waits on them instead. Returns `false` when no
+    /// lane was waiting on the commit.
+    pub fn pass_through(&mut self, commit_oid: &Oid, parent_oids: &[Oid]) -> bool {
+        let Some(lane) = self
+            .lanes
+            .iter()
+            .position(|slot| slot.as_ref() == Some(commit_oid))
+        else {
+            return false;
+        };
+        self.lanes[lane] = None;
+
+        for (i, parent) in parent_oids.iter().enumerate() {
+            if self.lanes.contains(&Some(*parent)) {
+                continue;
+            }
+            // the first parent continues straight down the same lane
+            if i == 0 {
+                self.lanes[lane] = Some(*parent);
+            } else if let Some(free) = self.lanes.iter().position(|l| l.is_none()) {
+                self.lanes[free] = Some(*parent);
+            } else {
+                self.lanes.push(Some(*parent));
+            }
+        }
+
+        while matches!(self.lanes.last(), Some(None)) {
+            self.lanes.pop();
+        }
+        true
+    }
 }
@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+
+use git2::Oid;
+
+use crate::color::ColorManager;
+use crate::edge::{Edge, EdgeKind, EdgeManager};
+use crate::history_oid::{HistoryOid, HistoryOidManager};
+use crate::lane::LaneManager;
+
+/// A position in the graph grid. Rows grow downwards in revwalk order and
+/// lanes grow to the right; pixels are only derived from this at render time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct GraphCell {
+    pub row: usize,
+    pub lane: usize,
+}
+
+impl GraphCell {
+    pub fn new(row: usize, lane: usize) -> Self {
+        Self { row, lane }
+    }
+
+    /// The same cell `rows` further down.
+    pub fn shifted(self, rows: usize) -> Self {
+        Self::new(self.row + rows, self.lane)
+    }
+}
+
+/// Which commits and parent links the graph draws.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GraphMode {
+    #[default]
+    Full,
+    /// follow only `parents[0]`, merged side branches disappear
+    FirstParent,
+    /// only commits a ref points at and merge points; the runs in between
+    /// collapse into dashed edges
+    SimplifyByDecoration,
+}
+
+impl GraphMode {
+    /// The parents a commit is connected to in this mode.
+    pub fn parents<'a>(&self, parents: &'a [Oid]) -> &'a [Oid] {
+        match self {
+            GraphMode::FirstParent => &parents[..parents.len().min(1)],
+            GraphMode::Full | GraphMode::SimplifyByDecoration => parents,
+        }
+    }
+
+    /// Whether a commit gets a row; `decorated` means a ref points at it.
+    pub fn shows(&self, decorated: bool, parent_count: usize) -> bool {
+        match self {
+            GraphMode::SimplifyByDecoration => decorated || parent_count > 1,
+            GraphMode::Full | GraphMode::FirstParent => true,
+        }
+    }
+}
+
+/// Where a commit landed and the edges that connect it to already placed children.
+#[derive(Debug, Clone)]
+pub struct PlacedCommit {
+    pub cell: GraphCell,
+    pub color: usize,
+    pub edges: Vec<Edge>,
+}
+
+/// Lays commits out one at a time in revwalk order. Feeding the same
+/// commits always yields the same cells and edges.
+#[derive(Debug, Clone)]
+pub struct GraphLayout {
+    lane_manager: LaneManager,
+    color_manager: ColorManager,
+    history_oids_manager: HistoryOidManager,
+    max_lane: usize,
+    row: usize,
+}
+
+impl GraphLayout {
+    pub fn new(palette_len: usize) -> Self {
+        Self {
+            lane_manager: LaneManager::new(),
+            color_manager: ColorManager::new(palette_len),
+            history_oids_manager: HistoryOidManager::new(),
+            max_lane: 0,
+            row: 0,
+        }
+    }
+
+    /// Number of rows placed so far.
+    pub fn rows(&self) -> usize {
+        self.row
+    }
+
+    pub fn max_lane(&self) -> usize {
+        self.max_lane
+    }
+
+    /// Place `oid` on the next row and resolve the edges waiting on it.
+    /// `branch` names the branch whose tip this commit is, if any.
+    pub fn place(&mut self, oid: Oid, parents: &[Oid], branch: Option<&str>) -> PlacedCommit {
+        let lane = self.lane_manager.assign_commit(&oid, parents);
+        let color = self.color_manager.get_color(&oid, parents, branch);
+        let cell = GraphCell::new(self.row, lane);
+        self.row += 1;
+        self.max_lane = self.max_lane.max(lane);
+
+        let mut edge_manager = EdgeManager::new();
+        if let Some(history_oids) = self.history_oids_manager.take(&oid) {
+            for history in &history_oids {
+                let child = history.cell;
+                let (kind, edge_color) = if child.lane > lane {
+                    (EdgeKind::MergeIn, history.color)
+                } else if child.lane < lane {
+                    (EdgeKind::ForkOut, color)
+                } else {
+                    (EdgeKind::Straight, history.color)
+                };
+                edge_manager.add(child, cell, kind, edge_color, history.elided);
+            }
+        }
+
+        for parent in parents {
+            self.history_oids_manager
+                .add_history(*parent, HistoryOid::new(cell, color));
+        }
+
+        PlacedCommit {
+            cell,
+            color,
+            edges: edge_manager.take_edges(),
+        }
+    }
+
+    /// What the rows still to come depend on, kept to compare a later
+    /// layout against without holding on to this one.
+    pub fn frontier(&self) -> Frontier {
+        Frontier {
+            row: self.row,
+            lanes: self.lane_manager.lanes.clone(),
+            colors: self.color_manager.clone(),
+            pending: self.history_oids_manager.history_oid.clone(),
+        }
+    }
+
+    /// Whether laying out the remaining history from here gives the same
+    /// lanes, colors and edges as from `earlier`, only further down. This is
+    /// how a refresh that prepends new commits knows the old rows below can
+    /// be kept.
+    pub fn continues(&self, earlier: &Frontier) -> bool {
+        let Some(shift) = self.row.checked_sub(earlier.row) else {
+            return false;
+        };
+        let pending = &self.history_oids_manager.history_oid;
+
+        self.lane_manager.lanes == earlier.lanes
+            && self.color_manager == earlier.colors
+            && pending.len() == earlier.pending.len()
+            && earlier.pending.iter().all(|(oid, waiting)| {
+                pending.get(oid).is_some_and(|mine| {
+                    mine.len() == waiting.len()
+                        && mine.iter().zip(waiting).all(|(a, b)| {
+                            *a == HistoryOid {
+                                cell: b.cell.shifted(shift),
+                                ..*b
+                            }
+                        })
+                })
+            })
+    }
+
+    /// Move everything laid out so far `rows` further down, so a layout that
+    /// `continues` below a new prefix carries on with the right rows.
+    pub fn shift_rows(&mut self, rows: usize) {
+        self.row += rows;
+        shift_pending(&mut self.history_oids_manager.history_oid, rows);
+    }
+
+    /// Leave `oid` out of the graph. Whatever was waiting on it now waits on
+    /// its parents, so edges run straight to the next placed ancestor and are
+    /// marked as elided.
+    pub fn skip(&mut self, oid: Oid, parents: &[Oid]) {
+        self.color_manager.pass_through(&oid, parents);
+        if self.lane_manager.pass_through(&oid, parents) {
+            self.history_oids_manager.forward(&oid, parents);
+        }
+    }
+}
+
+/// The lanes in use after some row of a layout, and the edges and colors
+/// still waiting there on commits not placed yet.
+#[derive(Clone, Debug)]
+pub struct Frontier {
+    row: usize,
+    lanes: Vec<Option<Oid>>,
+    colors: ColorManager,
+    pending: HashMap<Oid, Vec<HistoryOid>>,
+}
+
+impl Frontier {
+    /// Number of rows placed before it.
+    pub fn rows(&self) -> usize {
+        self.row
+    }
+
+    pub fn shift_rows(&mut self, rows: usize) {
+        self.row += rows;
+        shift_pending(&mut self.pending, rows);
+    }
+}
+
+fn shift_pending(pending: &mut HashMap<Oid, Vec<HistoryOid>>, rows: usize) {
+    for waiting in pending.values_mut() {
+        for history in waiting {
+            history.cell = history.cell.shifted(rows);
+        }
+    }
+}
@@ -1,20 +1,34 @@
 pub mod actions;
+pub mod ancestry;
 pub mod branch;
 pub mod color;
+pub mod columns;
 pub mod commit;
+pub mod composer;
+pub mod details;
 pub mod diff_viewer;
 pub mod edge;
+pub mod export;
 pub mod garph;
 pub mod history_oid;
+pub mod job;
 pub mod lane;
+pub mod layout;
 pub mod menu;
 pub mod panel_loader;
 pub mod path_bar;
+pub mod query;
+pub mod refs;
 pub mod repo_picker;
 pub mod repo_scanner;
+pub mod staging;
+pub mod stash;
 pub mod status_bar;
 pub mod status_panel;
 pub mod suggest;
+pub mod tags;
 pub mod text_input;
 pub mod title;
+pub mod watcher;
+pub mod widgets;
 pub mod workspace;
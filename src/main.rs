@@ -1,9 +1,18 @@
 use dotenv::dotenv;
 use gpig::actions::Quit;
-use gpig::garph::Garph;
+use gpig::branch::{CancelBranchForm, ConfirmBranchForm};
+use gpig::composer::CommitStaged;
+use gpig::garph::{
+    FollowNextParent, Garph, OpenSelected, SelectFirst, SelectLast, SelectNext, SelectPageDown,
+    SelectPageUp, SelectParent, SelectPrev,
+};
+use gpig::status_panel::{
+    OpenEntry, SelectNextEntry, SelectPrevEntry, StageAll, ToggleStaged, UnstageAll,
+};
+use gpig::tags::{CancelTagForm, ConfirmTagForm};
 use gpig::text_input::{
-    Backspace, Cut, Delete, End, Home, Left, Paste, Right, SelectAll, SelectLeft, SelectRight,
-    ShowCharacterPalette,
+    Backspace, Cut, Delete, Down, End, Home, Left, Newline, Paste, Right, SelectAll, SelectLeft,
+    SelectRight, ShowCharacterPalette, Up,
 };
 use gpig::workspace::Workspace;
 use gpui::{App, AppContext, Application, KeyBinding, QuitMode, WindowOptions};
@@ -11,12 +20,11 @@ use std::error::Error;
 
 fn main() -> Result<(), Box<dyn Error>> {
     dotenv().ok();
-    let garph_value = Garph::new(None);
 
     Application::with_platform(gpui_platform::current_platform(false))
         .with_quit_mode(QuitMode::Explicit)
         .run(move |cx: &mut App| {
-            let garph = cx.new(|_| garph_value.clone());
+            let garph = cx.new(|cx| Garph::new(None, cx));
 
             cx.bind_keys([
                 KeyBinding::new("ctrl-q", Quit, None),
@@ -33,6 +41,34 @@ fn main() -> Result<(), Box<dyn Error>> {
                 KeyBinding::new("cmd-c", gpig::text_input::Copy, Some("TextInput")),
                 KeyBinding::new("cmd-x", Cut, Some("TextInput")),
                 KeyBinding::new("ctrl-cmd-space", ShowCharacterPalette, Some("TextInput")),
+                KeyBinding::new("enter", Newline, Some("TextInput && multiline")),
+                KeyBinding::new("up", Up, Some("TextInput && multiline")),
+                KeyBinding::new("down", Down, Some("TextInput && multiline")),
+                KeyBinding::new("enter", ConfirmBranchForm, Some("BranchForm")),
+                KeyBinding::new("escape", CancelBranchForm, Some("BranchForm")),
+                KeyBinding::new("enter", ConfirmTagForm, Some("TagForm")),
+                KeyBinding::new("escape", CancelTagForm, Some("TagForm")),
+                KeyBinding::new("ctrl-enter", CommitStaged, Some("CommitComposer")),
+                KeyBinding::new("cmd-enter", CommitStaged, Some("CommitComposer")),
+                KeyBinding::new("j", SelectNext, Some("Garph")),
+                KeyBinding::new("down", SelectNext, Some("Garph")),
+                KeyBinding::new("k", SelectPrev, Some("Garph")),
+                KeyBinding::new("up", SelectPrev, Some("Garph")),
+                KeyBinding::new("pagedown", SelectPageDown, Some("Garph")),
+                KeyBinding::new("pageup", SelectPageUp, Some("Garph")),
+                KeyBinding::new("home", SelectFirst, Some("Garph")),
+                KeyBinding::new("end", SelectLast, Some("Garph")),
+                KeyBinding::new("p", SelectParent, Some("Garph")),
+                KeyBinding::new("f", FollowNextParent, Some("Garph")),
+                KeyBinding::new("enter", OpenSelected, Some("Garph")),
+                KeyBinding::new("j", SelectNextEntry, Some("StatusPanel")),
+                KeyBinding::new("down", SelectNextEntry, Some("StatusPanel")),
+                KeyBinding::new("k", SelectPrevEntry, Some("StatusPanel")),
+                KeyBinding::new("up", SelectPrevEntry, Some("StatusPanel")),
+                KeyBinding::new("space", ToggleStaged, Some("StatusPanel")),
+                KeyBinding::new("enter", OpenEntry, Some("StatusPanel")),
+                KeyBinding::new("shift-s", StageAll, Some("StatusPanel")),
+                KeyBinding::new("shift-u", UnstageAll, Some("StatusPanel")),
             ]);
             cx.on_action(|_action: &Quit, cx: &mut App| {
                 cx.quit();
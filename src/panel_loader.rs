@@ -1,11 +1,15 @@
-use git2::Repository;
+use std::cmp::Reverse;
+
+use git2::{Repository, StatusOptions};
 
 use crate::branch::{BranchInfo, BranchReloadResult};
 use crate::path_bar::RepoMode;
 use crate::status_panel::{StatusEntry, StatusKind, StatusReloadResult};
+use crate::tags::{TagInfo, TagReloadResult};
 
 pub struct PanelData {
     pub branches: BranchReloadResult,
+    pub tags: TagReloadResult,
     pub status: StatusReloadResult,
     pub branch_name: String,
     pub dirty_count: usize,
@@ -15,16 +19,18 @@ pub fn load_panel_data_bg(repo_path: &str, mode: &RepoMode) -> Result<PanelData,
     let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
 
     let branches = load_branches(&repo, mode)?;
+    let tags = load_tags(&repo)?;
     let status = load_status(&repo)?;
     let branch_name = repo
         .head()
         .ok()
         .and_then(|r| r.shorthand().map(|s| s.to_string()))
         .unwrap_or_default();
-    let dirty_count = status.entries.len();
+    let dirty_count = status.dirty_count();
 
     Ok(PanelData {
         branches,
+        tags,
         status,
         branch_name,
         dirty_count,
@@ -62,39 +68,114 @@ fn load_branches(repo: &Repository, mode: &RepoMode) -> Result<BranchReloadResul
     Ok(BranchReloadResult { branches })
 }
 
-fn load_status(repo: &Repository) -> Result<StatusReloadResult, String> {
-    let statuses = repo.statuses(None).map_err(|e| e.to_string())?;
+/// Tags of commits, newest commit first. Tags of trees or blobs are left
+/// out, there is nothing in the graph to jump to.
+pub fn load_tags(repo: &Repository) -> Result<TagReloadResult, String> {
+    let names = repo.tag_names(None).map_err(|e| e.to_string())?;
+
+    let mut tags = Vec::new();
+    for name in names.iter().flatten() {
+        let Ok(reference) = repo.find_reference(&format!("refs/tags/{}", name)) else {
+            continue;
+        };
+        let Ok(commit) = reference.peel_to_commit() else {
+            continue;
+        };
+        let annotated = reference.peel_to_tag().is_ok();
+        tags.push((
+            commit.time().seconds(),
+            TagInfo {
+                name: name.to_string(),
+                target: commit.id(),
+                annotated,
+            },
+        ));
+    }
+    tags.sort_by_key(|(time, tag)| (Reverse(*time), tag.name.clone()));
+
+    Ok(TagReloadResult {
+        tags: tags.into_iter().map(|(_, tag)| tag).collect(),
+    })
+}
+
+/// Staged and unstaged changes. Staged renames are detected so both of
+/// their paths can be unstaged together.
+pub fn load_status(repo: &Repository) -> Result<StatusReloadResult, String> {
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .renames_head_to_index(true);
+    let statuses = repo
+        .statuses(Some(&mut options))
+        .map_err(|e| e.to_string())?;
 
     let mut entries = Vec::new();
     for entry in statuses.iter() {
-        let path = entry.path().unwrap_or("?").to_string();
         let s = entry.status();
+        // for a rename `path` is the old side, the entry is shown under the new one
+        let renamed = s
+            .is_index_renamed()
+            .then(|| entry.head_to_index())
+            .flatten()
+            .and_then(|delta| {
+                let old = delta.old_file().path()?.to_str()?.to_string();
+                let new = delta.new_file().path()?.to_str()?.to_string();
+                Some((old, new))
+            });
+        let (path, old_path) = match renamed {
+            Some((old, new)) => (new, Some(old)),
+            None => (entry.path().unwrap_or("?").to_string(), None),
+        };
+
+        if s.is_conflicted() {
+            entries.push(StatusEntry {
+                path,
+                old_path: None,
+                staged: false,
+                status_kind: StatusKind::Conflicted,
+            });
+            continue;
+        }
 
-        let (staged, kind) = if s.is_conflicted() {
-            (false, StatusKind::Conflicted)
-        } else if s.is_index_new() {
-            (true, StatusKind::New)
+        // a file staged and then edited again shows up in both lists
+        let index_kind = if s.is_index_new() {
+            Some(StatusKind::New)
         } else if s.is_index_modified() {
-            (true, StatusKind::Modified)
+            Some(StatusKind::Modified)
         } else if s.is_index_deleted() {
-            (true, StatusKind::Deleted)
+            Some(StatusKind::Deleted)
         } else if s.is_index_renamed() {
-            (true, StatusKind::Renamed)
-        } else if s.is_wt_new() {
-            (false, StatusKind::Untracked)
+            Some(StatusKind::Renamed)
+        } else {
+            None
+        };
+        let work_tree_kind = if s.is_wt_new() {
+            Some(StatusKind::Untracked)
         } else if s.is_wt_modified() {
-            (false, StatusKind::Modified)
+            Some(StatusKind::Modified)
         } else if s.is_wt_deleted() {
-            (false, StatusKind::Deleted)
+            Some(StatusKind::Deleted)
         } else {
-            continue;
+            None
         };
 
-        entries.push(StatusEntry {
-            path,
-            staged,
-            status_kind: kind,
-        });
+        if let Some(kind) = index_kind {
+            entries.push(StatusEntry {
+                path: path.clone(),
+                old_path,
+                staged: true,
+                status_kind: kind,
+            });
+        }
+        if let Some(kind) = work_tree_kind {
+            entries.push(StatusEntry {
+                path,
+                old_path: None,
+                staged: false,
+                status_kind: kind,
+            });
+        }
     }
 
     Ok(StatusReloadResult { entries })
@@ -1,9 +1,11 @@
 use gpui::prelude::*;
 use gpui::{
-    Context, Entity, EventEmitter, InteractiveElement, IntoElement, MouseButton, ParentElement,
-    SharedString, StatefulInteractiveElement, Styled, Window, div, px,
+    Context, Div, Entity, EventEmitter, InteractiveElement, IntoElement, MouseButton,
+    ParentElement, SharedString, Stateful, StatefulInteractiveElement, Styled, Window, div, px,
 };
 
+use crate::layout::GraphMode;
+use crate::query::{CommitQuery, QueryMode};
 use crate::repo_picker::{self, RepoPicker, RepoSelected};
 use crate::suggest::SuggestState;
 use crate::text_input::{TextInput, TextInputSubmitted};
@@ -39,13 +41,19 @@ pub struct RepoPathSubmitted {
 }
 
 #[derive(Clone, Debug)]
-pub struct SearchPathSubmitted {
-    pub path: String,
+pub struct SearchQuerySubmitted {
+    pub query: CommitQuery,
+    pub mode: QueryMode,
 }
 
 #[derive(Clone, Debug)]
 pub struct SearchPathCleared;
 
+#[derive(Clone, Debug)]
+pub struct GraphModeChanged {
+    pub mode: GraphMode,
+}
+
 pub struct PathBar {
     repo_picker: Entity<RepoPicker>,
     search_input: Entity<TextInput>,
@@ -53,17 +61,25 @@ pub struct PathBar {
     suggest: SuggestState,
     suggest_open: bool,
     mode: RepoMode,
+    query_mode: QueryMode,
+    graph_mode: GraphMode,
 }
 
 impl EventEmitter<ViewModeChanged> for PathBar {}
 impl EventEmitter<RepoPathSubmitted> for PathBar {}
-impl EventEmitter<SearchPathSubmitted> for PathBar {}
+impl EventEmitter<SearchQuerySubmitted> for PathBar {}
 impl EventEmitter<SearchPathCleared> for PathBar {}
+impl EventEmitter<GraphModeChanged> for PathBar {}
 
 impl PathBar {
     pub fn new(cx: &mut Context<Self>) -> Self {
         let repo_picker = cx.new(RepoPicker::new);
-        let search_input = cx.new(|cx| TextInput::new("src/file.rs", cx));
+        let search_input = cx.new(|cx| {
+            TextInput::new(
+                "src/file.rs author:alice msg:\"fix crash\" since:2026-01-01",
+                cx,
+            )
+        });
 
         cx.subscribe(&repo_picker, Self::on_repo_selected).detach();
         cx.subscribe(&search_input, Self::on_search_submitted)
@@ -76,6 +92,8 @@ impl PathBar {
             suggest: SuggestState::new(),
             suggest_open: false,
             mode: RepoMode::Local,
+            query_mode: QueryMode::default(),
+            graph_mode: GraphMode::default(),
         }
     }
 
@@ -103,24 +121,31 @@ impl PathBar {
         self.suggest_open
     }
 
+    pub fn query_mode(&self) -> QueryMode {
+        self.query_mode
+    }
+
     pub fn suggest_items(&self, cx: &gpui::App) -> Vec<String> {
-        let text = self.search_input.read(cx).text().trim().to_string();
-        if text.is_empty() {
+        let text = self.search_input.read(cx).text().to_string();
+        let Some((_, fragment)) = path_fragment(&text) else {
             return Vec::new();
-        }
+        };
         self.suggest
-            .filter(&text)
+            .filter(fragment)
             .into_iter()
             .map(|s| s.to_string())
             .collect()
     }
 
+    /// Replace the path being typed with `path` and run the search.
     pub fn select_suggestion(&mut self, path: String, cx: &mut Context<Self>) {
+        let text = self.search_input.read(cx).text().to_string();
+        let start = path_fragment(&text).map(|(start, _)| start).unwrap_or(0);
+        let completed = format!("{}{}", &text[..start], path);
         self.search_input
-            .update(cx, |input, cx| input.set_text(&path, cx));
+            .update(cx, |input, cx| input.set_text(&completed, cx));
         self.suggest_open = false;
-        cx.emit(SearchPathSubmitted { path });
-        cx.notify();
+        self.submit_search(cx);
     }
 
     pub fn close_suggest(&mut self) {
@@ -164,24 +189,47 @@ impl PathBar {
         _event: &TextInputSubmitted,
         cx: &mut Context<Self>,
     ) {
-        let path = self.search_input.read(cx).text().trim().to_string();
-        if path.is_empty() {
+        self.submit_search(cx);
+    }
+
+    fn submit_search(&mut self, cx: &mut Context<Self>) {
+        let text = self.search_input.read(cx).text().trim().to_string();
+        if text.is_empty() {
             cx.emit(SearchPathCleared);
             return;
         }
         self.suggest_open = false;
-        cx.emit(SearchPathSubmitted { path });
+        match CommitQuery::parse(&text) {
+            Ok(query) => {
+                self.error_msg = None;
+                cx.emit(SearchQuerySubmitted {
+                    query,
+                    mode: self.query_mode,
+                });
+            }
+            Err(msg) => self.error_msg = Some(msg),
+        }
         cx.notify();
     }
 
-    fn submit_search(&mut self, cx: &mut Context<Self>) {
-        let path = self.search_input.read(cx).text().trim().to_string();
-        if path.is_empty() {
-            cx.emit(SearchPathCleared);
+    fn set_query_mode(&mut self, mode: QueryMode, cx: &mut Context<Self>) {
+        if self.query_mode == mode {
             return;
         }
-        self.suggest_open = false;
-        cx.emit(SearchPathSubmitted { path });
+        self.query_mode = mode;
+        // re-run an active search so the graph switches right away
+        if !self.search_input.read(cx).text().trim().is_empty() {
+            self.submit_search(cx);
+        }
+        cx.notify();
+    }
+
+    fn set_graph_mode(&mut self, mode: GraphMode, cx: &mut Context<Self>) {
+        if self.graph_mode == mode {
+            return;
+        }
+        self.graph_mode = mode;
+        cx.emit(GraphModeChanged { mode });
         cx.notify();
     }
 
@@ -253,6 +301,85 @@ impl PathBar {
             )
             .into_any()
     }
+
+    fn render_query_mode_toggle(&mut self, cx: &mut Context<Self>) -> gpui::AnyElement {
+        let options = [
+            ("query_filter", "FILTER", QueryMode::Filter),
+            ("query_highlight", "HIGHLIGHT", QueryMode::Highlight),
+        ];
+        segmented(options.map(|(id, label, mode)| {
+            toggle_segment(id, label, self.query_mode == mode).on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _ev, _win, cx| this.set_query_mode(mode, cx)),
+            )
+        }))
+    }
+
+    fn render_graph_mode_toggle(&mut self, cx: &mut Context<Self>) -> gpui::AnyElement {
+        let options = [
+            ("graph_full", "ALL", GraphMode::Full),
+            ("graph_first_parent", "FIRST", GraphMode::FirstParent),
+            ("graph_decorated", "REFS", GraphMode::SimplifyByDecoration),
+        ];
+        segmented(options.map(|(id, label, mode)| {
+            toggle_segment(id, label, self.graph_mode == mode).on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _ev, _win, cx| this.set_graph_mode(mode, cx)),
+            )
+        }))
+    }
+}
+
+fn segmented(segments: impl IntoIterator<Item = Stateful<Div>>) -> gpui::AnyElement {
+    div()
+        .flex()
+        .flex_row()
+        .rounded(px(4.0))
+        .border_1()
+        .border_color(gpui::rgb(COLOR_SEPARATOR))
+        .overflow_hidden()
+        .children(segments)
+        .into_any()
+}
+
+fn toggle_segment(id: &'static str, label: &'static str, active: bool) -> Stateful<Div> {
+    div()
+        .id(id)
+        .px(px(6.0))
+        .py(px(2.0))
+        .cursor_pointer()
+        .bg(if active {
+            gpui::rgb(COLOR_MODE_ACTIVE_BG)
+        } else {
+            gpui::rgb(COLOR_MODE_INACTIVE_BG)
+        })
+        .text_color(if active {
+            gpui::rgb(COLOR_MODE_ACTIVE_TEXT)
+        } else {
+            gpui::rgb(COLOR_MODE_INACTIVE_TEXT)
+        })
+        .text_size(px(9.0))
+        .font_weight(gpui::FontWeight::BOLD)
+        .child(label)
+}
+
+/// The bare word at the end of `text` that path suggestions complete, with
+/// its byte offset. `None` while a `key:` term or a quote is being typed.
+fn path_fragment(text: &str) -> Option<(usize, &str)> {
+    if text.matches('"').count() % 2 == 1 {
+        return None;
+    }
+    let start = text
+        .char_indices()
+        .rev()
+        .find(|(_, c)| c.is_whitespace())
+        .map(|(i, c)| i + c.len_utf8())
+        .unwrap_or(0);
+    let fragment = &text[start..];
+    if fragment.is_empty() || fragment.contains(':') || fragment.contains('"') {
+        return None;
+    }
+    Some((start, fragment))
 }
 
 pub fn render_suggest_dropdown(
@@ -327,14 +454,14 @@ pub fn render_suggest_dropdown(
 
 impl Render for PathBar {
     fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        self.repo_picker.update(cx, |p, cx| p.poll_scan(cx));
-
         let error = self.error_msg.clone();
         let mode_toggle = self.render_mode_toggle(cx);
 
         let search_text = self.search_input.read(cx).text().to_string();
-        let suggestions = self.suggest.filter(search_text.trim());
-        self.suggest_open = !suggestions.is_empty() && !search_text.trim().is_empty();
+        self.suggest_open = path_fragment(&search_text)
+            .is_some_and(|(_, fragment)| !self.suggest.filter(fragment).is_empty());
+        let query_toggle = self.render_query_mode_toggle(cx);
+        let graph_toggle = self.render_graph_mode_toggle(cx);
 
         let repo_picker = self.repo_picker.clone();
         let search_input = self.search_input.clone();
@@ -361,13 +488,14 @@ impl Render for PathBar {
             )
             .child(repo_picker::render_button(&repo_picker, cx))
             .child(mode_toggle)
+            .child(graph_toggle)
             .child(div().w(px(1.0)).h(px(20.0)).bg(gpui::rgb(COLOR_SEPARATOR)))
             .child(
                 div()
                     .text_color(gpui::rgb(COLOR_LABEL))
                     .text_size(px(10.0))
                     .font_weight(gpui::FontWeight::BOLD)
-                    .child("PATH"),
+                    .child("SEARCH"),
             )
             .child(
                 div()
@@ -396,12 +524,13 @@ impl Render for PathBar {
                     .text_color(gpui::rgb(0x4A90D9))
                     .text_size(px(10.0))
                     .font_weight(gpui::FontWeight::BOLD)
-                    .child("Filter")
+                    .child("Search")
                     .on_mouse_down(
                         MouseButton::Left,
                         cx.listener(|this, _ev, _win, cx| this.submit_search(cx)),
                     ),
             )
+            .child(query_toggle)
             .when(!search_text.is_empty(), |el| {
                 el.child(
                     div()
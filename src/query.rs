@@ -0,0 +1,187 @@
+use chrono::{Local, NaiveDate, TimeZone};
+use git2::{Commit, DiffOptions, Repository};
+
+const DAY_SECONDS: i64 = 24 * 60 * 60;
+
+/// What the graph does with commits that don't match the query.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum QueryMode {
+    /// hide them, edges are rewritten to connect the remaining commits
+    #[default]
+    Filter,
+    /// keep the full graph and dim them
+    Highlight,
+}
+
+/// A parsed search such as `author:alice msg:"fix crash" since:2026-01-01 src/`.
+///
+/// Every term must match. Bare words are pathspecs, so typing a path alone
+/// still filters by path like before.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CommitQuery {
+    /// lowercase substrings of the author name or email
+    pub authors: Vec<String>,
+    /// lowercase substrings of the full message
+    pub messages: Vec<String>,
+    /// lowercase hex prefixes of the commit id
+    pub shas: Vec<String>,
+    /// inclusive lower bound, seconds since the epoch
+    pub since: Option<i64>,
+    /// exclusive upper bound, seconds since the epoch
+    pub until: Option<i64>,
+    pub paths: Vec<String>,
+}
+
+/// The parts of a commit the query looks at, without the diff.
+pub struct CommitFields<'a> {
+    pub sha: &'a str,
+    pub message: &'a str,
+    pub author_name: &'a str,
+    pub author_email: &'a str,
+    pub time: i64,
+}
+
+impl CommitQuery {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let mut query = Self::default();
+
+        for token in tokenize(input)? {
+            let Some((key, value)) = split_term(&token) else {
+                query.paths.push(token);
+                continue;
+            };
+            // a key with nothing after it is still being typed
+            if value.is_empty() {
+                continue;
+            }
+            match key {
+                "author" => query.authors.push(value.to_lowercase()),
+                "msg" | "message" => query.messages.push(value.to_lowercase()),
+                "sha" => {
+                    if !value.chars().all(|c| c.is_ascii_hexdigit()) {
+                        return Err(format!("sha:{} is not a hex prefix", value));
+                    }
+                    query.shas.push(value.to_lowercase());
+                }
+                "since" => query.since = Some(parse_date(value)?),
+                "until" => query.until = Some(parse_date(value)? + DAY_SECONDS),
+                "path" => query.paths.push(value.to_string()),
+                _ => return Err(format!("unknown filter {}:", key)),
+            }
+        }
+
+        Ok(query)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Whether a match depends on anything but the paths a commit touches.
+    pub fn has_commit_terms(&self) -> bool {
+        !self.authors.is_empty()
+            || !self.messages.is_empty()
+            || !self.shas.is_empty()
+            || self.since.is_some()
+            || self.until.is_some()
+    }
+
+    pub fn matches_fields(&self, fields: &CommitFields) -> bool {
+        let author = format!("{} <{}>", fields.author_name, fields.author_email).to_lowercase();
+        let message = fields.message.to_lowercase();
+        let sha = fields.sha.to_lowercase();
+
+        self.authors.iter().all(|a| author.contains(a.as_str()))
+            && self.messages.iter().all(|m| message.contains(m.as_str()))
+            && self.shas.iter().all(|s| sha.starts_with(s.as_str()))
+            && self.since.is_none_or(|since| fields.time >= since)
+            && self.until.is_none_or(|until| fields.time < until)
+    }
+
+    pub fn matches(&self, repo: &Repository, commit: &Commit) -> bool {
+        if self.has_commit_terms() {
+            let author = commit.author();
+            let sha = commit.id().to_string();
+            let fields = CommitFields {
+                sha: &sha,
+                message: commit.message().unwrap_or_default(),
+                author_name: author.name().unwrap_or_default(),
+                author_email: author.email().unwrap_or_default(),
+                time: commit.time().seconds(),
+            };
+            if !self.matches_fields(&fields) {
+                return false;
+            }
+        }
+        self.paths.is_empty() || touches_paths(repo, commit, &self.paths)
+    }
+}
+
+fn touches_paths(repo: &Repository, commit: &Commit, paths: &[String]) -> bool {
+    let parent = commit.parent(0).ok();
+    let old_tree = parent.as_ref().and_then(|p| p.tree().ok());
+    let new_tree = commit.tree().ok();
+
+    let mut opts = DiffOptions::new();
+    for path in paths {
+        opts.pathspec(path);
+    }
+
+    let diff = repo.diff_tree_to_tree(old_tree.as_ref(), new_tree.as_ref(), Some(&mut opts));
+    diff.map(|d| d.deltas().len() > 0).unwrap_or(false)
+}
+
+/// Split on whitespace, keeping double-quoted runs together and dropping the quotes.
+fn tokenize(input: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_token = false;
+
+    for c in input.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_token = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if has_token {
+                    tokens.push(std::mem::take(&mut current));
+                    has_token = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_token = true;
+            }
+        }
+    }
+
+    if in_quotes {
+        return Err("unterminated quote".to_string());
+    }
+    if has_token {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+fn split_term(token: &str) -> Option<(&str, &str)> {
+    let (key, value) = token.split_once(':')?;
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    Some((key, value))
+}
+
+/// Local midnight at the start of a `YYYY-MM-DD` date.
+fn parse_date(value: &str) -> Result<i64, String> {
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| format!("{} is not a YYYY-MM-DD date", value))?;
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|t| t.timestamp())
+        .ok_or_else(|| format!("{} does not exist in the local timezone", value))
+}
@@ -0,0 +1,257 @@
+use std::collections::{HashMap, HashSet};
+
+use git2::{Oid, Repository};
+
+use crate::path_bar::RepoMode;
+use crate::stash;
+
+const LOCAL_PREFIX: &str = "refs/heads/";
+const REMOTE_PREFIX: &str = "refs/remotes/";
+const TAG_PREFIX: &str = "refs/tags/";
+
+/// Which refs the graph revwalk starts from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RefSelection {
+    pub head: bool,
+    pub local: bool,
+    pub remote: bool,
+    pub tags: bool,
+}
+
+impl Default for RefSelection {
+    fn default() -> Self {
+        Self::local_branches()
+    }
+}
+
+impl RefSelection {
+    pub fn head_only() -> Self {
+        Self {
+            head: true,
+            local: false,
+            remote: false,
+            tags: false,
+        }
+    }
+
+    /// HEAD plus every local branch, so unmerged feature branches show up.
+    pub fn local_branches() -> Self {
+        Self {
+            head: true,
+            local: true,
+            remote: false,
+            tags: false,
+        }
+    }
+
+    pub fn remotes() -> Self {
+        Self {
+            head: false,
+            local: false,
+            remote: true,
+            tags: false,
+        }
+    }
+
+    pub fn tags() -> Self {
+        Self {
+            head: false,
+            local: false,
+            remote: false,
+            tags: true,
+        }
+    }
+
+    pub fn all() -> Self {
+        Self {
+            head: true,
+            local: true,
+            remote: true,
+            tags: true,
+        }
+    }
+
+    pub fn includes(&self, refname: &str) -> bool {
+        (self.local && refname.starts_with(LOCAL_PREFIX))
+            || (self.remote && refname.starts_with(REMOTE_PREFIX))
+            || (self.tags && refname.starts_with(TAG_PREFIX))
+    }
+
+    /// The commits the graph revwalk starts from, sorted and deduplicated.
+    /// Falls back to HEAD when the selection matches nothing (e.g. REMOTE
+    /// mode in a repo without remotes).
+    pub fn tips(&self, repo: &Repository) -> Vec<Oid> {
+        let head = repo
+            .head()
+            .ok()
+            .and_then(|head| head.peel_to_commit().ok())
+            .map(|commit| commit.id());
+        let mut tips = Vec::new();
+
+        if self.head {
+            tips.extend(head);
+        }
+
+        if let Ok(references) = repo.references() {
+            for reference in references.flatten() {
+                let Some(name) = reference.name() else {
+                    continue;
+                };
+                if !self.includes(name) {
+                    continue;
+                }
+                if let Ok(commit) = reference.peel_to_commit() {
+                    tips.push(commit.id());
+                }
+            }
+        }
+
+        // stashes are local work, drawn next to the commit they were made on
+        if self.local {
+            tips.extend(stash::list(repo).iter().map(|entry| entry.oid));
+        }
+
+        if tips.is_empty() {
+            tips.extend(head);
+        }
+        tips.sort();
+        tips.dedup();
+        tips
+    }
+}
+
+impl From<&RepoMode> for RefSelection {
+    fn from(mode: &RepoMode) -> Self {
+        match mode {
+            RepoMode::Local => Self::local_branches(),
+            RepoMode::Remote => Self::remotes(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RefKind {
+    Head,
+    Local,
+    Remote,
+    Tag,
+    Stash,
+}
+
+/// A ref pointing at a commit, as shown in the graph badges.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RefLabel {
+    pub name: String,
+    pub kind: RefKind,
+}
+
+impl RefLabel {
+    pub fn new(name: impl Into<String>, kind: RefKind) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+        }
+    }
+}
+
+/// Every branch, remote branch, tag, stash and HEAD, keyed by the commit they point at.
+#[derive(Clone, Debug, Default)]
+pub struct RefIndex {
+    labels: HashMap<Oid, Vec<RefLabel>>,
+}
+
+impl RefIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn build(repo: &Repository) -> Self {
+        let mut index = Self::new();
+
+        if let Ok(references) = repo.references() {
+            for reference in references.flatten() {
+                let Some(name) = reference.name() else {
+                    continue;
+                };
+                let kind = if name.starts_with(LOCAL_PREFIX) {
+                    RefKind::Local
+                } else if name.starts_with(REMOTE_PREFIX) {
+                    RefKind::Remote
+                } else if name.starts_with(TAG_PREFIX) {
+                    RefKind::Tag
+                } else {
+                    continue;
+                };
+                // origin/HEAD only mirrors another remote branch
+                if kind == RefKind::Remote && name.ends_with("/HEAD") {
+                    continue;
+                }
+                let Ok(commit) = reference.peel_to_commit() else {
+                    continue;
+                };
+                let short = reference.shorthand().unwrap_or(name).to_string();
+                index.insert(commit.id(), RefLabel::new(short, kind));
+            }
+        }
+
+        if let Ok(head) = repo.head()
+            && let Ok(commit) = head.peel_to_commit()
+        {
+            index.insert(commit.id(), RefLabel::new("HEAD", RefKind::Head));
+        }
+
+        for entry in stash::list(repo) {
+            index.insert(entry.oid, RefLabel::new(entry.name(), RefKind::Stash));
+        }
+
+        for labels in index.labels.values_mut() {
+            labels.sort_by(|a, b| a.kind.cmp(&b.kind).then_with(|| a.name.cmp(&b.name)));
+        }
+        index
+    }
+
+    pub fn insert(&mut self, oid: Oid, label: RefLabel) {
+        self.labels.entry(oid).or_default().push(label);
+    }
+
+    pub fn get(&self, oid: &Oid) -> &[RefLabel] {
+        self.labels.get(oid).map(|l| l.as_slice()).unwrap_or(&[])
+    }
+
+    /// The branch a commit is the tip of, used to pick its lane color.
+    /// Remote branches drop their remote prefix so `origin/main` and `main`
+    /// share an identity.
+    pub fn branch(&self, oid: &Oid) -> Option<&str> {
+        self.get(oid).iter().find_map(|label| match label.kind {
+            RefKind::Local => Some(label.name.as_str()),
+            RefKind::Remote => Some(
+                label
+                    .name
+                    .split_once('/')
+                    .map(|(_, branch)| branch)
+                    .unwrap_or(&label.name),
+            ),
+            RefKind::Head | RefKind::Tag | RefKind::Stash => None,
+        })
+    }
+
+    /// Commits whose branch name differs between the two indexes, and with
+    /// `decorations` also those that gained or lost every label. These are
+    /// the commits whose row a refresh has to lay out again, once those the
+    /// walk never reaches are left out.
+    pub fn layout_changes(&self, other: &RefIndex, decorations: bool) -> HashSet<Oid> {
+        self.labels
+            .keys()
+            .chain(other.labels.keys())
+            .filter(|oid| {
+                self.branch(oid) != other.branch(oid)
+                    || (decorations && self.get(oid).is_empty() != other.get(oid).is_empty())
+            })
+            .copied()
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+}
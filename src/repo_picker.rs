@@ -4,8 +4,7 @@ use gpui::{
     SharedString, StatefulInteractiveElement, Styled, div, px,
 };
 
-use std::sync::mpsc::Receiver;
-
+use crate::job::Job;
 use crate::repo_scanner::{scan_git_repos, short_name};
 
 const MAX_VISIBLE: usize = 12;
@@ -23,21 +22,17 @@ pub struct RepoPicker {
     selected: Option<String>,
     is_open: bool,
     scanning: bool,
-    pending_rx: Option<Receiver<Vec<String>>>,
+    scan_job: Option<Job>,
 }
 
 impl EventEmitter<RepoSelected> for RepoPicker {}
 
 impl RepoPicker {
     pub fn new(cx: &mut Context<Self>) -> Self {
-        let (tx, rx) = std::sync::mpsc::channel();
-        std::thread::spawn(move || {
-            let repos = scan_git_repos();
-            let _ = tx.send(repos);
-        });
-
-        let _ = cx.on_release(|this: &mut RepoPicker, _cx| {
-            this.pending_rx = None;
+        let scan_job = Job::spawn(cx, scan_git_repos, |this: &mut Self, repos, _cx| {
+            this.repos = repos;
+            this.scanning = false;
+            this.scan_job = None;
         });
 
         Self {
@@ -45,7 +40,7 @@ impl RepoPicker {
             selected: None,
             is_open: false,
             scanning: true,
-            pending_rx: Some(rx),
+            scan_job: Some(scan_job),
         }
     }
 
@@ -83,27 +78,6 @@ impl RepoPicker {
             cx.notify();
         }
     }
-
-    pub fn poll_scan(&mut self, cx: &mut Context<Self>) {
-        let Some(rx) = &self.pending_rx else {
-            return;
-        };
-        match rx.try_recv() {
-            Ok(repos) => {
-                self.repos = repos;
-                self.scanning = false;
-                self.pending_rx = None;
-                cx.notify();
-            }
-            Err(std::sync::mpsc::TryRecvError::Empty) => {
-                cx.notify();
-            }
-            Err(std::sync::mpsc::TryRecvError::Disconnected) => {
-                self.scanning = false;
-                self.pending_rx = None;
-            }
-        }
-    }
 }
 
 pub fn render_button(
@@ -0,0 +1,108 @@
+use std::path::Path;
+
+use git2::{ApplyLocation, Diff, Repository};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StageAction {
+    Stage,
+    Unstage,
+}
+
+impl StageAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            StageAction::Stage => "Stage",
+            StageAction::Unstage => "Unstage",
+        }
+    }
+}
+
+/// Stage or unstage `paths`, relative to the working tree.
+pub fn run_bg(repo_path: &str, action: StageAction, paths: &[String]) -> Result<(), String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    match action {
+        StageAction::Stage => stage(&repo, paths),
+        StageAction::Unstage => unstage(&repo, paths),
+    }
+    .map_err(|e| e.to_string())
+}
+
+/// Put the working tree version of `paths` in the index. A path that is gone
+/// from the working tree is removed from the index, staging the deletion; a
+/// symlink counts as there even when its target is not. Adding a conflicted
+/// path marks it resolved.
+fn stage(repo: &Repository, paths: &[String]) -> Result<(), git2::Error> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| git2::Error::from_str("cannot stage in a bare repository"))?;
+    let mut index = repo.index()?;
+    for path in paths {
+        let path = Path::new(path);
+        if std::fs::symlink_metadata(workdir.join(path)).is_ok() {
+            index.add_path(path)?;
+        } else {
+            index.remove_path(path)?;
+        }
+    }
+    index.write()
+}
+
+/// Put the HEAD version of `paths` back in the index, leaving the working
+/// tree alone. Before the first commit there is no HEAD, so they are dropped
+/// from the index instead.
+fn unstage(repo: &Repository, paths: &[String]) -> Result<(), git2::Error> {
+    match repo.head().and_then(|head| head.peel_to_commit()) {
+        Ok(commit) => {
+            repo.reset_default(Some(commit.as_object()), paths.iter().map(String::as_str))
+        }
+        Err(_) => {
+            let mut index = repo.index()?;
+            for path in paths {
+                index.remove_path(Path::new(path))?;
+            }
+            index.write()
+        }
+    }
+}
+
+/// What a hunk or a selection of lines from the diff viewer is used for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HunkAction {
+    /// add the working tree change to the index
+    Stage,
+    /// take the staged change back out of the index
+    Unstage,
+    /// drop the unstaged change from the working tree
+    Discard,
+}
+
+impl HunkAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            HunkAction::Stage => "Stage",
+            HunkAction::Unstage => "Unstage",
+            HunkAction::Discard => "Discard",
+        }
+    }
+
+    /// Whether the patch has to be applied backwards: undoing a change
+    /// rather than making it.
+    pub fn reverse(&self) -> bool {
+        !matches!(self, HunkAction::Stage)
+    }
+
+    fn location(&self) -> ApplyLocation {
+        match self {
+            HunkAction::Stage | HunkAction::Unstage => ApplyLocation::Index,
+            HunkAction::Discard => ApplyLocation::WorkDir,
+        }
+    }
+}
+
+/// Apply `patch`, as built by `diff_viewer::build_patch` for `action`.
+pub fn apply_patch_bg(repo_path: &str, action: HunkAction, patch: &str) -> Result<(), String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let diff = Diff::from_buffer(patch.as_bytes()).map_err(|e| e.to_string())?;
+    repo.apply(&diff, action.location(), None)
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,100 @@
+use std::collections::{HashMap, HashSet};
+
+use git2::{Oid, Repository};
+
+const STASH_REF: &str = "refs/stash";
+
+/// One entry of the stash list, `stash@{index}`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StashEntry {
+    pub index: usize,
+    pub oid: Oid,
+    pub message: String,
+}
+
+impl StashEntry {
+    /// The name git uses for the entry, e.g. `stash@{0}`.
+    pub fn name(&self) -> String {
+        format!("stash@{{{}}}", self.index)
+    }
+}
+
+/// Every stash entry, newest first. Stashes are kept in the reflog of
+/// `refs/stash`, so this works on a shared repository.
+pub fn list(repo: &Repository) -> Vec<StashEntry> {
+    let Ok(reflog) = repo.reflog(STASH_REF) else {
+        return Vec::new();
+    };
+    reflog
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| StashEntry {
+            index,
+            oid: entry.id_new(),
+            message: entry.message().unwrap_or_default().to_string(),
+        })
+        .collect()
+}
+
+/// Stash entries keyed by their commit. A stash commit carries extra parents
+/// for the index and untracked files; those helpers are never drawn.
+#[derive(Clone, Debug, Default)]
+pub struct StashIndex {
+    entries: HashMap<Oid, StashEntry>,
+    helpers: HashSet<Oid>,
+}
+
+impl StashIndex {
+    pub fn build(repo: &Repository) -> Self {
+        let mut index = Self::default();
+        for entry in list(repo) {
+            if let Ok(commit) = repo.find_commit(entry.oid) {
+                index.helpers.extend(commit.parent_ids().skip(1));
+            }
+            index.entries.insert(entry.oid, entry);
+        }
+        index
+    }
+
+    pub fn get(&self, oid: &Oid) -> Option<&StashEntry> {
+        self.entries.get(oid)
+    }
+
+    pub fn is_helper(&self, oid: &Oid) -> bool {
+        self.helpers.contains(oid)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StashAction {
+    Apply,
+    Pop,
+    Drop,
+}
+
+impl StashAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            StashAction::Apply => "Apply",
+            StashAction::Pop => "Pop",
+            StashAction::Drop => "Drop",
+        }
+    }
+}
+
+/// Run `action` on the stash entry whose commit is `oid`. The entry is looked
+/// up again first, since its index shifts when other stashes come and go.
+pub fn run_bg(repo_path: &str, action: StashAction, oid: Oid) -> Result<(), String> {
+    let mut repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    let index = list(&repo)
+        .iter()
+        .position(|entry| entry.oid == oid)
+        .ok_or_else(|| "stash entry no longer exists".to_string())?;
+
+    match action {
+        StashAction::Apply => repo.stash_apply(index, None),
+        StashAction::Pop => repo.stash_pop(index, None),
+        StashAction::Drop => repo.stash_drop(index),
+    }
+    .map_err(|e| e.to_string())
+}
@@ -1,19 +1,47 @@
 use std::cell::RefCell;
+use std::collections::HashSet;
 use std::rc::Rc;
 
 use gpui::prelude::*;
 use gpui::{
-    AnyElement, Context, EventEmitter, IntoElement, ParentElement, Render, SharedString,
-    StatefulInteractiveElement, Styled, Window, div, px,
+    AnyElement, App, Context, EventEmitter, FocusHandle, Focusable, InteractiveElement,
+    IntoElement, MouseButton, ParentElement, Render, SharedString, StatefulInteractiveElement,
+    Styled, Window, actions, div, px,
 };
 
+use crate::job::Job;
+use crate::staging::{self, StageAction};
+use crate::widgets;
+
+actions!(
+    status_panel,
+    [
+        SelectNextEntry,
+        SelectPrevEntry,
+        ToggleStaged,
+        OpenEntry,
+        StageAll,
+        UnstageAll
+    ]
+);
+
 #[derive(Clone, Debug)]
 pub struct StatusEntry {
     pub path: String,
+    /// Where a staged rename came from.
+    pub old_path: Option<String>,
     pub staged: bool,
     pub status_kind: StatusKind,
 }
 
+impl StatusEntry {
+    /// The paths to stage or unstage together: both sides of a rename,
+    /// otherwise just the one.
+    pub fn paths(&self) -> Vec<String> {
+        self.old_path.iter().chain([&self.path]).cloned().collect()
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum StatusKind {
     New,
@@ -27,31 +55,62 @@ pub enum StatusKind {
 #[derive(Clone, Debug)]
 pub struct StatusUpdated;
 
+/// A row was clicked: show the staged or unstaged diff of its file.
+#[derive(Clone, Debug)]
+pub struct StatusEntryOpened {
+    pub path: String,
+    pub staged: bool,
+}
+
 pub struct StatusReloadResult {
     pub entries: Vec<StatusEntry>,
 }
 
+impl StatusReloadResult {
+    pub fn dirty_count(&self) -> usize {
+        count_paths(&self.entries)
+    }
+}
+
+/// Files with any change; a partly staged file counts once.
+fn count_paths(entries: &[StatusEntry]) -> usize {
+    let paths: HashSet<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+    paths.len()
+}
+
 pub struct StatusPanel {
     repo_path: Option<String>,
     entries: Vec<StatusEntry>,
     loading: bool,
+    focus_handle: FocusHandle,
+    /// index into the rows as drawn, staged entries first
+    selected: Option<usize>,
+    stage_job: Option<Job>,
+    error: Option<String>,
 }
 
 impl EventEmitter<StatusUpdated> for StatusPanel {}
+impl EventEmitter<StatusEntryOpened> for StatusPanel {}
 
 const COLOR_LOADING_TEXT: u32 = 0x888888;
+const COLOR_SELECTED_BG: u32 = 0x2F3F55;
 
 impl StatusPanel {
-    pub fn new(_repo: Rc<RefCell<Option<git2::Repository>>>) -> Self {
+    pub fn new(_repo: Rc<RefCell<Option<git2::Repository>>>, cx: &mut Context<Self>) -> Self {
         Self {
             repo_path: None,
             entries: Vec::new(),
             loading: false,
+            focus_handle: cx.focus_handle(),
+            selected: None,
+            stage_job: None,
+            error: None,
         }
     }
 
     pub fn set_repo_path(&mut self, path: String) {
         self.repo_path = Some(path);
+        self.error = None;
     }
 
     pub fn set_loading(&mut self, cx: &mut Context<Self>) {
@@ -61,19 +120,154 @@ impl StatusPanel {
     }
 
     pub fn apply_data(&mut self, data: &StatusReloadResult, cx: &mut Context<Self>) {
-        self.entries = data.entries.clone();
+        // staged entries are drawn first, so keep them first here too
+        let (mut entries, unstaged): (Vec<StatusEntry>, Vec<StatusEntry>) =
+            data.entries.iter().cloned().partition(|e| e.staged);
+        entries.extend(unstaged);
+        self.entries = entries;
+        self.selected = self
+            .selected
+            .map(|index| index.min(self.entries.len().saturating_sub(1)))
+            .filter(|_| !self.entries.is_empty());
         self.loading = false;
         cx.notify();
     }
 
     pub fn dirty_count(&self) -> usize {
-        self.entries.len()
+        count_paths(&self.entries)
+    }
+
+    /// Stage or unstage `paths` in the background; `StatusUpdated` follows
+    /// once the index is written.
+    pub fn run_stage_action(
+        &mut self,
+        action: StageAction,
+        paths: Vec<String>,
+        cx: &mut Context<Self>,
+    ) {
+        if self.stage_job.is_some() || paths.is_empty() {
+            return;
+        }
+        let Some(repo_path) = self.repo_path.clone() else {
+            return;
+        };
+        self.error = None;
+        self.stage_job = Some(Job::spawn(
+            cx,
+            move || staging::run_bg(&repo_path, action, &paths),
+            Self::on_stage_done,
+        ));
+        cx.notify();
+    }
+
+    fn on_stage_done(&mut self, result: Result<(), String>, cx: &mut Context<Self>) {
+        self.stage_job = None;
+        match result {
+            Ok(()) => cx.emit(StatusUpdated),
+            Err(msg) => self.error = Some(format!("staging failed: {}", msg)),
+        }
+        cx.notify();
+    }
+
+    /// Every path on one side, for the header actions.
+    fn paths(&self, staged: bool) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|e| e.staged == staged)
+            .flat_map(StatusEntry::paths)
+            .collect()
+    }
+
+    /// Move the selected entry to the other side.
+    fn toggle_entry(&mut self, index: usize, cx: &mut Context<Self>) {
+        let Some(entry) = self.entries.get(index) else {
+            return;
+        };
+        let action = if entry.staged {
+            StageAction::Unstage
+        } else {
+            StageAction::Stage
+        };
+        let paths = entry.paths();
+        self.run_stage_action(action, paths, cx);
+    }
+
+    /// Select the entry and show its diff.
+    fn open_entry(&mut self, index: usize, cx: &mut Context<Self>) {
+        let Some(entry) = self.entries.get(index) else {
+            return;
+        };
+        cx.emit(StatusEntryOpened {
+            path: entry.path.clone(),
+            staged: entry.staged,
+        });
+        self.selected = Some(index);
+        cx.notify();
+    }
+
+    fn move_selection(&mut self, delta: isize, cx: &mut Context<Self>) {
+        if self.entries.is_empty() {
+            return;
+        }
+        let last = self.entries.len() - 1;
+        self.selected = Some(match self.selected {
+            None if delta < 0 => last,
+            None => 0,
+            Some(index) => index.saturating_add_signed(delta).min(last),
+        });
+        cx.notify();
+    }
+
+    fn select_next(&mut self, _: &SelectNextEntry, _: &mut Window, cx: &mut Context<Self>) {
+        self.move_selection(1, cx);
+    }
+
+    fn select_prev(&mut self, _: &SelectPrevEntry, _: &mut Window, cx: &mut Context<Self>) {
+        self.move_selection(-1, cx);
+    }
+
+    fn toggle_selected(&mut self, _: &ToggleStaged, _: &mut Window, cx: &mut Context<Self>) {
+        if let Some(index) = self.selected {
+            self.toggle_entry(index, cx);
+        }
+    }
+
+    fn open_selected(&mut self, _: &OpenEntry, _: &mut Window, cx: &mut Context<Self>) {
+        if let Some(index) = self.selected {
+            self.open_entry(index, cx);
+        }
+    }
+
+    fn stage_all(&mut self, _: &StageAll, _: &mut Window, cx: &mut Context<Self>) {
+        let paths = self.paths(false);
+        self.run_stage_action(StageAction::Stage, paths, cx);
+    }
+
+    fn unstage_all(&mut self, _: &UnstageAll, _: &mut Window, cx: &mut Context<Self>) {
+        let paths = self.paths(true);
+        self.run_stage_action(StageAction::Unstage, paths, cx);
+    }
+}
+
+impl Focusable for StatusPanel {
+    fn focus_handle(&self, _: &App) -> FocusHandle {
+        self.focus_handle.clone()
     }
 }
 
 impl Render for StatusPanel {
-    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
-        self.render_panel()
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .size_full()
+            .key_context("StatusPanel")
+            .track_focus(&self.focus_handle)
+            .on_action(cx.listener(Self::select_next))
+            .on_action(cx.listener(Self::select_prev))
+            .on_action(cx.listener(Self::toggle_selected))
+            .on_action(cx.listener(Self::open_selected))
+            .on_action(cx.listener(Self::stage_all))
+            .on_action(cx.listener(Self::unstage_all))
+            .child(self.render_panel(cx))
     }
 }
 
@@ -89,7 +283,7 @@ impl StatusPanel {
         }
     }
 
-    fn render_panel(&self) -> AnyElement {
+    fn render_panel(&self, cx: &mut Context<Self>) -> AnyElement {
         let has_repo = self.repo_path.is_some();
 
         if !has_repo {
@@ -144,8 +338,9 @@ impl StatusPanel {
                 .into_any();
         }
 
-        let staged: Vec<&StatusEntry> = self.entries.iter().filter(|e| e.staged).collect();
-        let unstaged: Vec<&StatusEntry> = self.entries.iter().filter(|e| !e.staged).collect();
+        let staged = self.entries.iter().filter(|e| e.staged).count();
+        let unstaged = self.entries.len() - staged;
+        let busy = self.stage_job.is_some();
 
         div()
             .size_full()
@@ -163,25 +358,57 @@ impl StatusPanel {
                     .text_color(gpui::rgb(0xCCCCCC))
                     .font_weight(gpui::FontWeight::BOLD)
                     .text_size(px(12.0))
+                    .flex()
+                    .flex_row()
+                    .items_center()
+                    .justify_between()
                     .child(format!(
                         "Changes ({} staged, {} unstaged)",
-                        staged.len(),
-                        unstaged.len()
-                    )),
+                        staged, unstaged
+                    ))
+                    .child(
+                        div()
+                            .flex()
+                            .flex_row()
+                            .gap_1()
+                            .when(unstaged > 0, |el| {
+                                el.child(widgets::button(
+                                    "stage-all",
+                                    "Stage all",
+                                    !busy,
+                                    cx.listener(|this, _event, _window, cx| {
+                                        let paths = this.paths(false);
+                                        this.run_stage_action(StageAction::Stage, paths, cx);
+                                    }),
+                                ))
+                            })
+                            .when(staged > 0, |el| {
+                                el.child(widgets::button(
+                                    "unstage-all",
+                                    "Unstage all",
+                                    !busy,
+                                    cx.listener(|this, _event, _window, cx| {
+                                        let paths = this.paths(true);
+                                        this.run_stage_action(StageAction::Unstage, paths, cx);
+                                    }),
+                                ))
+                            }),
+                    ),
             )
+            .when_some(self.error.clone(), |el, msg| {
+                el.child(widgets::error_row(msg))
+            })
             .child(
                 div()
                     .id("status_list")
                     .flex_1()
                     .overflow_y_scroll()
-                    .children(staged.iter().map(|entry| {
-                        let (label, color) = Self::status_label(entry.status_kind);
-                        Self::render_entry(&entry.path, label, color, true)
-                    }))
-                    .children(unstaged.iter().map(|entry| {
-                        let (label, color) = Self::status_label(entry.status_kind);
-                        Self::render_entry(&entry.path, label, color, false)
-                    }))
+                    .children(
+                        self.entries
+                            .iter()
+                            .enumerate()
+                            .map(|(index, entry)| self.render_entry(index, entry, busy, cx)),
+                    )
                     .when(self.entries.is_empty(), |el| {
                         el.child(
                             div()
@@ -198,19 +425,31 @@ impl StatusPanel {
             .into_any()
     }
 
-    fn render_entry(path: &str, label: char, color: u32, staged: bool) -> impl IntoElement {
-        let section_color = if staged {
+    fn render_entry(
+        &self,
+        index: usize,
+        entry: &StatusEntry,
+        busy: bool,
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        let (label, color) = Self::status_label(entry.status_kind);
+        let staged = entry.staged;
+        let section_color = if self.selected == Some(index) {
+            gpui::rgb(COLOR_SELECTED_BG)
+        } else if staged {
             gpui::rgb(0x3A3A2A)
         } else {
             gpui::rgb(0x1E1E1E)
         };
+        let action = if staged {
+            StageAction::Unstage
+        } else {
+            StageAction::Stage
+        };
+        let prefix = if staged { "s" } else { "u" };
 
         div()
-            .id(SharedString::from(format!(
-                "{}-{}",
-                if staged { "s" } else { "u" },
-                path
-            )))
+            .id(SharedString::from(format!("{}-{}", prefix, entry.path)))
             .w_full()
             .px(px(10.0))
             .py(px(3.0))
@@ -221,6 +460,12 @@ impl StatusPanel {
             .flex_row()
             .items_center()
             .gap_2()
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _event, _window, cx| {
+                    this.open_entry(index, cx);
+                }),
+            )
             .child(
                 div()
                     .w(px(20.0))
@@ -238,7 +483,20 @@ impl StatusPanel {
                     .font_family("monospace")
                     .overflow_hidden()
                     .whitespace_nowrap()
-                    .child(path.to_string()),
+                    .child(match &entry.old_path {
+                        Some(old) => format!("{} → {}", old, entry.path),
+                        None => entry.path.clone(),
+                    }),
             )
+            .child(widgets::button(
+                SharedString::from(format!("{}-{}-{}", prefix, action.label(), entry.path)),
+                if staged { "-" } else { "+" },
+                !busy,
+                cx.listener(move |this, _event, _window, cx| {
+                    this.selected = Some(index);
+                    this.toggle_entry(index, cx);
+                }),
+            ))
+            .into_any()
     }
 }
@@ -0,0 +1,519 @@
+use git2::{Oid, Reference, Repository};
+use gpui::prelude::*;
+use gpui::{
+    AnyElement, App, Context, Entity, EventEmitter, InteractiveElement, IntoElement, MouseButton,
+    ParentElement, Render, SharedString, StatefulInteractiveElement, Styled, Window, actions, div,
+    px,
+};
+
+use crate::composer;
+use crate::job::Job;
+use crate::text_input::TextInput;
+use crate::widgets::{
+    self, COLOR_BG, COLOR_BORDER, COLOR_DIM_TEXT, COLOR_HEADING_BG, COLOR_HOVER_BG, COLOR_NO_REPO,
+    COLOR_TEXT, NameField,
+};
+
+actions!(tags, [ConfirmTagForm, CancelTagForm]);
+
+const COLOR_TAG_TEXT: u32 = 0xE5C07B;
+
+#[derive(Clone, Debug)]
+pub struct TagInfo {
+    pub name: String,
+    /// the commit the tag points at, through the tag object if annotated
+    pub target: Oid,
+    pub annotated: bool,
+}
+
+pub struct TagReloadResult {
+    pub tags: Vec<TagInfo>,
+}
+
+/// A tag was clicked: show its commit.
+#[derive(Clone, Debug)]
+pub struct TagOpened {
+    pub oid: Oid,
+}
+
+/// A tag was created or deleted.
+#[derive(Clone, Debug)]
+pub struct TagsChanged;
+
+/// The name input is open for a new tag on `at`.
+#[derive(Clone, Copy, Debug)]
+struct TagForm {
+    at: Oid,
+    annotated: bool,
+}
+
+pub struct TagPanel {
+    repo_path: Option<String>,
+    tags: Vec<TagInfo>,
+    loading: bool,
+    name: NameField,
+    message_input: Entity<TextInput>,
+    form: Option<TagForm>,
+    /// commit selected in the graph, for new tags
+    selected_commit: Option<Oid>,
+    /// who annotated tags will be made by, or why that is not set up
+    tagger: Option<Result<String, String>>,
+    tagger_job: Option<Job>,
+    /// tag whose delete waits for a confirmation
+    confirm_delete: Option<String>,
+    edit_job: Option<Job>,
+    error: Option<String>,
+}
+
+impl EventEmitter<TagOpened> for TagPanel {}
+impl EventEmitter<TagsChanged> for TagPanel {}
+
+impl TagPanel {
+    pub fn new(cx: &mut Context<Self>) -> Self {
+        let name = NameField::new("tag", validate_tag_name, cx);
+        let message_input = cx.new(|cx| TextInput::new("message", cx));
+        cx.observe(&message_input, |_, _, cx| cx.notify()).detach();
+        Self {
+            repo_path: None,
+            tags: Vec::new(),
+            loading: false,
+            name,
+            message_input,
+            form: None,
+            selected_commit: None,
+            tagger: None,
+            tagger_job: None,
+            confirm_delete: None,
+            edit_job: None,
+            error: None,
+        }
+    }
+
+    pub fn set_repo_path(&mut self, path: String) {
+        if self.repo_path.as_deref() != Some(path.as_str()) {
+            self.tagger = None;
+            self.form = None;
+            self.confirm_delete = None;
+        }
+        self.repo_path = Some(path);
+    }
+
+    pub fn set_loading(&mut self, cx: &mut Context<Self>) {
+        self.loading = true;
+        self.tags.clear();
+        cx.notify();
+    }
+
+    pub fn apply_data(&mut self, data: &TagReloadResult, cx: &mut Context<Self>) {
+        self.tags = data.tags.clone();
+        self.loading = false;
+        cx.notify();
+    }
+
+    pub fn tags(&self) -> &[TagInfo] {
+        &self.tags
+    }
+
+    pub fn set_selected_commit(&mut self, oid: Option<Oid>, cx: &mut Context<Self>) {
+        self.selected_commit = oid;
+        cx.notify();
+    }
+
+    /// Open the form for a tag on the commit selected in the graph.
+    fn start_create(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(at) = self.selected_commit else {
+            return;
+        };
+        self.form = Some(TagForm {
+            at,
+            annotated: false,
+        });
+        self.error = None;
+        self.message_input.update(cx, |input, cx| input.clear(cx));
+        self.name.start("", window, cx);
+        cx.notify();
+    }
+
+    fn toggle_annotated(&mut self, cx: &mut Context<Self>) {
+        let Some(form) = &mut self.form else {
+            return;
+        };
+        form.annotated = !form.annotated;
+        // the tagger is only shown, and looked up, for annotated tags
+        if form.annotated
+            && self.tagger.is_none()
+            && let Some(repo_path) = self.repo_path.clone()
+        {
+            self.tagger_job = Some(Job::spawn(
+                cx,
+                move || composer::identity_bg(&repo_path),
+                |this, result, _cx| {
+                    this.tagger_job = None;
+                    this.tagger = Some(result);
+                },
+            ));
+        }
+        cx.notify();
+    }
+
+    fn close_form(&mut self, cx: &mut Context<Self>) {
+        self.form = None;
+        self.error = None;
+        cx.notify();
+    }
+
+    /// An annotated tag without a message, which git refuses.
+    fn missing_message(&self, cx: &App) -> bool {
+        self.form.is_some_and(|form| form.annotated)
+            && self.message_input.read(cx).text().trim().is_empty()
+    }
+
+    fn name_error(&self, cx: &App) -> Option<String> {
+        self.name
+            .error(cx, |name| self.tags.iter().any(|t| t.name == name))
+    }
+
+    fn confirm_form(&mut self, cx: &mut Context<Self>) {
+        let (Some(form), Some(repo_path)) = (self.form, self.repo_path.clone()) else {
+            return;
+        };
+        // the form already shows why the name is refused
+        if self.edit_job.is_some() || self.name_error(cx).is_some() || self.missing_message(cx) {
+            return;
+        }
+        let name = self.name.name(cx);
+        let message = form
+            .annotated
+            .then(|| self.message_input.read(cx).text().to_string());
+        self.edit_job = Some(Job::spawn(
+            cx,
+            move || create_tag_bg(&repo_path, &name, form.at, message.as_deref()),
+            Self::on_create_done,
+        ));
+        cx.notify();
+    }
+
+    fn delete(&mut self, name: &str, cx: &mut Context<Self>) {
+        if self.edit_job.is_some() {
+            return;
+        }
+        let Some(repo_path) = self.repo_path.clone() else {
+            return;
+        };
+        let name = name.to_string();
+        self.confirm_delete = None;
+        self.error = None;
+        self.edit_job = Some(Job::spawn(
+            cx,
+            move || delete_tag_bg(&repo_path, &name),
+            |this, result, cx| {
+                this.edit_job = None;
+                match result {
+                    Ok(()) => cx.emit(TagsChanged),
+                    Err(msg) => this.error = Some(msg),
+                }
+            },
+        ));
+        cx.notify();
+    }
+
+    fn on_create_done(&mut self, result: Result<(), String>, cx: &mut Context<Self>) {
+        self.edit_job = None;
+        match result {
+            Ok(()) => {
+                self.form = None;
+                self.error = None;
+                cx.emit(TagsChanged);
+            }
+            Err(msg) => self.error = Some(msg),
+        }
+    }
+
+    fn confirm(&mut self, _: &ConfirmTagForm, _: &mut Window, cx: &mut Context<Self>) {
+        self.confirm_form(cx);
+    }
+
+    fn cancel(&mut self, _: &CancelTagForm, _: &mut Window, cx: &mut Context<Self>) {
+        self.close_form(cx);
+    }
+}
+
+/// Why `name` cannot name a tag, if it cannot.
+pub fn validate_tag_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("enter a tag name".to_string());
+    }
+    if name.starts_with('-') || !Reference::is_valid_name(&format!("refs/tags/{}", name)) {
+        return Err(format!("'{}' is not a valid tag name", name));
+    }
+    Ok(())
+}
+
+/// Tag the commit `at` as `name`. With a `message` the tag is annotated and
+/// the tagger comes from `user.name` and `user.email` in git config;
+/// without one it is a lightweight tag.
+pub fn create_tag_bg(
+    repo_path: &str,
+    name: &str,
+    at: Oid,
+    message: Option<&str>,
+) -> Result<(), String> {
+    validate_tag_name(name)?;
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    if repo.find_reference(&format!("refs/tags/{}", name)).is_ok() {
+        return Err(format!("a tag named '{}' already exists", name));
+    }
+    let target = repo.find_object(at, None).map_err(|e| e.to_string())?;
+    match message {
+        Some(message) if message.trim().is_empty() => {
+            return Err("an annotated tag needs a message".to_string());
+        }
+        Some(message) => {
+            let tagger = repo
+                .signature()
+                .map_err(|_| "set user.name and user.email in git config".to_string())?;
+            let message = git2::message_prettify(message, None).map_err(|e| e.to_string())?;
+            repo.tag(name, &target, &tagger, &message, false)
+        }
+        None => repo.tag_lightweight(name, &target, false),
+    }
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub fn delete_tag_bg(repo_path: &str, name: &str) -> Result<(), String> {
+    let repo = Repository::open(repo_path).map_err(|e| e.to_string())?;
+    repo.tag_delete(name).map_err(|e| e.to_string())
+}
+
+impl Render for TagPanel {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        self.render_panel(cx)
+    }
+}
+
+impl TagPanel {
+    fn render_form(&self, form: TagForm, cx: &mut Context<Self>) -> AnyElement {
+        let name_error = self.name_error(cx);
+        let enabled = self.edit_job.is_none() && name_error.is_none() && !self.missing_message(cx);
+        let tagger = match &self.tagger {
+            Some(Ok(tagger)) => format!("tagger {}", tagger),
+            Some(Err(msg)) => msg.clone(),
+            None => String::new(),
+        };
+
+        div()
+            .w_full()
+            .px(px(10.0))
+            .py(px(4.0))
+            .flex()
+            .flex_col()
+            .gap_1()
+            .border_b_1()
+            .border_color(gpui::rgb(COLOR_BORDER))
+            .key_context("TagForm")
+            .on_action(cx.listener(Self::confirm))
+            .on_action(cx.listener(Self::cancel))
+            .child(
+                div()
+                    .text_color(gpui::rgb(COLOR_DIM_TEXT))
+                    .text_size(px(10.0))
+                    .font_family("monospace")
+                    .child(format!("New tag at {:.7}", form.at.to_string())),
+            )
+            .child(self.name.render(name_error, cx))
+            .when(form.annotated, |el| {
+                el.child(widgets::input_box(&self.message_input, false))
+                    .child(
+                        div()
+                            .text_color(gpui::rgb(COLOR_DIM_TEXT))
+                            .text_size(px(10.0))
+                            .font_family("monospace")
+                            .child(tagger),
+                    )
+            })
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .items_center()
+                    .justify_between()
+                    .child(widgets::button(
+                        "tag_annotated",
+                        if form.annotated {
+                            "[x] annotated"
+                        } else {
+                            "[ ] annotated"
+                        },
+                        true,
+                        cx.listener(|this, _event, _window, cx| this.toggle_annotated(cx)),
+                    ))
+                    .child(
+                        div()
+                            .flex()
+                            .flex_row()
+                            .gap_1()
+                            .child(widgets::button(
+                                "tag_form_confirm",
+                                "Create",
+                                enabled,
+                                cx.listener(|this, _event, _window, cx| this.confirm_form(cx)),
+                            ))
+                            .child(widgets::button(
+                                "tag_form_cancel",
+                                "Cancel",
+                                true,
+                                cx.listener(|this, _event, _window, cx| this.close_form(cx)),
+                            )),
+                    ),
+            )
+            .into_any()
+    }
+
+    fn render_confirm_delete(&self, name: String, cx: &mut Context<Self>) -> AnyElement {
+        widgets::confirm_prompt(
+            "tag_delete",
+            format!("Delete tag '{}'?", name),
+            "Delete",
+            self.edit_job.is_none(),
+            cx.listener(move |this, _event, _window, cx| this.delete(&name, cx)),
+            cx.listener(|this, _event, _window, cx| {
+                this.confirm_delete = None;
+                cx.notify();
+            }),
+        )
+    }
+
+    fn render_panel(&self, cx: &mut Context<Self>) -> AnyElement {
+        if self.repo_path.is_none() {
+            return div()
+                .size_full()
+                .flex()
+                .flex_col()
+                .bg(gpui::rgb(COLOR_BG))
+                .child(
+                    div()
+                        .flex()
+                        .items_center()
+                        .justify_center()
+                        .size_full()
+                        .text_color(gpui::rgb(COLOR_NO_REPO))
+                        .text_size(px(12.0))
+                        .child("No repo"),
+                )
+                .into_any();
+        }
+
+        let busy = self.edit_job.is_some();
+        let can_create = self.selected_commit.is_some() && self.form.is_none();
+
+        div()
+            .size_full()
+            .flex()
+            .flex_col()
+            .bg(gpui::rgb(COLOR_BG))
+            .child(
+                div()
+                    .w_full()
+                    .px(px(10.0))
+                    .py(px(6.0))
+                    .border_b_1()
+                    .border_color(gpui::rgb(COLOR_BORDER))
+                    .bg(gpui::rgb(COLOR_HEADING_BG))
+                    .text_color(gpui::rgb(COLOR_TEXT))
+                    .font_weight(gpui::FontWeight::BOLD)
+                    .text_size(px(12.0))
+                    .flex()
+                    .flex_row()
+                    .items_center()
+                    .justify_between()
+                    .child(format!("Tags ({})", self.tags.len()))
+                    .when(self.form.is_none(), |el| {
+                        el.child(widgets::button(
+                            "tag_new",
+                            "Tag selected",
+                            can_create && !busy,
+                            cx.listener(|this, _event, window, cx| this.start_create(window, cx)),
+                        ))
+                    }),
+            )
+            .when_some(self.form, |el, form| el.child(self.render_form(form, cx)))
+            .when_some(self.confirm_delete.clone(), |el, name| {
+                el.child(self.render_confirm_delete(name, cx))
+            })
+            .when_some(self.error.clone(), |el, msg| {
+                el.child(widgets::error_row(msg))
+            })
+            .child(
+                div()
+                    .id("tag_list")
+                    .flex_1()
+                    .overflow_y_scroll()
+                    .when(self.loading && self.tags.is_empty(), |el| {
+                        el.child(
+                            div()
+                                .flex()
+                                .items_center()
+                                .justify_center()
+                                .py(px(12.0))
+                                .text_color(gpui::rgb(COLOR_DIM_TEXT))
+                                .text_size(px(11.0))
+                                .font_family("monospace")
+                                .child("Loading tags..."),
+                        )
+                    })
+                    .children(self.tags.iter().map(|tag| {
+                        let name = tag.name.clone();
+                        let oid = tag.target;
+                        let label = if tag.annotated {
+                            format!("◆ {}", name)
+                        } else {
+                            format!("◇ {}", name)
+                        };
+
+                        div()
+                            .id(SharedString::from(format!("tag-{}", name)))
+                            .w_full()
+                            .px(px(10.0))
+                            .py(px(4.0))
+                            .hover(|s| s.bg(gpui::rgb(COLOR_HOVER_BG)))
+                            .cursor_pointer()
+                            .flex()
+                            .flex_row()
+                            .items_center()
+                            .gap_2()
+                            .child(
+                                div()
+                                    .flex_1()
+                                    .text_color(gpui::rgb(COLOR_TAG_TEXT))
+                                    .text_size(px(12.0))
+                                    .font_family("monospace")
+                                    .child(label),
+                            )
+                            .child(
+                                div()
+                                    .text_color(gpui::rgb(COLOR_DIM_TEXT))
+                                    .text_size(px(10.0))
+                                    .font_family("monospace")
+                                    .child(format!("{:.7}", oid.to_string())),
+                            )
+                            .child(widgets::button(
+                                SharedString::from(format!("delete-tag-{}", name)),
+                                "delete",
+                                !busy,
+                                cx.listener(move |this, _event, _window, cx| {
+                                    this.confirm_delete = Some(name.clone());
+                                    cx.notify();
+                                }),
+                            ))
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(move |_this, _event, _window, cx| {
+                                    cx.emit(TagOpened { oid });
+                                }),
+                            )
+                    })),
+            )
+            .into_any()
+    }
+}
@@ -28,6 +28,9 @@ actions!(
         Copy,
         Cut,
         Submit,
+        Newline,
+        Up,
+        Down,
     ]
 );
 
@@ -38,9 +41,12 @@ pub struct TextInput {
     selected_range: Range<usize>,
     selection_reversed: bool,
     marked_range: Option<Range<usize>>,
-    last_layout: Option<ShapedLine>,
+    // one shaped line per line of the displayed text
+    last_lines: Vec<ShapedLine>,
     last_bounds: Option<Bounds<Pixels>>,
+    last_line_height: Pixels,
     is_selecting: bool,
+    multi_line: bool,
 }
 
 impl TextInput {
@@ -52,9 +58,20 @@ impl TextInput {
             selected_range: 0..0,
             selection_reversed: false,
             marked_range: None,
-            last_layout: None,
+            last_lines: Vec::new(),
             last_bounds: None,
+            last_line_height: px(0.),
             is_selecting: false,
+            multi_line: false,
+        }
+    }
+
+    /// An input that keeps newlines: enter breaks the line, up and down move
+    /// between lines and pasted text keeps its line breaks.
+    pub fn multi_line(placeholder: &str, cx: &mut Context<Self>) -> Self {
+        Self {
+            multi_line: true,
+            ..Self::new(placeholder, cx)
         }
     }
 
@@ -108,11 +125,25 @@ impl TextInput {
     }
 
     fn home(&mut self, _: &Home, _: &mut Window, cx: &mut Context<Self>) {
-        self.move_to(0, cx);
+        self.move_to(self.cursor_line().start, cx);
     }
 
     fn end(&mut self, _: &End, _: &mut Window, cx: &mut Context<Self>) {
-        self.move_to(self.content.len(), cx);
+        self.move_to(self.cursor_line().end, cx);
+    }
+
+    fn up(&mut self, _: &Up, _: &mut Window, cx: &mut Context<Self>) {
+        self.move_vertically(-1, cx);
+    }
+
+    fn down(&mut self, _: &Down, _: &mut Window, cx: &mut Context<Self>) {
+        self.move_vertically(1, cx);
+    }
+
+    fn newline(&mut self, _: &Newline, window: &mut Window, cx: &mut Context<Self>) {
+        if self.multi_line {
+            self.replace_text_in_range(None, "\n", window, cx)
+        }
     }
 
     fn backspace(&mut self, _: &Backspace, window: &mut Window, cx: &mut Context<Self>) {
@@ -164,7 +195,12 @@ impl TextInput {
 
     fn paste(&mut self, _: &Paste, window: &mut Window, cx: &mut Context<Self>) {
         if let Some(text) = cx.read_from_clipboard().and_then(|item| item.text()) {
-            self.replace_text_in_range(None, &text.replace('\n', " "), window, cx);
+            let text = if self.multi_line {
+                text
+            } else {
+                text.replace('\n', " ")
+            };
+            self.replace_text_in_range(None, &text, window, cx);
         }
     }
 
@@ -202,18 +238,60 @@ impl TextInput {
         }
     }
 
+    /// The line the cursor is on, the whole content for a single line input.
+    fn cursor_line(&self) -> Range<usize> {
+        let lines = line_ranges(&self.content);
+        lines[line_at(&lines, self.cursor_offset())].clone()
+    }
+
+    /// Move the cursor `delta` lines up or down, keeping it as close to its
+    /// x position as the other line allows. Past the first or the last line
+    /// it goes to the start or the end of the text.
+    fn move_vertically(&mut self, delta: isize, cx: &mut Context<Self>) {
+        let lines = line_ranges(&self.content);
+        let cursor = self.cursor_offset();
+        let row = line_at(&lines, cursor);
+        let offset = match row.checked_add_signed(delta) {
+            None => 0,
+            Some(target) if target >= lines.len() => self.content.len(),
+            Some(target) => {
+                let column = cursor - lines[row].start;
+                let column = match (self.last_lines.get(row), self.last_lines.get(target)) {
+                    (Some(from), Some(to)) => to.closest_index_for_x(from.x_for_index(column)),
+                    _ => column,
+                };
+                let line = &lines[target];
+                (line.start..=line.start + column.min(line.len()))
+                    .rev()
+                    .find(|offset| self.content.is_char_boundary(*offset))
+                    .unwrap_or(line.start)
+            }
+        };
+        self.move_to(offset, cx);
+    }
+
+    /// Line of the last layout under `y`, clamped to the first and last.
+    fn row_for_y(&self, bounds: &Bounds<Pixels>, y: Pixels) -> usize {
+        if self.last_line_height <= px(0.) {
+            return 0;
+        }
+        let row = ((y - bounds.top()) / self.last_line_height).floor().max(0.) as usize;
+        row.min(self.last_lines.len().saturating_sub(1))
+    }
+
     fn index_for_mouse(&self, position: Point<Pixels>) -> usize {
         if self.content.is_empty() {
             return 0;
         }
-        let (Some(bounds), Some(line)) = (self.last_bounds.as_ref(), self.last_layout.as_ref())
-        else {
+        let Some(bounds) = self.last_bounds.as_ref() else {
             return 0;
         };
-        if position.y < bounds.top() || position.y > bounds.bottom() {
+        let lines = line_ranges(&self.content);
+        let row = self.row_for_y(bounds, position.y);
+        let (Some(line), Some(range)) = (self.last_lines.get(row), lines.get(row)) else {
             return 0;
-        }
-        line.closest_index_for_x(position.x - bounds.left())
+        };
+        range.start + line.closest_index_for_x(position.x - bounds.left())
     }
 
     fn select_to(&mut self, offset: usize, cx: &mut Context<Self>) {
@@ -279,6 +357,27 @@ impl TextInput {
     }
 }
 
+/// Byte ranges of the lines of `text`, without their newlines. There is
+/// always at least one, empty for empty text.
+fn line_ranges(text: &str) -> Vec<Range<usize>> {
+    let mut start = 0;
+    let mut lines = Vec::new();
+    for (newline, _) in text.match_indices('\n') {
+        lines.push(start..newline);
+        start = newline + 1;
+    }
+    lines.push(start..text.len());
+    lines
+}
+
+/// Index of the line in `lines` that holds `offset`.
+fn line_at(lines: &[Range<usize>], offset: usize) -> usize {
+    lines
+        .iter()
+        .rposition(|line| line.start <= offset)
+        .unwrap_or(0)
+}
+
 #[derive(Clone, Debug)]
 pub struct TextInputSubmitted;
 
@@ -381,16 +480,17 @@ impl EntityInputHandler for TextInput {
         _window: &mut Window,
         _cx: &mut Context<Self>,
     ) -> Option<Bounds<Pixels>> {
-        let last_layout = self.last_layout.as_ref()?;
         let range = self.range_from_utf16(&range_utf16);
+        let lines = line_ranges(&self.content);
+        let row = line_at(&lines, range.start);
+        let line = self.last_lines.get(row)?;
+        let start = lines[row].start;
+        let top = bounds.top() + self.last_line_height * row as f32;
         Some(Bounds::from_corners(
+            point(bounds.left() + line.x_for_index(range.start - start), top),
             point(
-                bounds.left() + last_layout.x_for_index(range.start),
-                bounds.top(),
-            ),
-            point(
-                bounds.left() + last_layout.x_for_index(range.end),
-                bounds.bottom(),
+                bounds.left() + line.x_for_index(range.end.min(lines[row].end) - start),
+                top + self.last_line_height,
             ),
         ))
     }
@@ -401,9 +501,12 @@ impl EntityInputHandler for TextInput {
         _window: &mut Window,
         _cx: &mut Context<Self>,
     ) -> Option<usize> {
-        let line_point = self.last_bounds?.localize(&point)?;
-        let last_layout = self.last_layout.as_ref()?;
-        let utf8_index = last_layout.index_for_x(line_point.x)?;
+        let bounds = self.last_bounds?;
+        let line_point = bounds.localize(&point)?;
+        let row = self.row_for_y(&bounds, point.y);
+        let line = self.last_lines.get(row)?;
+        let start = line_ranges(&self.content).get(row)?.start;
+        let utf8_index = start + line.index_for_x(line_point.x)?;
         Some(self.offset_to_utf16(utf8_index))
     }
 }
@@ -413,9 +516,9 @@ struct TextElement {
 }
 
 struct PrepaintState {
-    line: Option<ShapedLine>,
+    lines: Vec<ShapedLine>,
     cursor: Option<PaintQuad>,
-    selection: Option<PaintQuad>,
+    selections: Vec<PaintQuad>,
 }
 
 impl IntoElement for TextElement {
@@ -426,6 +529,44 @@ impl IntoElement for TextElement {
     }
 }
 
+/// Runs for the `line` range of the displayed text, with the part of it in
+/// `marked` underlined.
+fn line_runs(line: &Range<usize>, marked: Option<&Range<usize>>, run: &TextRun) -> Vec<TextRun> {
+    let marked = marked
+        .map(|marked| {
+            marked.start.clamp(line.start, line.end)..marked.end.clamp(line.start, line.end)
+        })
+        .filter(|marked| !marked.is_empty());
+    let Some(marked) = marked else {
+        return vec![TextRun {
+            len: line.len(),
+            ..run.clone()
+        }];
+    };
+    vec![
+        TextRun {
+            len: marked.start - line.start,
+            ..run.clone()
+        },
+        TextRun {
+            len: marked.end - marked.start,
+            underline: Some(UnderlineStyle {
+                color: Some(run.color),
+                thickness: px(1.0),
+                wavy: false,
+            }),
+            ..run.clone()
+        },
+        TextRun {
+            len: line.end - marked.end,
+            ..run.clone()
+        },
+    ]
+    .into_iter()
+    .filter(|r| r.len > 0)
+    .collect()
+}
+
 impl Element for TextElement {
     type RequestLayoutState = ();
     type PrepaintState = PrepaintState;
@@ -445,9 +586,16 @@ impl Element for TextElement {
         window: &mut Window,
         cx: &mut App,
     ) -> (LayoutId, Self::RequestLayoutState) {
+        let input = self.input.read(cx);
+        let text = if input.content.is_empty() {
+            &input.placeholder
+        } else {
+            &input.content
+        };
+        let rows = line_ranges(text).len();
         let mut style = Style::default();
         style.size.width = relative(1.).into();
-        style.size.height = window.line_height().into();
+        style.size.height = (window.line_height() * rows as f32).into();
         (window.request_layout(style, [], cx), ())
     }
 
@@ -465,6 +613,7 @@ impl Element for TextElement {
         let selected_range = input.selected_range.clone();
         let cursor = input.cursor_offset();
         let style = window.text_style();
+        let line_height = window.line_height();
 
         let (display_text, text_color) = if content.is_empty() {
             (input.placeholder.clone(), hsla(0., 0., 1., 0.3))
@@ -481,73 +630,70 @@ impl Element for TextElement {
             strikethrough: None,
         };
 
-        let runs = if let Some(marked_range) = input.marked_range.as_ref() {
-            vec![
-                TextRun {
-                    len: marked_range.start,
-                    ..run.clone()
-                },
-                TextRun {
-                    len: marked_range.end - marked_range.start,
-                    underline: Some(UnderlineStyle {
-                        color: Some(run.color),
-                        thickness: px(1.0),
-                        wavy: false,
-                    }),
-                    ..run.clone()
-                },
-                TextRun {
-                    len: display_text.len().saturating_sub(marked_range.end),
-                    ..run
-                },
-            ]
-            .into_iter()
-            .filter(|r| r.len > 0)
-            .collect()
-        } else {
-            vec![run]
-        };
-
         let font_size = style.font_size.to_pixels(window.rem_size());
-        let line = window
-            .text_system()
-            .shape_line(display_text, font_size, &runs, None);
-
-        let cursor_pos = line.x_for_index(cursor);
-        let (selection, cursor) = if selected_range.is_empty() {
+        let ranges = line_ranges(&display_text);
+        let lines: Vec<ShapedLine> = ranges
+            .iter()
+            .map(|range| {
+                let runs = line_runs(range, input.marked_range.as_ref(), &run);
+                window.text_system().shape_line(
+                    display_text[range.clone()].to_string().into(),
+                    font_size,
+                    &runs,
+                    None,
+                )
+            })
+            .collect();
+        let top = |row: usize| bounds.top() + line_height * row as f32;
+
+        let (selections, cursor) = if selected_range.is_empty() {
+            let row = line_at(&ranges, cursor);
+            let cursor_pos = lines[row].x_for_index(cursor - ranges[row].start);
             (
-                None,
+                Vec::new(),
                 Some(fill(
                     Bounds::new(
-                        point(bounds.left() + cursor_pos, bounds.top()),
-                        size(px(2.), bounds.bottom() - bounds.top()),
+                        point(bounds.left() + cursor_pos, top(row)),
+                        size(px(2.), line_height),
                     ),
                     gpui::blue(),
                 )),
             )
         } else {
-            (
-                Some(fill(
-                    Bounds::from_corners(
-                        point(
-                            bounds.left() + line.x_for_index(selected_range.start),
-                            bounds.top(),
-                        ),
-                        point(
-                            bounds.left() + line.x_for_index(selected_range.end),
-                            bounds.bottom(),
+            let selections = ranges
+                .iter()
+                .enumerate()
+                .filter(|(_, range)| {
+                    selected_range.start <= range.end && selected_range.end >= range.start
+                })
+                .map(|(row, range)| {
+                    let start = selected_range.start.max(range.start) - range.start;
+                    let end = selected_range.end.min(range.end) - range.start;
+                    // a selection running on past the line takes its newline along
+                    let newline = if selected_range.end > range.end {
+                        px(4.)
+                    } else {
+                        px(0.)
+                    };
+                    fill(
+                        Bounds::from_corners(
+                            point(bounds.left() + lines[row].x_for_index(start), top(row)),
+                            point(
+                                bounds.left() + lines[row].x_for_index(end) + newline,
+                                top(row) + line_height,
+                            ),
                         ),
-                    ),
-                    rgba(0x3311ff30),
-                )),
-                None,
-            )
+                        rgba(0x3311ff30),
+                    )
+                })
+                .collect();
+            (selections, None)
         };
 
         PrepaintState {
-            line: Some(line),
+            lines,
             cursor,
-            selection,
+            selections,
         }
     }
 
@@ -567,27 +713,25 @@ impl Element for TextElement {
             ElementInputHandler::new(bounds, self.input.clone()),
             cx,
         );
-        if let Some(selection) = prepaint.selection.take() {
+        for selection in prepaint.selections.drain(..) {
             window.paint_quad(selection)
         }
-        let line = prepaint.line.take().unwrap();
-        line.paint(
-            bounds.origin,
-            window.line_height(),
-            TextAlign::Left,
-            None,
-            window,
-            cx,
-        )
-        .unwrap();
+        let line_height = window.line_height();
+        let lines = std::mem::take(&mut prepaint.lines);
+        for (row, line) in lines.iter().enumerate() {
+            let origin = bounds.origin + point(px(0.), line_height * row as f32);
+            line.paint(origin, line_height, TextAlign::Left, None, window, cx)
+                .unwrap();
+        }
         if focus_handle.is_focused(window)
             && let Some(cursor) = prepaint.cursor.take()
         {
             window.paint_quad(cursor);
         }
         self.input.update(cx, |input, _cx| {
-            input.last_layout = Some(line);
+            input.last_lines = lines;
             input.last_bounds = Some(bounds);
+            input.last_line_height = line_height;
         });
     }
 }
@@ -596,7 +740,11 @@ impl Render for TextInput {
     fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         div()
             .flex()
-            .key_context("TextInput")
+            .key_context(if self.multi_line {
+                "TextInput multiline"
+            } else {
+                "TextInput"
+            })
             .track_focus(&self.focus_handle(cx))
             .cursor(CursorStyle::IBeam)
             .on_action(cx.listener(Self::backspace))
@@ -608,6 +756,9 @@ impl Render for TextInput {
             .on_action(cx.listener(Self::select_all))
             .on_action(cx.listener(Self::home))
             .on_action(cx.listener(Self::end))
+            .on_action(cx.listener(Self::up))
+            .on_action(cx.listener(Self::down))
+            .on_action(cx.listener(Self::newline))
             .on_action(cx.listener(Self::show_character_palette))
             .on_action(cx.listener(Self::paste))
             .on_action(cx.listener(Self::cut))
@@ -0,0 +1,204 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use fs::{Fs, RealFs};
+use futures::{FutureExt, StreamExt};
+use git2::Repository;
+use gpui::{Context, Task};
+
+/// How long the watcher waits after the first event of a burst before it
+/// reports, so a commit or a branch switch causes one refresh instead of dozens.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+const LATENCY: Duration = Duration::from_millis(100);
+
+/// What a change on disk touched.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// HEAD or any ref moved
+    Refs,
+    /// the staging area was rewritten
+    Index,
+    /// a tracked or untracked file that is not ignored
+    WorkTree,
+}
+
+/// Everything that changed during one debounced burst.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RepoChanges {
+    pub refs: bool,
+    pub index: bool,
+    pub work_tree: bool,
+}
+
+impl RepoChanges {
+    pub fn add(&mut self, kind: ChangeKind) {
+        match kind {
+            ChangeKind::Refs => self.refs = true,
+            ChangeKind::Index => self.index = true,
+            ChangeKind::WorkTree => self.work_tree = true,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !(self.refs || self.index || self.work_tree)
+    }
+}
+
+/// Kind of change for a path relative to the git directory. Lock files and
+/// object writes are skipped; the ref update that follows them is what counts.
+pub fn git_dir_change(relative: &Path) -> Option<ChangeKind> {
+    if relative.extension().is_some_and(|ext| ext == "lock") {
+        return None;
+    }
+    let mut components = relative.components();
+    let first = components.next()?.as_os_str();
+    if first == "HEAD" || first == "packed-refs" {
+        return (components.next().is_none()).then_some(ChangeKind::Refs);
+    }
+    if first == "refs" {
+        return Some(ChangeKind::Refs);
+    }
+    if first == "index" {
+        return (components.next().is_none()).then_some(ChangeKind::Index);
+    }
+    None
+}
+
+/// Watches a repository on disk and reports bursts of relevant changes to the
+/// entity that started it. Dropping the watcher stops it.
+pub struct RepoWatcher {
+    _task: Task<()>,
+}
+
+impl RepoWatcher {
+    pub fn spawn<T: 'static>(
+        repo_path: &str,
+        cx: &mut Context<T>,
+        changed: impl Fn(&mut T, RepoChanges, &mut Context<T>) + 'static,
+    ) -> Self {
+        let fs = Arc::new(RealFs::new(None, cx.background_executor().clone()));
+        let repo_path = PathBuf::from(repo_path);
+        let task = cx.spawn(async move |this, cx| {
+            let path = repo_path.clone();
+            let root = cx
+                .background_executor()
+                .spawn(async move {
+                    let repo = Repository::open(&path).ok()?;
+                    Some(repo.workdir().unwrap_or(repo.path()).to_path_buf())
+                })
+                .await;
+            let Some(root) = root else {
+                return;
+            };
+
+            let (mut events, watcher) = fs.watch(&root, LATENCY).await;
+            // not every platform watches recursively, so every directory
+            // that matters is added on its own
+            let path = repo_path.clone();
+            let dirs = cx
+                .background_executor()
+                .spawn(async move { watched_dirs(&path) })
+                .await;
+            for dir in dirs {
+                watcher.add(&dir).ok();
+            }
+
+            while let Some(batch) = events.next().await {
+                let mut paths: Vec<PathBuf> = batch.into_iter().map(|e| e.path).collect();
+                cx.background_executor().timer(DEBOUNCE).await;
+                while let Some(Some(batch)) = events.next().now_or_never() {
+                    paths.extend(batch.into_iter().map(|e| e.path));
+                }
+
+                let path = repo_path.clone();
+                let (changes, new_dirs) = cx
+                    .background_executor()
+                    .spawn(async move { classify_bg(&path, &paths) })
+                    .await;
+                // new directories are not covered by the watches above
+                for dir in new_dirs {
+                    watcher.add(&dir).ok();
+                }
+                if changes.is_empty() {
+                    continue;
+                }
+                if this
+                    .update(cx, |this, cx| changed(this, changes, cx))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+        RepoWatcher { _task: task }
+    }
+}
+
+/// What the changed `paths` of one burst touched, and the directories among
+/// them that are new and need a watch of their own. This reads the ignore
+/// rules and the disk, so the watcher runs it on the background executor.
+pub fn classify_bg(repo_path: &Path, paths: &[PathBuf]) -> (RepoChanges, Vec<PathBuf>) {
+    let mut changes = RepoChanges::default();
+    let mut new_dirs = Vec::new();
+    let Ok(repo) = Repository::open(repo_path) else {
+        return (changes, new_dirs);
+    };
+    for path in paths {
+        let Some(kind) = classify(&repo, path) else {
+            continue;
+        };
+        changes.add(kind);
+        if path.is_dir() {
+            new_dirs.push(path.clone());
+        }
+    }
+    (changes, new_dirs)
+}
+
+fn classify(repo: &Repository, path: &Path) -> Option<ChangeKind> {
+    if let Ok(relative) = path.strip_prefix(repo.path()) {
+        return git_dir_change(relative);
+    }
+    let relative = path.strip_prefix(repo.workdir()?).ok()?;
+    if relative.as_os_str().is_empty() || repo.is_path_ignored(relative).unwrap_or(false) {
+        return None;
+    }
+    Some(ChangeKind::WorkTree)
+}
+
+/// The git directory, every directory under `refs`, and every directory of
+/// the working tree that `.gitignore` does not exclude.
+fn watched_dirs(repo_path: &Path) -> Vec<PathBuf> {
+    let Ok(repo) = Repository::open(repo_path) else {
+        return Vec::new();
+    };
+    let git_dir = repo.path().to_path_buf();
+    let mut dirs = vec![git_dir.clone()];
+    collect_dirs(&git_dir.join("refs"), &mut dirs, &|_| true);
+
+    if let Some(workdir) = repo.workdir() {
+        dirs.push(workdir.to_path_buf());
+        collect_dirs(workdir, &mut dirs, &|dir| {
+            dir != git_dir.as_path()
+                && dir
+                    .strip_prefix(workdir)
+                    .is_ok_and(|relative| !repo.is_path_ignored(relative).unwrap_or(false))
+        });
+    }
+    dirs
+}
+
+fn collect_dirs(dir: &Path, dirs: &mut Vec<PathBuf>, keep: &dyn Fn(&Path) -> bool) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !entry.file_type().is_ok_and(|t| t.is_dir()) || !keep(&path) {
+            continue;
+        }
+        dirs.push(path.clone());
+        collect_dirs(&path, dirs, keep);
+    }
+}
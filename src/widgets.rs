@@ -0,0 +1,191 @@
+use gpui::prelude::*;
+use gpui::{
+    AnyElement, App, Context, Div, ElementId, Entity, InteractiveElement, MouseButton,
+    MouseDownEvent, ParentElement, SharedString, Stateful, Styled, Window, div, px,
+};
+
+use crate::text_input::TextInput;
+
+pub const COLOR_BG: u32 = 0x1E1E1E;
+pub const COLOR_HEADING_BG: u32 = 0x252525;
+pub const COLOR_BORDER: u32 = 0x333333;
+pub const COLOR_TEXT: u32 = 0xCCCCCC;
+pub const COLOR_DIM_TEXT: u32 = 0x888888;
+pub const COLOR_HOVER_BG: u32 = 0x2A2A2A;
+pub const COLOR_NO_REPO: u32 = 0x666666;
+pub const COLOR_WARNING_TEXT: u32 = 0xF39C12;
+pub const COLOR_ERROR_TEXT: u32 = 0xE74C3C;
+pub const COLOR_BUTTON_BG: u32 = 0x333333;
+pub const COLOR_BUTTON_HOVER_BG: u32 = 0x444444;
+pub const COLOR_BUSY_TEXT: u32 = 0x666666;
+pub const COLOR_INPUT_BG: u32 = 0x2A2A2A;
+
+/// Small flat button used in panel headings, rows and forms. A disabled
+/// button is greyed out and ignores clicks.
+pub fn button(
+    id: impl Into<ElementId>,
+    label: impl Into<SharedString>,
+    enabled: bool,
+    on_click: impl Fn(&MouseDownEvent, &mut Window, &mut App) + 'static,
+) -> Stateful<Div> {
+    div()
+        .id(id)
+        .px(px(6.0))
+        .rounded(px(3.0))
+        .bg(gpui::rgb(COLOR_BUTTON_BG))
+        .text_color(gpui::rgb(if enabled {
+            COLOR_TEXT
+        } else {
+            COLOR_BUSY_TEXT
+        }))
+        .font_weight(gpui::FontWeight::NORMAL)
+        .font_family("monospace")
+        .text_size(px(10.0))
+        .when(enabled, |el| {
+            el.cursor_pointer()
+                .hover(|style| style.bg(gpui::rgb(COLOR_BUTTON_HOVER_BG)))
+                .on_mouse_down(MouseButton::Left, move |event, window, cx| {
+                    on_click(event, window, cx);
+                    cx.stop_propagation();
+                })
+        })
+        .child(label.into())
+}
+
+pub fn error_text(msg: impl Into<SharedString>) -> Div {
+    div()
+        .text_color(gpui::rgb(COLOR_ERROR_TEXT))
+        .text_size(px(10.0))
+        .font_family("monospace")
+        .child(msg.into())
+}
+
+/// An error line across the width of a panel.
+pub fn error_row(msg: impl Into<SharedString>) -> Div {
+    error_text(msg).w_full().px(px(10.0)).py(px(2.0))
+}
+
+/// Frame around a single-line text input, outlined in red when `invalid`.
+pub fn input_box(input: &Entity<TextInput>, invalid: bool) -> Div {
+    div()
+        .h(px(22.0))
+        .px(px(6.0))
+        .bg(gpui::rgb(COLOR_INPUT_BG))
+        .border_1()
+        .border_color(gpui::rgb(if invalid {
+            COLOR_ERROR_TEXT
+        } else {
+            COLOR_BORDER
+        }))
+        .rounded(px(4.0))
+        .overflow_hidden()
+        .text_size(px(11.0))
+        .font_family("monospace")
+        .child(input.clone())
+}
+
+/// A question asked before something that can't be undone, with a button
+/// that goes ahead and one that backs out. The buttons get `id` as prefix.
+pub fn confirm_prompt(
+    id: &str,
+    question: impl Into<SharedString>,
+    confirm: &'static str,
+    enabled: bool,
+    on_confirm: impl Fn(&MouseDownEvent, &mut Window, &mut App) + 'static,
+    on_cancel: impl Fn(&MouseDownEvent, &mut Window, &mut App) + 'static,
+) -> AnyElement {
+    div()
+        .w_full()
+        .px(px(10.0))
+        .py(px(4.0))
+        .flex()
+        .flex_row()
+        .items_center()
+        .justify_between()
+        .gap_2()
+        .border_b_1()
+        .border_color(gpui::rgb(COLOR_BORDER))
+        .child(
+            div()
+                .text_color(gpui::rgb(COLOR_WARNING_TEXT))
+                .text_size(px(10.0))
+                .font_family("monospace")
+                .font_weight(gpui::FontWeight::NORMAL)
+                .child(question.into()),
+        )
+        .child(
+            div()
+                .flex()
+                .flex_row()
+                .gap_1()
+                .child(button(
+                    SharedString::from(format!("{}_confirm", id)),
+                    confirm,
+                    enabled,
+                    on_confirm,
+                ))
+                .child(button(
+                    SharedString::from(format!("{}_cancel", id)),
+                    "Cancel",
+                    true,
+                    on_cancel,
+                )),
+        )
+        .into_any()
+}
+
+/// Name input of the forms that create or rename refs. It tells, while the
+/// name is typed, why the name cannot be used.
+pub struct NameField {
+    input: Entity<TextInput>,
+    kind: &'static str,
+    validate: fn(&str) -> Result<(), String>,
+}
+
+impl NameField {
+    /// `kind` names what is being named in messages, e.g. "branch".
+    pub fn new<V: 'static>(
+        kind: &'static str,
+        validate: fn(&str) -> Result<(), String>,
+        cx: &mut Context<V>,
+    ) -> Self {
+        let input = cx.new(|cx| TextInput::new(&format!("{} name", kind), cx));
+        cx.observe(&input, |_, _, cx| cx.notify()).detach();
+        Self {
+            input,
+            kind,
+            validate,
+        }
+    }
+
+    /// Put `text` in the input and move the focus there.
+    pub fn start(&self, text: &str, window: &mut Window, cx: &mut App) {
+        self.input.update(cx, |input, cx| input.set_text(text, cx));
+        window.focus(&self.input.read(cx).focus_handle);
+    }
+
+    pub fn name(&self, cx: &App) -> String {
+        self.input.read(cx).text().trim().to_string()
+    }
+
+    /// Why the typed name cannot be used. `taken` checks it against the
+    /// names the panel lists; the background job checks again on disk.
+    pub fn error(&self, cx: &App, taken: impl Fn(&str) -> bool) -> Option<String> {
+        let name = self.input.read(cx).text().trim();
+        if let Err(msg) = (self.validate)(name) {
+            return Some(msg);
+        }
+        taken(name).then(|| format!("a {} named '{}' already exists", self.kind, name))
+    }
+
+    /// The input with `error` below it, once something has been typed.
+    pub fn render(&self, error: Option<String>, cx: &App) -> Div {
+        let error = error.filter(|_| !self.input.read(cx).text().trim().is_empty());
+        div()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .child(input_box(&self.input, error.is_some()))
+            .when_some(error, |el, msg| el.child(error_text(msg)))
+    }
+}
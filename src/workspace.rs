@@ -1,45 +1,154 @@
+use std::collections::HashSet;
+use std::rc::Rc;
+
 use gpui::prelude::*;
 use gpui::{
-    AnyElement, Context, Entity, EventEmitter, InteractiveElement, IntoElement, MouseButton,
-    ParentElement, Render, SharedString, Styled, Window, div, px,
+    Action, AnyElement, AnyView, Context, Entity, EventEmitter, InteractiveElement, IntoElement,
+    MouseButton, ParentElement, PromptLevel, Render, SharedString, Styled, Window, div, px,
 };
 
-use crate::actions::Quit;
-use crate::branch::{BranchCheckedOut, BranchPanel};
-use crate::diff_viewer;
-use crate::garph::{self, ChangedFile, CommitSelected, Garph};
+use crate::actions::{ExportAscii, ExportDot, ExportSvg, Quit};
+use crate::branch::{BranchCheckedOut, BranchPanel, BranchesChanged};
+use crate::composer::{CommitComposer, CommitCreated};
+use crate::details::{self, CommitDetails, Identity};
+use crate::diff_viewer::{self, StagingControls, StagingEvent};
+use crate::export::{self, ExportFormat};
+use crate::garph::{
+    self, ChangedFile, CommitSelected, Garph, RangeSelected, RefBadgeClicked, StashSelected,
+    WorkingTreeSelected,
+};
+use crate::job::Job;
 use crate::menu::{DropdownEvent, MenuBar};
 use crate::panel_loader::{self, PanelData};
 use crate::path_bar::{
-    self, PathBar, RepoPathSubmitted, SearchPathCleared, SearchPathSubmitted, ViewModeChanged,
+    self, GraphModeChanged, PathBar, RepoPathSubmitted, SearchPathCleared, SearchQuerySubmitted,
+    ViewModeChanged,
 };
+use crate::query::CommitQuery;
+use crate::refs::{RefKind, RefSelection};
 use crate::repo_picker;
+use crate::staging::{self, HunkAction};
+use crate::stash::{self, StashAction};
 use crate::status_bar::StatusBar;
-use crate::status_panel::StatusPanel;
+use crate::status_panel::{StatusEntryOpened, StatusPanel, StatusUpdated};
+use crate::tags::{TagOpened, TagPanel, TagsChanged};
 use crate::title::{QuitClicked, TitleBar};
-use std::sync::mpsc::{self, Receiver};
+use crate::watcher::{RepoChanges, RepoWatcher};
+use crate::widgets;
 
 pub struct Dock;
 pub struct Pane;
+
+/// What the file panel lists changes of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffSource {
+    /// a commit against its first parent
+    Commit(git2::Oid),
+    /// HEAD against the working tree
+    WorkingTree,
+    /// a stash entry against the commit it was made on
+    Stash { index: usize, oid: git2::Oid },
+    /// the tree of one commit against the tree of another
+    Range { old: git2::Oid, new: git2::Oid },
+    /// HEAD against the index
+    Staged,
+    /// the index against the working tree
+    Unstaged,
+}
+
+impl DiffSource {
+    /// Sources that change with the working tree rather than with history.
+    fn follows_status(&self) -> bool {
+        matches!(
+            self,
+            DiffSource::WorkingTree | DiffSource::Staged | DiffSource::Unstaged
+        )
+    }
+
+    /// What the hunks of this source can be used for.
+    fn hunk_actions(&self) -> Option<&'static [HunkAction]> {
+        match self {
+            DiffSource::Unstaged => Some(&[HunkAction::Stage, HunkAction::Discard]),
+            DiffSource::Staged => Some(&[HunkAction::Unstage]),
+            _ => None,
+        }
+    }
+
+    fn changed_files_bg(self, repo_path: String) -> Vec<ChangedFile> {
+        let result = match self {
+            DiffSource::Commit(oid) | DiffSource::Stash { oid, .. } => {
+                garph::get_changed_files_bg(repo_path, oid)
+            }
+            DiffSource::WorkingTree => garph::get_worktree_changes_bg(repo_path),
+            DiffSource::Staged => garph::get_index_changes_bg(repo_path, true),
+            DiffSource::Unstaged => garph::get_index_changes_bg(repo_path, false),
+            DiffSource::Range { old, new } => garph::get_range_changes_bg(repo_path, old, new),
+        };
+        result.unwrap_or_else(|e| {
+            eprintln!("Failed to get changed files: {}", e);
+            Vec::new()
+        })
+    }
+
+    fn file_diff_bg(self, repo_path: String, path: String) -> String {
+        let result = match self {
+            DiffSource::Commit(oid) | DiffSource::Stash { oid, .. } => {
+                garph::compute_file_diff_bg(repo_path, oid, path)
+            }
+            DiffSource::WorkingTree => garph::compute_worktree_file_diff_bg(repo_path, path),
+            DiffSource::Staged | DiffSource::Unstaged => {
+                garph::compute_index_file_diff_bg(repo_path, self == DiffSource::Staged, path)
+            }
+            DiffSource::Range { old, new } => {
+                garph::compute_range_file_diff_bg(repo_path, old, new, path)
+            }
+        };
+        result.unwrap_or_else(|e| format!("Failed to compute diff: {}", e))
+    }
+}
+
 pub struct Workspace {
     dock: Option<Entity<Garph>>,
     title_bar: Entity<TitleBar>,
     menu_bar: Entity<MenuBar>,
     path_bar: Entity<PathBar>,
     branch_panel: Option<Entity<BranchPanel>>,
+    tag_panel: Option<Entity<TagPanel>>,
     status_panel: Option<Entity<StatusPanel>>,
+    composer: Option<Entity<CommitComposer>>,
     status_bar: Option<Entity<StatusBar>>,
     selected_commit: Option<CommitSelected>,
     changed_files: Vec<ChangedFile>,
     expanded_file: Option<usize>,
     file_diff: Option<String>,
     active_pane: ActivePane,
+    /// panel open above the graph, none when the graph has the whole sidebar
+    sidebar_tab: Option<SidebarTab>,
     loading_diff: bool,
-    current_commit_oid: Option<git2::Oid>,
-    pending_files_rx: Option<Receiver<Vec<ChangedFile>>>,
-    pending_diff_rx: Option<Receiver<String>>,
-    pending_paths_rx: Option<Receiver<Vec<String>>>,
-    pending_panel_rx: Option<Receiver<Result<PanelData, String>>>,
+    diff_source: Option<DiffSource>,
+    details: Option<CommitDetails>,
+    /// why the details of the selected commit could not be read
+    details_error: Option<String>,
+    files_job: Option<Job>,
+    details_job: Option<Job>,
+    diff_job: Option<Job>,
+    paths_job: Option<Job>,
+    panel_job: Option<Job>,
+    stash_job: Option<Job>,
+    patch_job: Option<Job>,
+    /// file to expand once the file list has loaded
+    pending_expand: Option<String>,
+    /// rows of the expanded diff picked for staging
+    diff_selection: HashSet<usize>,
+    selection_anchor: Option<usize>,
+    /// discard waiting for the user to confirm it
+    confirm_discard: Option<StagingEvent>,
+    /// why the last stage, unstage or discard failed
+    patch_error: Option<String>,
+    /// why the last apply, pop or drop of the open stash failed
+    stash_error: Option<String>,
+    confirm_stash_drop: bool,
+    watcher: Option<RepoWatcher>,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -48,6 +157,36 @@ pub enum ActivePane {
     Content,
 }
 
+/// The panels that share the sidebar with the graph, one at a time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SidebarTab {
+    Branches,
+    Tags,
+    Changes,
+    Commit,
+}
+
+impl SidebarTab {
+    const ALL: [SidebarTab; 4] = [
+        SidebarTab::Branches,
+        SidebarTab::Tags,
+        SidebarTab::Changes,
+        SidebarTab::Commit,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            SidebarTab::Branches => "Branches",
+            SidebarTab::Tags => "Tags",
+            SidebarTab::Changes => "Changes",
+            SidebarTab::Commit => "Commit",
+        }
+    }
+}
+
+// height of the open sidebar panel; the graph takes the rest
+const SIDEBAR_PANEL_HEIGHT: f32 = 200.0;
+
 impl Workspace {
     pub fn new(dock: Option<Entity<Garph>>, cx: &mut Context<Self>) -> Self {
         let dock_clone = dock.clone();
@@ -58,14 +197,18 @@ impl Workspace {
 
         let branch_panel = dock.as_ref().map(|garph| {
             let repo = garph.read(cx).repo();
-            cx.new(|_| BranchPanel::new(repo.clone()))
+            cx.new(|cx| BranchPanel::new(repo.clone(), cx))
         });
 
+        let tag_panel = dock.as_ref().map(|_| cx.new(TagPanel::new));
+
         let status_panel = dock.as_ref().map(|garph| {
             let repo = garph.read(cx).repo();
-            cx.new(|_| StatusPanel::new(repo.clone()))
+            cx.new(|cx| StatusPanel::new(repo.clone(), cx))
         });
 
+        let composer = dock.as_ref().map(|_| cx.new(CommitComposer::new));
+
         let status_bar = dock.as_ref().map(|garph| {
             let repo = garph.read(cx).repo();
             cx.new(|_| StatusBar::new(repo))
@@ -73,14 +216,39 @@ impl Workspace {
 
         cx.subscribe(&path_bar, Self::on_repo_path_submitted)
             .detach();
-        cx.subscribe(&path_bar, Self::on_search_path_submitted)
+        cx.subscribe(&path_bar, Self::on_search_query_submitted)
             .detach();
         cx.subscribe(&path_bar, Self::on_search_path_cleared)
             .detach();
         cx.subscribe(&path_bar, Self::on_view_mode_changed).detach();
+        cx.subscribe(&path_bar, Self::on_graph_mode_changed)
+            .detach();
+
+        cx.subscribe(&menu_bar, Self::on_dropdown_changed).detach();
+        cx.subscribe(&title_bar, Self::on_quit_clicked).detach();
+        if let Some(bp) = &branch_panel {
+            cx.subscribe(bp, Self::on_branch_checked_out).detach();
+            cx.subscribe(bp, Self::on_branches_changed).detach();
+        }
+        if let Some(tp) = &tag_panel {
+            cx.subscribe(tp, Self::on_tag_opened).detach();
+            cx.subscribe(tp, Self::on_tags_changed).detach();
+        }
+        if let Some(sp) = &status_panel {
+            cx.subscribe(sp, Self::on_status_updated).detach();
+            cx.subscribe(sp, Self::on_status_entry_opened).detach();
+        }
+        if let Some(composer) = &composer {
+            cx.subscribe(composer, Self::on_commit_created).detach();
+        }
 
         if let Some(ref garph) = dock {
+            cx.subscribe(garph, Self::on_commit_selected).detach();
             cx.subscribe(garph, Self::on_repo_path_changed).detach();
+            cx.subscribe(garph, Self::on_ref_badge_clicked).detach();
+            cx.subscribe(garph, Self::on_working_tree_selected).detach();
+            cx.subscribe(garph, Self::on_stash_selected).detach();
+            cx.subscribe(garph, Self::on_range_selected).detach();
         }
 
         Self {
@@ -89,19 +257,35 @@ impl Workspace {
             menu_bar,
             path_bar,
             branch_panel,
+            tag_panel,
             status_panel,
+            composer,
             status_bar,
             selected_commit: None,
             changed_files: Vec::new(),
             expanded_file: None,
             file_diff: None,
             active_pane: ActivePane::Content,
+            sidebar_tab: Some(SidebarTab::Branches),
             loading_diff: false,
-            current_commit_oid: None,
-            pending_files_rx: None,
-            pending_diff_rx: None,
-            pending_paths_rx: None,
-            pending_panel_rx: None,
+            diff_source: None,
+            details: None,
+            details_error: None,
+            files_job: None,
+            details_job: None,
+            diff_job: None,
+            paths_job: None,
+            panel_job: None,
+            stash_job: None,
+            patch_job: None,
+            pending_expand: None,
+            diff_selection: HashSet::new(),
+            selection_anchor: None,
+            confirm_discard: None,
+            patch_error: None,
+            stash_error: None,
+            confirm_stash_drop: false,
+            watcher: None,
         }
     }
 
@@ -116,38 +300,203 @@ impl Workspace {
         self.set_selected_commit(Some(event_clone.clone()), cx);
 
         // Immediately load changed files when commit is selected
-        self.load_changed_files(&garph, &event_clone, cx);
+        self.load_changed_files(&garph, DiffSource::Commit(event_clone.oid), cx);
+    }
+
+    fn on_working_tree_selected(
+        &mut self,
+        garph: Entity<Garph>,
+        _event: &WorkingTreeSelected,
+        cx: &mut Context<Self>,
+    ) {
+        self.set_selected_commit(None, cx);
+        self.load_changed_files(&garph, DiffSource::WorkingTree, cx);
+    }
+
+    fn on_stash_selected(
+        &mut self,
+        garph: Entity<Garph>,
+        event: &StashSelected,
+        cx: &mut Context<Self>,
+    ) {
+        let source = DiffSource::Stash {
+            index: event.entry.index,
+            oid: event.entry.oid,
+        };
+        self.set_selected_commit(None, cx);
+        self.load_changed_files(&garph, source, cx);
+    }
+
+    fn on_range_selected(
+        &mut self,
+        garph: Entity<Garph>,
+        event: &RangeSelected,
+        cx: &mut Context<Self>,
+    ) {
+        let source = DiffSource::Range {
+            old: event.old,
+            new: event.new,
+        };
+        self.set_selected_commit(None, cx);
+        self.load_changed_files(&garph, source, cx);
+    }
+
+    /// Diff the current range the other way round.
+    fn swap_range(&mut self, cx: &mut Context<Self>) {
+        let (Some(DiffSource::Range { old, new }), Some(dock)) =
+            (self.diff_source, self.dock.clone())
+        else {
+            return;
+        };
+        self.load_changed_files(&dock, DiffSource::Range { old: new, new: old }, cx);
     }
 
     fn load_changed_files(
         &mut self,
         garph: &Entity<Garph>,
-        commit: &CommitSelected,
+        source: DiffSource,
         cx: &mut Context<Self>,
     ) {
         let repo_path = garph.read(cx).repo_path().map(|s| s.to_string());
-        let oid = commit.oid;
 
         self.changed_files.clear();
         self.expanded_file = None;
         self.file_diff = None;
-        self.current_commit_oid = Some(oid);
+        self.diff_source = Some(source);
+        self.pending_expand = None;
+        self.diff_selection.clear();
+        self.selection_anchor = None;
+        self.confirm_discard = None;
+        self.patch_error = None;
+        self.stash_error = None;
+        self.confirm_stash_drop = false;
+        self.details = None;
+        self.details_error = None;
+        self.details_job = None;
         cx.notify();
 
         let Some(repo_path) = repo_path else {
             return;
         };
 
-        let (tx, rx) = mpsc::channel();
-        self.pending_files_rx = Some(rx);
+        if let DiffSource::Commit(oid) = source {
+            let repo_path = repo_path.clone();
+            self.details_job = Some(Job::spawn(
+                cx,
+                move || details::load_bg(repo_path, oid),
+                |this, result, _cx| {
+                    this.details_job = None;
+                    match result {
+                        Ok(details) => this.details = Some(details),
+                        Err(e) => {
+                            this.details_error =
+                                Some(format!("Failed to load commit details: {}", e))
+                        }
+                    }
+                },
+            ));
+        }
 
-        std::thread::spawn(move || {
-            let result = garph::get_changed_files_bg(repo_path, oid).unwrap_or_else(|e| {
-                eprintln!("Failed to get changed files: {}", e);
-                Vec::new()
-            });
-            let _ = tx.send(result);
-        });
+        self.files_job = Some(Job::spawn(
+            cx,
+            move || source.changed_files_bg(repo_path),
+            |this, files, cx| {
+                this.changed_files = files;
+                this.files_job = None;
+                if let Some(path) = this.pending_expand.take()
+                    && let Some(index) = this.changed_files.iter().position(|f| f.path == path)
+                    && let Some(dock) = this.dock.clone()
+                {
+                    this.on_file_toggled(index, dock, cx);
+                }
+            },
+        ));
+    }
+
+    /// Open a commit from the details header or the tag list in the graph,
+    /// which loads history down to it. Until its row is in, or when the
+    /// graph does not show it, only its files and details are opened.
+    fn open_commit(&mut self, oid: git2::Oid, cx: &mut Context<Self>) {
+        let Some(dock) = self.dock.clone() else {
+            return;
+        };
+        if !dock.update(cx, |garph, cx| garph.seek_oid(oid, cx)) {
+            self.set_selected_commit(None, cx);
+            self.load_changed_files(&dock, DiffSource::Commit(oid), cx);
+        }
+    }
+
+    /// Load the files of `source` again after the status changed. The open
+    /// diff stays open, and so do the rows picked in it and a pending discard
+    /// while its text is the same; a file that no longer changes is closed.
+    fn reload_changed_files(
+        &mut self,
+        garph: &Entity<Garph>,
+        source: DiffSource,
+        cx: &mut Context<Self>,
+    ) {
+        // nothing is open yet while the first load is still on its way
+        if let Some(path) = self.pending_expand.clone() {
+            self.load_changed_files_expanding(garph, source, path, cx);
+            return;
+        }
+        let Some(repo_path) = garph.read(cx).repo_path().map(|s| s.to_string()) else {
+            return;
+        };
+        let expanded = self
+            .expanded_file
+            .and_then(|i| self.changed_files.get(i))
+            .map(|f| f.path.clone());
+        self.files_job = Some(Job::spawn(
+            cx,
+            move || {
+                let files = source.changed_files_bg(repo_path.clone());
+                let diff = expanded.and_then(|path| {
+                    let index = files.iter().position(|f| f.path == path)?;
+                    Some((index, source.file_diff_bg(repo_path, path)))
+                });
+                (files, diff)
+            },
+            move |this, (files, diff), _cx| {
+                this.files_job = None;
+                if this.diff_source != Some(source) {
+                    return;
+                }
+                this.changed_files = files;
+                let unchanged = diff
+                    .as_ref()
+                    .is_some_and(|(_, text)| this.file_diff.as_ref() == Some(text));
+                if !unchanged {
+                    this.diff_selection.clear();
+                    this.selection_anchor = None;
+                    this.confirm_discard = None;
+                }
+                match diff {
+                    Some((index, text)) => {
+                        this.expanded_file = Some(index);
+                        this.file_diff = Some(text);
+                        this.loading_diff = false;
+                        this.diff_job = None;
+                    }
+                    None => {
+                        this.expanded_file = None;
+                        this.file_diff = None;
+                    }
+                }
+            },
+        ));
+    }
+
+    /// Load the files of `source` and open the diff of `path` among them.
+    fn load_changed_files_expanding(
+        &mut self,
+        garph: &Entity<Garph>,
+        source: DiffSource,
+        path: String,
+        cx: &mut Context<Self>,
+    ) {
+        self.load_changed_files(garph, source, cx);
+        self.pending_expand = Some(path);
     }
 
     fn on_file_toggled(&mut self, file_index: usize, garph: Entity<Garph>, cx: &mut Context<Self>) {
@@ -159,19 +508,23 @@ impl Workspace {
             self.expanded_file = None;
             self.file_diff = None;
             self.loading_diff = false;
-            self.pending_diff_rx = None;
+            self.diff_job = None;
             cx.notify();
             return;
         }
 
         self.expanded_file = Some(file_index);
         self.file_diff = None;
+        self.diff_selection.clear();
+        self.selection_anchor = None;
+        self.confirm_discard = None;
+        self.patch_error = None;
         self.loading_diff = true;
         cx.notify();
 
         let file = self.changed_files[file_index].clone();
-        let commit_oid = match self.current_commit_oid {
-            Some(oid) => oid,
+        let source = match self.diff_source {
+            Some(source) => source,
             None => {
                 self.file_diff = Some("No commit selected".to_string());
                 self.loading_diff = false;
@@ -190,14 +543,187 @@ impl Workspace {
             }
         };
 
-        let (tx, rx) = mpsc::channel();
-        self.pending_diff_rx = Some(rx);
+        self.diff_job = Some(Job::spawn(
+            cx,
+            move || source.file_diff_bg(repo_path, file.path),
+            |this, diff, _cx| {
+                this.file_diff = Some(diff);
+                this.loading_diff = false;
+                this.diff_job = None;
+            },
+        ));
+    }
 
-        std::thread::spawn(move || {
-            let result = garph::compute_file_diff_bg(repo_path, commit_oid, file.path.clone())
-                .unwrap_or_else(|e| format!("Failed to compute diff: {}", e));
-            let _ = tx.send(result);
-        });
+    fn on_staging_event(&mut self, event: &StagingEvent, cx: &mut Context<Self>) {
+        // discarding drops work that exists nowhere else, so ask first
+        if let StagingEvent::Hunk {
+            action: HunkAction::Discard,
+            ..
+        }
+        | StagingEvent::Selection(HunkAction::Discard) = event
+        {
+            self.confirm_discard = Some(*event);
+            cx.notify();
+            return;
+        }
+        self.run_staging_event(event, cx);
+    }
+
+    fn run_staging_event(&mut self, event: &StagingEvent, cx: &mut Context<Self>) {
+        self.confirm_discard = None;
+        let (Some(raw), Some(file)) = (
+            self.file_diff.as_ref(),
+            self.expanded_file.and_then(|i| self.changed_files.get(i)),
+        ) else {
+            return;
+        };
+        let rows = diff_viewer::parse_diff(raw);
+        let change = diff_viewer::parse_file_change(raw);
+        let path = file.path.clone();
+        match *event {
+            StagingEvent::Select { row, extend } => {
+                match self.selection_anchor.filter(|_| extend) {
+                    Some(anchor) => {
+                        let (low, high) = (anchor.min(row), anchor.max(row));
+                        self.diff_selection
+                            .extend((low..=high).filter(|r| rows[*r].is_change()));
+                    }
+                    None => {
+                        if !self.diff_selection.remove(&row) {
+                            self.diff_selection.insert(row);
+                        }
+                    }
+                }
+                self.selection_anchor = Some(row);
+            }
+            StagingEvent::Hunk { row, action } => {
+                let hunk = diff_viewer::hunk_rows(&rows, row);
+                let patch = diff_viewer::build_patch(
+                    &path,
+                    &change,
+                    &rows,
+                    |r| hunk.contains(&r),
+                    action.reverse(),
+                );
+                self.apply_patch(action, patch, cx);
+            }
+            StagingEvent::Selection(action) => {
+                let selected = &self.diff_selection;
+                let patch = diff_viewer::build_patch(
+                    &path,
+                    &change,
+                    &rows,
+                    |r| selected.contains(&r),
+                    action.reverse(),
+                );
+                self.apply_patch(action, patch, cx);
+            }
+            StagingEvent::ClearSelection => {
+                self.diff_selection.clear();
+                self.selection_anchor = None;
+            }
+        }
+        cx.notify();
+    }
+
+    fn apply_patch(&mut self, action: HunkAction, patch: Option<String>, cx: &mut Context<Self>) {
+        let Some(patch) = patch else {
+            return;
+        };
+        if self.patch_job.is_some() {
+            return;
+        }
+        let Some(repo_path) = self
+            .dock
+            .as_ref()
+            .and_then(|dock| dock.read(cx).repo_path().map(|s| s.to_string()))
+        else {
+            return;
+        };
+        self.patch_job = Some(Job::spawn(
+            cx,
+            move || {
+                staging::apply_patch_bg(&repo_path, action, &patch)
+                    .map_err(|e| format!("{} failed: {}", action.label(), e))
+            },
+            Self::on_patch_applied,
+        ));
+    }
+
+    fn on_patch_applied(&mut self, result: Result<(), String>, cx: &mut Context<Self>) {
+        self.patch_job = None;
+        match result {
+            Ok(()) => {
+                self.diff_selection.clear();
+                self.selection_anchor = None;
+                self.patch_error = None;
+                // reloading the status reloads the open diff as well
+                self.spawn_panel_reload(cx);
+            }
+            Err(msg) => {
+                self.patch_error = Some(msg);
+                cx.notify();
+            }
+        }
+    }
+
+    fn run_stash_action(&mut self, action: StashAction, cx: &mut Context<Self>) {
+        let Some(DiffSource::Stash { oid, .. }) = self.diff_source else {
+            return;
+        };
+        if self.stash_job.is_some() {
+            return;
+        }
+        let repo_path = self
+            .dock
+            .as_ref()
+            .and_then(|dock| dock.read(cx).repo_path().map(|s| s.to_string()));
+        let Some(repo_path) = repo_path else {
+            return;
+        };
+
+        self.stash_error = None;
+        self.confirm_stash_drop = false;
+        self.stash_job = Some(Job::spawn(
+            cx,
+            move || {
+                stash::run_bg(&repo_path, action, oid)
+                    .map(|_| action)
+                    .map_err(|e| format!("{} failed: {}", action.label(), e))
+            },
+            Self::on_stash_action_done,
+        ));
+        cx.notify();
+    }
+
+    fn on_stash_action_done(
+        &mut self,
+        result: Result<StashAction, String>,
+        cx: &mut Context<Self>,
+    ) {
+        self.stash_job = None;
+        let action = match result {
+            Ok(action) => action,
+            Err(msg) => {
+                self.stash_error = Some(msg);
+                cx.notify();
+                return;
+            }
+        };
+        // the entry is gone after pop and drop
+        if action != StashAction::Apply {
+            self.diff_source = None;
+            self.changed_files.clear();
+            self.expanded_file = None;
+            self.file_diff = None;
+        }
+        if let Some(dock) = &self.dock {
+            dock.update(cx, |garph, cx| {
+                garph.mark_dirty(cx);
+                cx.notify();
+            });
+        }
+        self.spawn_panel_reload(cx);
     }
 
     fn on_branch_checked_out(
@@ -208,13 +734,119 @@ impl Workspace {
     ) {
         if let Some(dock) = &self.dock {
             dock.update(cx, |garph, cx| {
-                garph.mark_dirty();
+                garph.mark_dirty(cx);
                 cx.notify();
             });
         }
         self.spawn_panel_reload(cx);
     }
 
+    /// Branches were created, renamed or deleted: their badges in the graph
+    /// and the branch list both change.
+    fn on_branches_changed(
+        &mut self,
+        _branch_panel: Entity<BranchPanel>,
+        _event: &BranchesChanged,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(dock) = &self.dock {
+            dock.update(cx, |garph, cx| {
+                garph.mark_dirty(cx);
+            });
+        }
+        self.spawn_panel_reload(cx);
+    }
+
+    /// Show the commit of a tag, in the graph when it is loaded there.
+    fn on_tag_opened(
+        &mut self,
+        _tag_panel: Entity<TagPanel>,
+        event: &TagOpened,
+        cx: &mut Context<Self>,
+    ) {
+        self.open_commit(event.oid, cx);
+    }
+
+    /// A tag was created or deleted: its badge in the graph and the tag
+    /// list both change.
+    fn on_tags_changed(
+        &mut self,
+        _tag_panel: Entity<TagPanel>,
+        _event: &TagsChanged,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(dock) = &self.dock {
+            dock.update(cx, |garph, cx| {
+                garph.mark_dirty(cx);
+            });
+        }
+        self.spawn_panel_reload(cx);
+    }
+
+    /// Files were staged or unstaged; the panels, the dirty count and the
+    /// uncommitted row of the graph all follow the new status.
+    fn on_status_updated(
+        &mut self,
+        _panel: Entity<StatusPanel>,
+        _event: &StatusUpdated,
+        cx: &mut Context<Self>,
+    ) {
+        self.spawn_panel_reload(cx);
+    }
+
+    /// HEAD moved to a new commit: refresh the graph and the panels, and
+    /// open the commit once the graph has it.
+    fn on_commit_created(
+        &mut self,
+        _composer: Entity<CommitComposer>,
+        event: &CommitCreated,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(dock) = &self.dock {
+            dock.update(cx, |garph, cx| {
+                garph.mark_dirty_and_open(event.oid, cx);
+            });
+        }
+        self.spawn_panel_reload(cx);
+    }
+
+    /// Show the staged or unstaged diff of a status panel entry.
+    fn on_status_entry_opened(
+        &mut self,
+        _panel: Entity<StatusPanel>,
+        event: &StatusEntryOpened,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(dock) = self.dock.clone() else {
+            return;
+        };
+        let source = if event.staged {
+            DiffSource::Staged
+        } else {
+            DiffSource::Unstaged
+        };
+        self.set_selected_commit(None, cx);
+        self.load_changed_files_expanding(&dock, source, event.path.clone(), cx);
+    }
+
+    fn on_ref_badge_clicked(
+        &mut self,
+        _garph: Entity<Garph>,
+        event: &RefBadgeClicked,
+        cx: &mut Context<Self>,
+    ) {
+        let is_remote = match event.kind {
+            RefKind::Local => false,
+            RefKind::Remote => true,
+            RefKind::Head | RefKind::Tag | RefKind::Stash => return,
+        };
+        if let Some(bp) = &self.branch_panel {
+            bp.update(cx, |panel, cx| {
+                panel.checkout_branch(&event.name, is_remote, cx)
+            });
+        }
+    }
+
     fn on_repo_path_submitted(
         &mut self,
         _path_bar: Entity<PathBar>,
@@ -236,15 +868,15 @@ impl Workspace {
         cx.notify();
     }
 
-    fn on_search_path_submitted(
+    fn on_search_query_submitted(
         &mut self,
         _path_bar: Entity<PathBar>,
-        event: &SearchPathSubmitted,
+        event: &SearchQuerySubmitted,
         cx: &mut Context<Self>,
     ) {
         if let Some(dock) = &self.dock {
             dock.update(cx, |garph, cx| {
-                garph.set_search_path(Some(event.path.clone()));
+                garph.set_query(event.query.clone(), event.mode, cx);
                 cx.notify();
             });
         }
@@ -259,7 +891,22 @@ impl Workspace {
     ) {
         if let Some(dock) = &self.dock {
             dock.update(cx, |garph, cx| {
-                garph.set_search_path(None);
+                garph.set_query(CommitQuery::default(), garph.query_mode(), cx);
+                cx.notify();
+            });
+        }
+        cx.notify();
+    }
+
+    fn on_graph_mode_changed(
+        &mut self,
+        _path_bar: Entity<PathBar>,
+        event: &GraphModeChanged,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(dock) = &self.dock {
+            dock.update(cx, |garph, cx| {
+                garph.set_graph_mode(event.mode, cx);
                 cx.notify();
             });
         }
@@ -277,6 +924,12 @@ impl Workspace {
                 bp.set_mode(event.mode.clone(), cx);
             });
         }
+        if let Some(dock) = &self.dock {
+            dock.update(cx, |garph, cx| {
+                garph.set_ref_selection(RefSelection::from(&event.mode), cx);
+                cx.notify();
+            });
+        }
         self.spawn_panel_reload(cx);
     }
 
@@ -291,19 +944,34 @@ impl Workspace {
             pb.clear_search(cx);
         });
         if let Some(path) = garph.read(cx).repo_path().map(|s| s.to_string()) {
-            self.spawn_path_collection(&path);
+            self.spawn_path_collection(&path, cx);
+            self.watcher = Some(RepoWatcher::spawn(&path, cx, Self::on_repo_changed));
         }
         cx.notify();
     }
 
-    fn spawn_path_collection(&mut self, repo_path: &str) {
-        let (tx, rx) = mpsc::channel();
-        self.pending_paths_rx = Some(rx);
+    /// Something committed, checked out or edited outside gpig.
+    fn on_repo_changed(&mut self, changes: RepoChanges, cx: &mut Context<Self>) {
+        if let Some(dock) = self.dock.as_ref().filter(|_| changes.refs) {
+            dock.update(cx, |garph, cx| {
+                garph.mark_dirty(cx);
+            });
+        }
+        self.spawn_panel_reload(cx);
+    }
+
+    fn spawn_path_collection(&mut self, repo_path: &str, cx: &mut Context<Self>) {
         let repo_path = repo_path.to_string();
-        std::thread::spawn(move || {
-            let result = garph::collect_paths_bg(repo_path).unwrap_or_default();
-            let _ = tx.send(result);
-        });
+        self.paths_job = Some(Job::spawn(
+            cx,
+            move || garph::collect_paths_bg(repo_path).unwrap_or_default(),
+            |this, paths, cx| {
+                this.path_bar.update(cx, |pb, _| {
+                    pb.set_suggest_paths(paths);
+                });
+                this.paths_job = None;
+            },
+        ));
     }
 
     fn spawn_panel_reload(&mut self, cx: &mut Context<Self>) {
@@ -328,6 +996,11 @@ impl Workspace {
                 sp.set_loading(cx);
             });
         }
+        if let Some(composer) = &self.composer {
+            composer.update(cx, |composer, cx| {
+                composer.set_repo_path(repo_path.clone(), cx);
+            });
+        }
         if let Some(sb) = &self.status_bar {
             sb.update(cx, |sb, _| {
                 sb.set_repo_path(repo_path.clone());
@@ -340,63 +1013,86 @@ impl Workspace {
                 bp.set_loading(cx);
             });
         }
+        if let Some(tp) = &self.tag_panel {
+            tp.update(cx, |tp, cx| {
+                tp.set_repo_path(repo_path.clone());
+                tp.set_loading(cx);
+            });
+        }
 
-        let (tx, rx) = mpsc::channel();
-        self.pending_panel_rx = Some(rx);
-
-        std::thread::spawn(move || {
-            let result = panel_loader::load_panel_data_bg(&repo_path, &mode);
-            let _ = tx.send(result);
-        });
+        self.panel_job = Some(Job::spawn(
+            cx,
+            move || panel_loader::load_panel_data_bg(&repo_path, &mode),
+            Self::on_panel_loaded,
+        ));
     }
 
-    fn poll_panel_reload(&mut self, cx: &mut Context<Self>) {
-        let Some(rx) = &self.pending_panel_rx else {
-            return;
-        };
-        match rx.try_recv() {
-            Ok(Ok(data)) => {
-                self.pending_panel_rx = None;
-                if let Some(bp) = &self.branch_panel {
-                    bp.update(cx, |bp, cx| {
-                        bp.apply_data(&data.branches, cx);
-                    });
-                }
-                if let Some(sp) = &self.status_panel {
-                    sp.update(cx, |sp, cx| {
-                        sp.apply_data(&data.status, cx);
-                    });
-                }
-                if let Some(sb) = &self.status_bar {
-                    let node_count = self
-                        .dock
-                        .as_ref()
-                        .map(|d| d.read(cx).node_count())
-                        .unwrap_or(0);
-                    sb.update(cx, |sb, _| {
-                        sb.apply_data(data.branch_name, data.dirty_count);
-                        sb.set_node_count(node_count);
-                    });
-                }
-                cx.notify();
-            }
-            Ok(Err(msg)) => {
-                self.pending_panel_rx = None;
+    fn on_panel_loaded(&mut self, result: Result<PanelData, String>, cx: &mut Context<Self>) {
+        self.panel_job = None;
+        let data = match result {
+            Ok(data) => data,
+            Err(msg) => {
                 eprintln!("panel reload failed: {}", msg);
-                cx.notify();
-            }
-            Err(std::sync::mpsc::TryRecvError::Empty) => {
-                cx.notify();
+                return;
             }
-            Err(std::sync::mpsc::TryRecvError::Disconnected) => {
-                self.pending_panel_rx = None;
-                cx.notify();
+        };
+        if let Some(bp) = &self.branch_panel {
+            bp.update(cx, |bp, cx| {
+                bp.apply_data(&data.branches, cx);
+            });
+        }
+        if let Some(tp) = &self.tag_panel {
+            tp.update(cx, |tp, cx| {
+                tp.apply_data(&data.tags, cx);
+            });
+        }
+        if let Some(sp) = &self.status_panel {
+            sp.update(cx, |sp, cx| {
+                sp.apply_data(&data.status, cx);
+            });
+        }
+        if let Some(dock) = self.dock.clone() {
+            dock.update(cx, |garph, cx| {
+                garph.set_uncommitted_count(data.dirty_count, cx);
+            });
+            // a working tree list is stale once the status changed
+            if let Some(source) = self.diff_source.filter(|s| s.follows_status()) {
+                self.reload_changed_files(&dock, source, cx);
             }
         }
+        if let Some(sb) = &self.status_bar {
+            let node_count = self
+                .dock
+                .as_ref()
+                .map(|d| d.read(cx).node_count())
+                .unwrap_or(0);
+            sb.update(cx, |sb, _| {
+                sb.apply_data(data.branch_name, data.dirty_count);
+                sb.set_node_count(node_count);
+            });
+        }
     }
 
     fn render_file_panel(&self, dock: &Entity<Garph>, cx: &mut Context<Self>) -> AnyElement {
-        if self.changed_files.is_empty() {
+        let stash_index = match self.diff_source {
+            Some(DiffSource::Stash { index, .. }) => Some(index),
+            _ => None,
+        };
+        let is_range = matches!(self.diff_source, Some(DiffSource::Range { .. }));
+        // a stash or range keeps its header so its actions stay reachable,
+        // and a commit its details
+        if self.changed_files.is_empty()
+            && stash_index.is_none()
+            && !is_range
+            && self.details.is_none()
+            && self.details_error.is_none()
+        {
+            let empty = match self.diff_source {
+                Some(DiffSource::WorkingTree) => "Working tree is clean",
+                Some(DiffSource::Staged) => "Nothing staged",
+                Some(DiffSource::Unstaged) => "Nothing left to stage",
+                _ => "No files changed in this commit",
+            };
             return div()
                 .flex()
                 .items_center()
@@ -404,9 +1100,19 @@ impl Workspace {
                 .size_full()
                 .bg(gpui::rgb(0x1E1E1E))
                 .text_color(gpui::rgb(0x888888))
-                .child("No files changed in this commit")
+                .child(empty)
                 .into_any();
         }
+        let heading = match self.diff_source {
+            Some(DiffSource::Stash { index, .. }) => format!("stash@{{{}}}", index),
+            Some(DiffSource::WorkingTree) => "Uncommitted Changes".to_string(),
+            Some(DiffSource::Staged) => "Staged Changes".to_string(),
+            Some(DiffSource::Unstaged) => "Unstaged Changes".to_string(),
+            Some(DiffSource::Range { old, new }) => {
+                format!("{:.7}..{:.7}", old.to_string(), new.to_string())
+            }
+            _ => "Changed Files".to_string(),
+        };
 
         let dock_for_file = dock.clone();
         div()
@@ -414,6 +1120,12 @@ impl Workspace {
             .flex()
             .flex_col()
             .bg(gpui::rgb(0x1E1E1E))
+            .when_some(self.details.as_ref(), |el, details| {
+                el.child(Self::render_details(details, cx))
+            })
+            .when_some(self.details_error.clone(), |el, msg| {
+                el.child(widgets::error_row(msg))
+            })
             .child(
                 div()
                     .w_full()
@@ -425,7 +1137,23 @@ impl Workspace {
                     .text_color(gpui::white())
                     .font_weight(gpui::FontWeight::BOLD)
                     .text_size(px(14.0))
-                    .child(format!("Changed Files ({})", self.changed_files.len())),
+                    .flex()
+                    .flex_row()
+                    .items_center()
+                    .justify_between()
+                    .child(format!("{} ({})", heading, self.changed_files.len()))
+                    .when(stash_index.is_some(), |el| {
+                        el.child(self.render_stash_actions(cx))
+                    })
+                    .when(is_range, |el| el.child(Self::render_swap_button(cx))),
+            )
+            .when_some(
+                self.stash_error.clone().filter(|_| stash_index.is_some()),
+                |el, msg| el.child(widgets::error_row(msg)),
+            )
+            .when_some(
+                stash_index.filter(|_| self.confirm_stash_drop),
+                |el, index| el.child(self.render_confirm_stash_drop(index, cx)),
             )
             .child(
                 div()
@@ -442,6 +1170,185 @@ impl Workspace {
             .into_any()
     }
 
+    /// Full message, identities, parents and trailers of the selected commit.
+    fn render_details(details: &CommitDetails, cx: &mut Context<Self>) -> AnyElement {
+        let label = |text: &'static str| {
+            div()
+                .w(px(80.0))
+                .flex_none()
+                .text_color(gpui::rgb(0x888888))
+                .child(text)
+        };
+        let identity = |title: &'static str, identity: &Identity| {
+            div().flex().flex_row().child(label(title)).child(format!(
+                "{} <{}>  {}",
+                identity.name,
+                identity.email,
+                identity.when()
+            ))
+        };
+
+        div()
+            .id("commit-details")
+            .w_full()
+            .max_h(px(260.0))
+            .overflow_y_scroll()
+            .flex_none()
+            .flex()
+            .flex_col()
+            .gap_1()
+            .px(px(12.0))
+            .py(px(8.0))
+            .border_b_1()
+            .border_color(gpui::rgb(0x333333))
+            .bg(gpui::rgb(0x252525))
+            .text_color(gpui::rgb(0xCCCCCC))
+            .text_size(px(12.0))
+            .child(
+                div()
+                    .text_color(gpui::white())
+                    .font_weight(gpui::FontWeight::BOLD)
+                    .text_size(px(14.0))
+                    .child(details.summary.clone()),
+            )
+            .when(!details.body.is_empty(), |el| {
+                el.child(
+                    div().py(px(4.0)).flex().flex_col().children(
+                        details
+                            .body
+                            .lines()
+                            .map(|line| div().min_h(px(14.0)).child(line.to_string())),
+                    ),
+                )
+            })
+            .child(identity("Author", &details.author))
+            .child(identity("Committer", &details.committer))
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .gap_2()
+                    .child(label("Parents"))
+                    .when(details.parents.is_empty(), |el| el.child("none"))
+                    .children(details.parents.iter().map(|parent| {
+                        let parent = *parent;
+                        div()
+                            .id(SharedString::from(format!("parent-{}", parent)))
+                            .text_color(gpui::rgb(0x4A90D9))
+                            .cursor_pointer()
+                            .hover(|style| style.underline())
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(move |this, _event, _window, cx| {
+                                    this.open_commit(parent, cx);
+                                    cx.stop_propagation();
+                                }),
+                            )
+                            .child(export::short_oid(&parent))
+                    })),
+            )
+            .when(!details.trailers.is_empty(), |el| {
+                el.child(
+                    div()
+                        .pt(px(4.0))
+                        .flex()
+                        .flex_col()
+                        .children(details.trailers.iter().map(|trailer| {
+                            div()
+                                .flex()
+                                .flex_row()
+                                .gap_2()
+                                .child(
+                                    div()
+                                        .text_color(gpui::rgb(0x888888))
+                                        .child(format!("{}:", trailer.key)),
+                                )
+                                .child(trailer.value.clone())
+                        })),
+                )
+            })
+            .into_any()
+    }
+
+    fn render_stash_actions(&self, cx: &mut Context<Self>) -> AnyElement {
+        let busy = self.stash_job.is_some();
+        div()
+            .flex()
+            .flex_row()
+            .gap_2()
+            .children(
+                [StashAction::Apply, StashAction::Pop, StashAction::Drop]
+                    .into_iter()
+                    .map(|action| {
+                        div()
+                            .id(SharedString::from(format!("stash-{}", action.label())))
+                            .px(px(8.0))
+                            .py(px(2.0))
+                            .rounded(px(3.0))
+                            .bg(gpui::rgb(0x333333))
+                            .text_color(gpui::rgb(if busy { 0x666666 } else { 0xCCCCCC }))
+                            .font_weight(gpui::FontWeight::NORMAL)
+                            .text_size(px(12.0))
+                            .when(!busy, |el| {
+                                el.cursor_pointer()
+                                    .hover(|style| style.bg(gpui::rgb(0x444444)))
+                            })
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(move |this, _event, _window, cx| {
+                                    // a dropped entry is gone for good
+                                    if action == StashAction::Drop {
+                                        this.confirm_stash_drop = true;
+                                        cx.notify();
+                                    } else {
+                                        this.run_stash_action(action, cx);
+                                    }
+                                }),
+                            )
+                            .child(action.label())
+                    }),
+            )
+            .into_any()
+    }
+
+    fn render_confirm_stash_drop(&self, index: usize, cx: &mut Context<Self>) -> AnyElement {
+        widgets::confirm_prompt(
+            "stash_drop",
+            format!("Drop stash@{{{}}}? It cannot be restored.", index),
+            "Drop",
+            self.stash_job.is_none(),
+            cx.listener(|this, _event, _window, cx| {
+                this.run_stash_action(StashAction::Drop, cx);
+            }),
+            cx.listener(|this, _event, _window, cx| {
+                this.confirm_stash_drop = false;
+                cx.notify();
+            }),
+        )
+    }
+
+    fn render_swap_button(cx: &mut Context<Self>) -> AnyElement {
+        div()
+            .id("swap-range")
+            .px(px(8.0))
+            .py(px(2.0))
+            .rounded(px(3.0))
+            .bg(gpui::rgb(0x333333))
+            .text_color(gpui::rgb(0xCCCCCC))
+            .font_weight(gpui::FontWeight::NORMAL)
+            .text_size(px(12.0))
+            .cursor_pointer()
+            .hover(|style| style.bg(gpui::rgb(0x444444)))
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|this, _event, _window, cx| {
+                    this.swap_range(cx);
+                }),
+            )
+            .child("Swap")
+            .into_any()
+    }
+
     fn render_file_row(
         &self,
         index: usize,
@@ -528,13 +1435,13 @@ impl Workspace {
             );
 
         if is_expanded {
-            row = row.child(self.render_inline_diff());
+            row = row.child(self.render_inline_diff(cx));
         }
 
         row.into_any()
     }
 
-    fn render_inline_diff(&self) -> AnyElement {
+    fn render_inline_diff(&self, cx: &mut Context<Self>) -> AnyElement {
         if self.loading_diff {
             return div()
                 .w_full()
@@ -577,40 +1484,110 @@ impl Workspace {
         }
 
         let rows = diff_viewer::parse_diff(&raw);
-        diff_viewer::render_side_by_side(&rows)
-    }
-
-    fn poll_pending_results(&mut self, cx: &mut Context<Self>) {
-        if let Some(rx) = &self.pending_files_rx {
-            if let Ok(files) = rx.try_recv() {
-                self.changed_files = files;
-                self.pending_files_rx = None;
-                cx.notify();
-            } else {
-                cx.notify();
-            }
-        }
-        if let Some(rx) = &self.pending_diff_rx {
-            if let Ok(diff) = rx.try_recv() {
-                self.file_diff = Some(diff);
-                self.loading_diff = false;
-                self.pending_diff_rx = None;
-                cx.notify();
-            } else {
-                cx.notify();
+        match self.diff_source.and_then(|s| s.hunk_actions()) {
+            Some(actions) => {
+                let controls = StagingControls {
+                    actions,
+                    selected: &self.diff_selection,
+                    busy: self.patch_job.is_some(),
+                    on_event: Rc::new(cx.listener(|this, event, _window, cx| {
+                        this.on_staging_event(event, cx);
+                    })),
+                };
+                div()
+                    .w_full()
+                    .flex()
+                    .flex_col()
+                    .when_some(self.patch_error.clone(), |el, msg| {
+                        el.child(widgets::error_row(msg))
+                    })
+                    .when_some(self.confirm_discard, |el, event| {
+                        el.child(self.render_confirm_discard(event, cx))
+                    })
+                    .child(diff_viewer::render_staging(&rows, &controls))
+                    .into_any()
             }
+            None => diff_viewer::render_side_by_side(&rows),
         }
-        if let Some(rx) = &self.pending_paths_rx {
-            if let Ok(paths) = rx.try_recv() {
-                self.path_bar.update(cx, |pb, _| {
-                    pb.set_suggest_paths(paths);
-                });
-                self.pending_paths_rx = None;
-                cx.notify();
-            } else {
+    }
+
+    fn render_confirm_discard(&self, event: StagingEvent, cx: &mut Context<Self>) -> AnyElement {
+        let what = match event {
+            StagingEvent::Selection(_) => "the selected lines",
+            _ => "this hunk",
+        };
+        widgets::confirm_prompt(
+            "discard",
+            format!("Discard {}? The change is lost.", what),
+            "Discard",
+            self.patch_job.is_none(),
+            cx.listener(move |this, _event, _window, cx| this.run_staging_event(&event, cx)),
+            cx.listener(|this, _event, _window, cx| {
+                this.confirm_discard = None;
                 cx.notify();
-            }
-        }
+            }),
+        )
+    }
+
+    /// Tab strip over the sidebar panel it picks. Clicking the open tab
+    /// folds the panel away and leaves the sidebar to the graph.
+    fn render_sidebar_tools(&self, cx: &mut Context<Self>) -> AnyElement {
+        let panel: Option<AnyView> = match self.sidebar_tab {
+            Some(SidebarTab::Branches) => self.branch_panel.clone().map(AnyView::from),
+            Some(SidebarTab::Tags) => self.tag_panel.clone().map(AnyView::from),
+            Some(SidebarTab::Changes) => self.status_panel.clone().map(AnyView::from),
+            Some(SidebarTab::Commit) => self.composer.clone().map(AnyView::from),
+            None => None,
+        };
+        div()
+            .w_full()
+            .flex()
+            .flex_col()
+            .child(
+                div()
+                    .w_full()
+                    .flex()
+                    .flex_row()
+                    .border_b_1()
+                    .border_color(gpui::rgb(widgets::COLOR_BORDER))
+                    .bg(gpui::rgb(widgets::COLOR_BG))
+                    .text_size(px(11.0))
+                    .children(SidebarTab::ALL.into_iter().map(|tab| {
+                        let open = self.sidebar_tab == Some(tab);
+                        div()
+                            .id(SharedString::from(format!("sidebar-tab-{}", tab.label())))
+                            .px(px(10.0))
+                            .py(px(4.0))
+                            .cursor_pointer()
+                            .text_color(gpui::rgb(if open {
+                                widgets::COLOR_TEXT
+                            } else {
+                                widgets::COLOR_DIM_TEXT
+                            }))
+                            .when(open, |el| el.bg(gpui::rgb(widgets::COLOR_HEADING_BG)))
+                            .hover(|style| style.bg(gpui::rgb(widgets::COLOR_HOVER_BG)))
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(move |this, _event, _window, cx| {
+                                    this.sidebar_tab =
+                                        (this.sidebar_tab != Some(tab)).then_some(tab);
+                                    cx.notify();
+                                }),
+                            )
+                            .child(tab.label())
+                    })),
+            )
+            .when_some(panel, |el, panel| {
+                el.child(
+                    div()
+                        .w_full()
+                        .h(px(SIDEBAR_PANEL_HEIGHT))
+                        .border_b_1()
+                        .border_color(gpui::rgb(widgets::COLOR_BORDER))
+                        .child(panel),
+                )
+            })
+            .into_any()
     }
 
     fn on_dropdown_changed(
@@ -622,6 +1599,81 @@ impl Workspace {
         cx.notify();
     }
 
+    fn on_export_ascii(&mut self, _: &ExportAscii, window: &mut Window, cx: &mut Context<Self>) {
+        self.export_graph(ExportFormat::Ascii, window, cx);
+    }
+
+    fn on_export_dot(&mut self, _: &ExportDot, window: &mut Window, cx: &mut Context<Self>) {
+        self.export_graph(ExportFormat::Dot, window, cx);
+    }
+
+    fn on_export_svg(&mut self, _: &ExportSvg, window: &mut Window, cx: &mut Context<Self>) {
+        self.export_graph(ExportFormat::Svg, window, cx);
+    }
+
+    /// Ask where to save the graph and write it there.
+    fn export_graph(&mut self, format: ExportFormat, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(dock) = &self.dock else {
+            return;
+        };
+        let garph = dock.read(cx);
+        let Some(repo_path) = garph.repo_path().map(std::path::PathBuf::from) else {
+            return;
+        };
+        let content = format.render(&garph.snapshot(), garph.palette());
+        let name = format!("graph.{}", format.extension());
+        let prompt = cx.prompt_for_new_path(&repo_path, Some(&name));
+
+        cx.spawn_in(window, async move |_this, cx| {
+            let Ok(Ok(Some(path))) = prompt.await else {
+                return;
+            };
+            let written = cx
+                .background_executor()
+                .spawn(async move {
+                    std::fs::write(&path, content).map_err(|e| format!("{}: {}", path.display(), e))
+                })
+                .await;
+            if let Err(detail) = written {
+                let answer = cx.prompt(
+                    PromptLevel::Critical,
+                    "Export failed",
+                    Some(&detail),
+                    &["OK"],
+                );
+                answer.await.ok();
+            }
+        })
+        .detach();
+    }
+
+    /// File menu entry that closes the menu and dispatches `action`.
+    fn render_menu_action(
+        id: &'static str,
+        label: &'static str,
+        action: Box<dyn Action>,
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        div()
+            .id(id)
+            .text_color(gpui::white())
+            .px(px(16.0))
+            .py(px(8.0))
+            .child(label)
+            .hover(|style| style.bg(gpui::rgb(0x333333)))
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(move |this, _event, window, cx| {
+                    this.menu_bar.update(cx, |menu_bar, cx| {
+                        menu_bar.close_dropdown(cx);
+                    });
+                    window.dispatch_action(action.boxed_clone(), cx);
+                    cx.notify();
+                    cx.stop_propagation();
+                }),
+            )
+    }
+
     fn on_quit_clicked(
         &mut self,
         _title_bar: Entity<TitleBar>,
@@ -639,6 +1691,13 @@ impl Workspace {
     }
 
     pub fn set_selected_commit(&mut self, commit: Option<CommitSelected>, cx: &mut Context<Self>) {
+        let oid = commit.as_ref().map(|c| c.oid);
+        if let Some(bp) = &self.branch_panel {
+            bp.update(cx, |bp, cx| bp.set_selected_commit(oid, cx));
+        }
+        if let Some(tp) = &self.tag_panel {
+            tp.update(cx, |tp, cx| tp.set_selected_commit(oid, cx));
+        }
         self.selected_commit = commit;
         cx.notify();
     }
@@ -648,21 +1707,6 @@ impl EventEmitter<CommitSelected> for Workspace {}
 
 impl Render for Workspace {
     fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        self.poll_pending_results(cx);
-        self.poll_panel_reload(cx);
-
-        if let Some(dock) = &self.dock {
-            cx.subscribe(dock, Self::on_commit_selected).detach();
-        }
-
-        cx.subscribe(&self.menu_bar, Self::on_dropdown_changed)
-            .detach();
-        cx.subscribe(&self.title_bar, Self::on_quit_clicked)
-            .detach();
-        if let Some(bp) = &self.branch_panel {
-            cx.subscribe(bp, Self::on_branch_checked_out).detach();
-        }
-
         let dock = self.dock.clone().unwrap();
         let title_bar = self.title_bar.clone();
         let menu_bar = self.menu_bar.clone();
@@ -673,6 +1717,9 @@ impl Render for Workspace {
             .relative()
             .flex()
             .flex_col()
+            .on_action(cx.listener(Self::on_export_ascii))
+            .on_action(cx.listener(Self::on_export_dot))
+            .on_action(cx.listener(Self::on_export_svg))
             .on_mouse_down(
                 MouseButton::Left,
                 cx.listener(|this, _event, _window, cx| {
@@ -705,26 +1752,7 @@ impl Render for Workspace {
                             .border_r_1()
                             .border_color(gpui::rgb(0x333333))
                             .bg(gpui::rgb(0x282828))
-                            .when_some(self.branch_panel.clone(), |el, bp| {
-                                el.child(
-                                    div()
-                                        .w_full()
-                                        .h(gpui::px(140.0))
-                                        .border_b_1()
-                                        .border_color(gpui::rgb(0x333333))
-                                        .child(bp),
-                                )
-                            })
-                            .when_some(self.status_panel.clone(), |el, sp| {
-                                el.child(
-                                    div()
-                                        .w_full()
-                                        .h(gpui::px(140.0))
-                                        .border_b_1()
-                                        .border_color(gpui::rgb(0x333333))
-                                        .child(sp),
-                                )
-                            })
+                            .child(self.render_sidebar_tools(cx))
                             .child(div().flex_1().child(dock.clone())),
                     )
                     .child(
@@ -785,6 +1813,24 @@ impl Render for Workspace {
                                     }),
                                 ),
                         )
+                        .child(Self::render_menu_action(
+                            "menu_item_export_ascii",
+                            "Export graph as text...",
+                            Box::new(ExportAscii),
+                            cx,
+                        ))
+                        .child(Self::render_menu_action(
+                            "menu_item_export_dot",
+                            "Export graph as DOT...",
+                            Box::new(ExportDot),
+                            cx,
+                        ))
+                        .child(Self::render_menu_action(
+                            "menu_item_export_svg",
+                            "Export graph as SVG...",
+                            Box::new(ExportSvg),
+                            cx,
+                        ))
                         .child(
                             div()
                                 .id("menu_item_exit")
@@ -0,0 +1,102 @@
+// each test binary uses only some of the helpers
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use git2::{Oid, Repository, Signature, Time};
+
+/// Fresh repository in the temp directory, removed on drop.
+pub struct TempRepo {
+    pub path: PathBuf,
+    pub repo: Repository,
+}
+
+impl TempRepo {
+    /// `name` keeps the repositories of the tests in one binary apart.
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("gpig-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&path);
+        let repo = Repository::init(&path).unwrap();
+        Self { path, repo }
+    }
+
+    /// Like `new`, with `user.name` and `user.email` set for the code under
+    /// test that signs commits or tags.
+    pub fn with_identity(name: &str) -> Self {
+        let repo = Self::new(name);
+        let mut config = repo.repo.config().unwrap();
+        config.set_str("user.name", "Ann").unwrap();
+        config.set_str("user.email", "ann@x").unwrap();
+        repo
+    }
+
+    pub fn path(&self) -> &str {
+        self.path.to_str().unwrap()
+    }
+
+    pub fn write(&self, name: &str, text: &str) {
+        fs::write(self.path.join(name), text).unwrap();
+    }
+
+    /// Write `name` and add it to the index.
+    pub fn stage(&self, name: &str, text: &str) {
+        self.write(name, text);
+        let mut index = self.repo.index().unwrap();
+        index.add_path(Path::new(name)).unwrap();
+        index.write().unwrap();
+    }
+
+    /// Add every file of the working tree to the index and commit it on
+    /// top of HEAD.
+    pub fn commit_all(&self, message: &str) -> Oid {
+        let mut index = self.repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        self.commit(message)
+    }
+
+    /// Commit the index on top of HEAD.
+    pub fn commit(&self, message: &str) -> Oid {
+        self.commit_as(message, &Signature::now("t", "t@x").unwrap())
+    }
+
+    /// Commit the index on top of HEAD, dated `seconds` after the epoch.
+    pub fn commit_at(&self, message: &str, seconds: i64) -> Oid {
+        self.commit_as(
+            message,
+            &Signature::new("t", "t@x", &Time::new(seconds, 0)).unwrap(),
+        )
+    }
+
+    fn commit_as(&self, message: &str, sig: &Signature) -> Oid {
+        let tree_id = self.repo.index().unwrap().write_tree().unwrap();
+        let tree = self.repo.find_tree(tree_id).unwrap();
+        let parent = self.repo.head().and_then(|h| h.peel_to_commit()).ok();
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        self.repo
+            .commit(Some("HEAD"), sig, sig, message, &tree, &parents)
+            .unwrap()
+    }
+
+    pub fn head(&self) -> git2::Commit<'_> {
+        self.repo.head().unwrap().peel_to_commit().unwrap()
+    }
+
+    /// Content of `name` as staged in the index.
+    pub fn index_text(&self, name: &str) -> String {
+        let mut index = self.repo.index().unwrap();
+        index.read(true).unwrap();
+        let entry = index.get_path(Path::new(name), 0).unwrap();
+        let blob = self.repo.find_blob(entry.id).unwrap();
+        String::from_utf8(blob.content().to_vec()).unwrap()
+    }
+}
+
+impl Drop for TempRepo {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,123 @@
+use gpig::ancestry::{ParentFollow, related};
+use gpig::commit::CommitNode;
+use gpig::edge::Edge;
+use gpig::layout::GraphLayout;
+
+#[cfg(test)]
+mod test_ancestry {
+    use git2::{Oid, Time};
+
+    use super::*;
+
+    fn oid(s: &str) -> Oid {
+        Oid::from_str(s).unwrap()
+    }
+
+    /// Lay `commits` out in order, leaving out the `hidden` ones, and return
+    /// the rows with the edges drawn between them.
+    fn lay_out(commits: &[(&str, &[&str])], hidden: &[&str]) -> (Vec<CommitNode>, Vec<Edge>) {
+        let mut layout = GraphLayout::new(5);
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        for (id, parents) in commits {
+            let parents: Vec<Oid> = parents.iter().map(|p| oid(p)).collect();
+            if hidden.contains(id) {
+                layout.skip(oid(id), &parents);
+                continue;
+            }
+            let placed = layout.place(oid(id), &parents, None);
+            nodes.push(CommitNode::new(
+                oid(id),
+                String::new(),
+                String::new(),
+                Time::new(0, 0),
+                parents,
+                placed.cell,
+                placed.color,
+            ));
+            edges.extend(placed.edges);
+        }
+        (nodes, edges)
+    }
+
+    //   0001       merge of 0002 and 0003
+    //   |    \
+    //   0002 0003
+    //   |    /
+    //   0004
+    //   0005       unrelated root
+    fn graph() -> (Vec<CommitNode>, Vec<Edge>) {
+        lay_out(
+            &[
+                ("0001", &["0002", "0003"]),
+                ("0002", &["0004"]),
+                ("0003", &["0004"]),
+                ("0004", &[]),
+                ("0005", &[]),
+            ],
+            &[],
+        )
+    }
+
+    #[test]
+    fn side_commit_relates_to_its_line_only() {
+        let (nodes, edges) = graph();
+        let related = related(&nodes, &edges, oid("0003"), ParentFollow::All);
+        assert!(related.contains(&oid("0001")));
+        assert!(related.contains(&oid("0003")));
+        assert!(related.contains(&oid("0004")));
+        assert!(!related.contains(&oid("0002")));
+        assert!(!related.contains(&oid("0005")));
+    }
+
+    #[test]
+    fn merge_follows_the_chosen_parent() {
+        let (nodes, edges) = graph();
+        let all = related(&nodes, &edges, oid("0001"), ParentFollow::All);
+        assert_eq!(all.len(), 4);
+
+        let second = related(&nodes, &edges, oid("0001"), ParentFollow::Only(1));
+        assert!(second.contains(&oid("0003")));
+        assert!(second.contains(&oid("0004")));
+        assert!(!second.contains(&oid("0002")));
+    }
+
+    #[test]
+    fn unloaded_parents_are_left_out() {
+        let (nodes, edges) = lay_out(&[("0001", &["0009"])], &[]);
+        let related = related(&nodes, &edges, oid("0001"), ParentFollow::All);
+        assert_eq!(related.len(), 1);
+    }
+
+    #[test]
+    fn commits_joined_across_a_left_out_one_stay_related() {
+        //   0001
+        //   :      0002 left out, the edge is drawn dashed
+        //   0003
+        //   0004   unrelated root
+        let (nodes, edges) = lay_out(
+            &[
+                ("0001", &["0002"]),
+                ("0002", &["0003"]),
+                ("0003", &[]),
+                ("0004", &[]),
+            ],
+            &["0002"],
+        );
+        let up = related(&nodes, &edges, oid("0003"), ParentFollow::All);
+        assert!(up.contains(&oid("0001")));
+        assert!(!up.contains(&oid("0004")));
+
+        let down = related(&nodes, &edges, oid("0001"), ParentFollow::Only(0));
+        assert!(down.contains(&oid("0003")));
+        assert!(!down.contains(&oid("0002")));
+    }
+
+    #[test]
+    fn follow_cycles_through_parents() {
+        assert_eq!(ParentFollow::All.next(1), ParentFollow::All);
+        assert_eq!(ParentFollow::All.next(2), ParentFollow::Only(0));
+        assert_eq!(ParentFollow::Only(0).next(2), ParentFollow::Only(1));
+        assert_eq!(ParentFollow::Only(1).next(2), ParentFollow::All);
+    }
+}
@@ -0,0 +1,113 @@
+use gpig::branch::{
+    DeleteOutcome, create_branch_bg, delete_branch_bg, rename_branch_bg, validate_branch_name,
+};
+
+mod common;
+use common::TempRepo;
+
+#[cfg(test)]
+mod test_branch {
+    use git2::{BranchType, Oid};
+
+    use super::*;
+
+    /// Fresh repository with one commit on `main`.
+    fn repo_on_main(name: &str) -> TempRepo {
+        let repo = TempRepo::new(name);
+        repo.repo.set_head("refs/heads/main").unwrap();
+        repo.commit("init");
+        repo
+    }
+
+    fn tip(repo: &TempRepo, name: &str) -> Option<Oid> {
+        let branch = repo.repo.find_branch(name, BranchType::Local).ok()?;
+        branch.get().target()
+    }
+
+    #[test]
+    fn branch_names_follow_ref_rules() {
+        assert!(validate_branch_name("feature/login").is_ok());
+        for name in ["", "HEAD", "-x", "a..b", "a b", "a~1", "ends.lock", "x/"] {
+            assert!(validate_branch_name(name).is_err(), "{name:?}");
+        }
+    }
+
+    #[test]
+    fn create_at_head_or_at_a_commit() {
+        let repo = repo_on_main("create");
+        let first = tip(&repo, "main").unwrap();
+        let second = repo.commit("second");
+
+        create_branch_bg(repo.path(), "here", None, false).unwrap();
+        create_branch_bg(repo.path(), "there", Some(first), false).unwrap();
+        assert_eq!(tip(&repo, "here"), Some(second));
+        assert_eq!(tip(&repo, "there"), Some(first));
+
+        let taken = create_branch_bg(repo.path(), "here", Some(first), false);
+        assert_eq!(
+            taken,
+            Err("a branch named 'here' already exists".to_string())
+        );
+        assert_eq!(tip(&repo, "here"), Some(second));
+    }
+
+    #[test]
+    fn upstream_needs_no_remote_branch_yet() {
+        let repo = repo_on_main("upstream");
+        assert!(create_branch_bg(repo.path(), "lonely", None, true).is_err());
+
+        repo.repo
+            .remote("origin", "https://example.com/x.git")
+            .unwrap();
+        create_branch_bg(repo.path(), "topic", None, true).unwrap();
+        let config = repo.repo.config().unwrap().snapshot().unwrap();
+        assert_eq!(config.get_str("branch.topic.remote").unwrap(), "origin");
+        assert_eq!(
+            config.get_str("branch.topic.merge").unwrap(),
+            "refs/heads/topic"
+        );
+    }
+
+    #[test]
+    fn rename_the_checked_out_branch() {
+        let repo = repo_on_main("rename");
+        create_branch_bg(repo.path(), "other", None, false).unwrap();
+        assert!(rename_branch_bg(repo.path(), "main", "other").is_err());
+
+        rename_branch_bg(repo.path(), "main", "trunk").unwrap();
+        assert_eq!(tip(&repo, "main"), None);
+        assert_eq!(
+            repo.repo.head().unwrap().shorthand(),
+            Some("trunk"),
+            "HEAD follows the rename"
+        );
+    }
+
+    #[test]
+    fn unmerged_branches_need_force() {
+        let repo = repo_on_main("delete");
+        let base = tip(&repo, "main").unwrap();
+        create_branch_bg(repo.path(), "merged", None, false).unwrap();
+        repo.commit("only on side");
+        // side keeps the commit that main is moved back from
+        create_branch_bg(repo.path(), "side", None, false).unwrap();
+        repo.repo
+            .reference("refs/heads/main", base, true, "test")
+            .unwrap();
+
+        assert_eq!(
+            delete_branch_bg(repo.path(), "merged", false),
+            Ok(DeleteOutcome::Deleted)
+        );
+        assert_eq!(
+            delete_branch_bg(repo.path(), "side", false),
+            Ok(DeleteOutcome::Unmerged)
+        );
+        assert!(tip(&repo, "side").is_some());
+        assert_eq!(
+            delete_branch_bg(repo.path(), "side", true),
+            Ok(DeleteOutcome::Deleted)
+        );
+        assert!(delete_branch_bg(repo.path(), "main", true).is_err());
+    }
+}
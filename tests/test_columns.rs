@@ -0,0 +1,59 @@
+use gpig::columns::{Column, Columns, DateStyle, MAX_COLUMN_WIDTH, MIN_COLUMN_WIDTH, relative};
+
+#[cfg(test)]
+mod test_columns {
+    use chrono::{TimeZone, Utc};
+    use git2::Time;
+
+    use super::*;
+
+    #[test]
+    fn relative_dates() {
+        assert_eq!(relative(-30), "just now");
+        assert_eq!(relative(59), "just now");
+        assert_eq!(relative(5 * 60), "5m ago");
+        assert_eq!(relative(3 * 60 * 60 + 59), "3h ago");
+        assert_eq!(relative(2 * 24 * 60 * 60), "2d ago");
+        assert_eq!(relative(15 * 24 * 60 * 60), "2w ago");
+        assert_eq!(relative(90 * 24 * 60 * 60), "3mo ago");
+        assert_eq!(relative(800 * 24 * 60 * 60), "2y ago");
+    }
+
+    #[test]
+    fn commit_timezone_is_kept() {
+        let now = Utc.with_ymd_and_hms(2026, 1, 2, 0, 0, 0).unwrap();
+        // 2026-01-01 12:00 UTC, made at +02:00
+        let time = Time::new(now.timestamp() - 12 * 60 * 60, 120);
+        assert_eq!(DateStyle::Commit.format(time, now), "2026-01-01 14:00");
+        assert_eq!(DateStyle::Relative.format(time, now), "12h ago");
+    }
+
+    #[test]
+    fn widths_are_clamped_and_hidden_columns_take_no_room() {
+        let mut columns = Columns::default();
+        columns.set_width(Column::Author, 1.0);
+        assert_eq!(columns.get(Column::Author).width, MIN_COLUMN_WIDTH);
+        columns.set_width(Column::Author, 10_000.0);
+        assert_eq!(columns.get(Column::Author).width, MAX_COLUMN_WIDTH);
+
+        assert_eq!(columns.visible().count(), 0);
+        assert_eq!(columns.total_width(), 0.0);
+        columns.toggle(Column::Sha);
+        columns.toggle(Column::Author);
+        assert_eq!(
+            columns.visible().collect::<Vec<_>>(),
+            vec![Column::Author, Column::Sha]
+        );
+        assert_eq!(
+            columns.total_width(),
+            columns.get(Column::Author).width + columns.get(Column::Sha).width
+        );
+    }
+
+    #[test]
+    fn date_style_cycles() {
+        let style = DateStyle::default();
+        assert_eq!(style, DateStyle::Relative);
+        assert_eq!(style.next().next().next(), style);
+    }
+}
@@ -0,0 +1,142 @@
+use gpig::composer::{CommitOptions, SubjectLength, commit_bg, sign_off, subject_length};
+
+mod common;
+use common::TempRepo;
+
+#[cfg(test)]
+mod test_composer {
+    use git2::Oid;
+
+    use super::*;
+
+    fn commit(repo: &TempRepo, message: &str, options: CommitOptions) -> Result<Oid, String> {
+        commit_bg(repo.path(), message, options)
+    }
+
+    #[test]
+    fn subject_length_follows_the_guide() {
+        assert_eq!(subject_length(""), (0, SubjectLength::Fits));
+        assert_eq!(
+            subject_length(&format!("{}\n\nbody", "a".repeat(50))),
+            (50, SubjectLength::Fits)
+        );
+        assert_eq!(subject_length(&"é".repeat(51)), (51, SubjectLength::Long));
+        assert_eq!(
+            subject_length(&"a".repeat(73)),
+            (73, SubjectLength::TooLong)
+        );
+    }
+
+    #[test]
+    fn sign_off_starts_or_joins_the_trailers() {
+        assert_eq!(
+            sign_off("Fix it\n\nBecause.\n", "Ann", "ann@x"),
+            "Fix it\n\nBecause.\n\nSigned-off-by: Ann <ann@x>"
+        );
+        assert_eq!(
+            sign_off("Fix it\n\nAcked-by: Bob <bob@x>", "Ann", "ann@x"),
+            "Fix it\n\nAcked-by: Bob <bob@x>\nSigned-off-by: Ann <ann@x>"
+        );
+        // a subject that looks like a trailer is still a subject
+        assert_eq!(
+            sign_off("Docs: typo", "Ann", "ann@x"),
+            "Docs: typo\n\nSigned-off-by: Ann <ann@x>"
+        );
+    }
+
+    #[test]
+    fn signing_off_twice_changes_nothing() {
+        let signed = sign_off("Fix it", "Ann", "ann@x");
+        assert_eq!(sign_off(&signed, "Ann", "ann@x"), signed);
+    }
+
+    #[test]
+    fn commit_and_amend_the_index() {
+        let repo = TempRepo::with_identity("amend");
+        repo.stage("a.txt", "one\n");
+        let first = commit(
+            &repo,
+            "Add a\n",
+            CommitOptions {
+                sign_off: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let head = repo.head();
+        assert_eq!(head.id(), first);
+        assert_eq!(
+            head.message(),
+            Some("Add a\n\nSigned-off-by: Ann <ann@x>\n")
+        );
+        assert_eq!(head.author().name(), Some("Ann"));
+        assert_eq!(head.parent_count(), 0);
+
+        repo.stage("b.txt", "two\n");
+        let amended = commit(
+            &repo,
+            "Add a and b",
+            CommitOptions {
+                amend: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let head = repo.head();
+        assert_eq!(head.id(), amended);
+        assert_eq!(head.message(), Some("Add a and b\n"));
+        assert_eq!(head.parent_count(), 0);
+        assert!(head.tree().unwrap().get_name("b.txt").is_some());
+    }
+
+    #[test]
+    fn refuses_empty_messages_and_empty_commits() {
+        let repo = TempRepo::with_identity("empty");
+        repo.stage("a.txt", "one\n");
+        assert!(commit(&repo, "  \n", CommitOptions::default()).is_err());
+        let first = commit(&repo, "Add a", CommitOptions::default()).unwrap();
+
+        assert_eq!(
+            commit(&repo, "Again", CommitOptions::default()),
+            Err("nothing staged to commit".to_string())
+        );
+        assert_eq!(repo.head().id(), first);
+    }
+
+    #[test]
+    fn refuses_to_skip_signing_or_hooks() {
+        let repo = TempRepo::with_identity("unsupported");
+        repo.stage("a.txt", "one\n");
+        repo.repo
+            .config()
+            .unwrap()
+            .set_bool("commit.gpgsign", true)
+            .unwrap();
+        let signed = commit(&repo, "Add a", CommitOptions::default());
+        assert!(signed.unwrap_err().contains("commit.gpgsign"));
+        repo.repo
+            .config()
+            .unwrap()
+            .set_bool("commit.gpgsign", false)
+            .unwrap();
+
+        let hooks = repo.repo.path().join("hooks");
+        std::fs::create_dir_all(&hooks).unwrap();
+        // samples and hooks that are not executable are not run by git either
+        std::fs::write(hooks.join("pre-commit.sample"), "#!/bin/sh\nexit 1\n").unwrap();
+        std::fs::write(hooks.join("commit-msg"), "#!/bin/sh\nexit 1\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let hook = hooks.join("commit-msg");
+            std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o644)).unwrap();
+            assert!(commit(&repo, "Add a", CommitOptions::default()).is_ok());
+
+            repo.stage("b.txt", "two\n");
+            std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).unwrap();
+            let hooked = commit(&repo, "Add b", CommitOptions::default());
+            assert!(hooked.unwrap_err().contains("commit-msg"));
+        }
+    }
+}
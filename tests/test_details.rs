@@ -0,0 +1,62 @@
+use gpig::details::{Trailer, format_time, split_trailers};
+
+#[cfg(test)]
+mod test_details {
+    use git2::Time;
+
+    use super::*;
+
+    fn trailer(key: &str, value: &str) -> Trailer {
+        Trailer {
+            key: key.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn trailers_come_from_the_last_paragraph() {
+        let body = "\nExplain the change.\n\nSigned-off-by: A <a@x>\nCo-authored-by: B <b@x>\nReviewed-by: C <c@x>\n";
+        let (text, trailers) = split_trailers(body);
+        assert_eq!(text, "Explain the change.");
+        assert_eq!(
+            trailers,
+            vec![
+                trailer("Signed-off-by", "A <a@x>"),
+                trailer("Co-authored-by", "B <b@x>"),
+                trailer("Reviewed-by", "C <c@x>"),
+            ]
+        );
+    }
+
+    #[test]
+    fn paragraph_with_prose_has_no_trailers() {
+        let body = "\nFirst paragraph.\n\nNote: this line looks like one\nbut this one does not.";
+        let (text, trailers) = split_trailers(body);
+        assert!(trailers.is_empty());
+        assert_eq!(
+            text,
+            "First paragraph.\n\nNote: this line looks like one\nbut this one does not."
+        );
+    }
+
+    #[test]
+    fn trailers_only_and_continuations() {
+        let (text, trailers) = split_trailers("\nSigned-off-by: A\n  <a@x>\n");
+        assert_eq!(text, "");
+        assert_eq!(trailers, vec![trailer("Signed-off-by", "A <a@x>")]);
+    }
+
+    #[test]
+    fn keys_with_spaces_are_not_trailers() {
+        let (_, trailers) = split_trailers("\nSee also: the docs");
+        assert!(trailers.is_empty());
+        assert_eq!(split_trailers(""), (String::new(), Vec::new()));
+    }
+
+    #[test]
+    fn time_keeps_its_offset() {
+        // 2026-01-01 12:00:00 UTC, made at -05:30
+        let time = Time::new(1_767_268_800, -330);
+        assert_eq!(format_time(time), "2026-01-01 06:30:00 -0530");
+    }
+}
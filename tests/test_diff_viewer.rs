@@ -0,0 +1,148 @@
+use gpig::diff_viewer::{
+    DiffLineKind, FileChange, HunkRange, SideBySideRow, build_patch, hunk_rows, parse_diff,
+    parse_file_change, parse_hunk_header,
+};
+
+#[cfg(test)]
+mod test_diff_viewer {
+    use super::*;
+
+    const DIFF: &str = "diff --git a/f b/f\n\
+                        --- a/f\n\
+                        +++ b/f\n\
+                        @@ -1,5 +1,5 @@\n \
+                        1\n\
+                        -2\n\
+                        +X\n \
+                        3\n \
+                        4\n\
+                        -5\n\
+                        +Y\n";
+
+    fn sides(row: &SideBySideRow) -> (Option<String>, Option<String>) {
+        match row {
+            SideBySideRow::Hunk { .. } => panic!("expected a line"),
+            SideBySideRow::Line { left, right } => (
+                left.as_ref().map(|l| l.content.clone()),
+                right.as_ref().map(|l| l.content.clone()),
+            ),
+        }
+    }
+
+    #[test]
+    fn hunk_headers() {
+        assert_eq!(
+            parse_hunk_header("@@ -3,2 +4 @@ fn main() {"),
+            Some(HunkRange {
+                old_start: 3,
+                old_lines: 2,
+                new_start: 4,
+                new_lines: 1,
+            })
+        );
+        assert_eq!(parse_hunk_header("@@ garbage @@"), None);
+    }
+
+    #[test]
+    fn removed_and_added_lines_pair_in_order() {
+        let rows = parse_diff("@@ -1,2 +1,2 @@\n-a\n-b\n+A\n+B\n");
+        assert_eq!(rows.len(), 3);
+        assert_eq!(sides(&rows[1]), (Some("a".into()), Some("A".into())));
+        assert_eq!(sides(&rows[2]), (Some("b".into()), Some("B".into())));
+    }
+
+    #[test]
+    fn content_that_looks_like_a_header_is_kept() {
+        let rows = parse_diff("@@ -1,2 +0,0 @@\n--- a\n-b\n\\ No newline at end of file\n");
+        assert_eq!(sides(&rows[1]), (Some("-- a".into()), None));
+        let SideBySideRow::Line {
+            left: Some(last), ..
+        } = &rows[2]
+        else {
+            panic!("expected a removed line");
+        };
+        assert_eq!(last.kind, DiffLineKind::Removed);
+        assert!(last.no_newline);
+    }
+
+    #[test]
+    fn hunk_rows_stop_at_the_next_header() {
+        let rows = parse_diff("@@ -1 +1 @@\n-a\n+b\n@@ -9 +9 @@\n-c\n+d\n");
+        assert_eq!(hunk_rows(&rows, 0), 1..2);
+        assert_eq!(hunk_rows(&rows, 2), 3..4);
+    }
+
+    #[test]
+    fn unselected_changes_are_left_as_they_are() {
+        let rows = parse_diff(DIFF);
+        assert!(rows[5].is_change() && !rows[4].is_change());
+        let patch = build_patch("f", &FileChange::Modified, &rows, |row| row == 5, false).unwrap();
+        assert_eq!(
+            patch,
+            "diff --git a/f b/f\n--- a/f\n+++ b/f\n@@ -1,5 +1,5 @@\n 1\n 2\n 3\n 4\n-5\n+Y\n"
+        );
+    }
+
+    #[test]
+    fn reverse_patch_starts_from_the_new_side() {
+        let rows = parse_diff(DIFF);
+        let patch = build_patch("f", &FileChange::Modified, &rows, |row| row == 2, true).unwrap();
+        assert_eq!(
+            patch,
+            "diff --git a/f b/f\n--- a/f\n+++ b/f\n@@ -1,5 +1,5 @@\n 1\n-X\n+2\n 3\n 4\n Y\n"
+        );
+    }
+
+    #[test]
+    fn later_hunks_shift_by_the_lines_kept_before_them() {
+        let rows = parse_diff("@@ -1,1 +1,2 @@\n a\n+b\n@@ -10,1 +11,1 @@\n-c\n+d\n");
+        // only the second hunk: the line the first one added is not there
+        let patch = build_patch("f", &FileChange::Modified, &rows, |row| row >= 3, false).unwrap();
+        assert!(patch.ends_with("@@ -10,1 +10,1 @@\n-c\n+d\n"));
+        assert_eq!(
+            build_patch("f", &FileChange::Modified, &rows, |_| false, false),
+            None
+        );
+    }
+
+    #[test]
+    fn new_files_apply_from_nothing() {
+        let raw = "diff --git a/n b/n\nnew file mode 100755\nindex 0000000..1111111\n\
+                   --- /dev/null\n+++ b/n\n@@ -0,0 +1,2 @@\n+a\n+b\n";
+        let change = parse_file_change(raw);
+        assert_eq!(
+            change,
+            FileChange::Added {
+                mode: "100755".to_string()
+            }
+        );
+        let patch = build_patch("n", &change, &parse_diff(raw), |row| row == 1, false).unwrap();
+        assert_eq!(
+            patch,
+            "diff --git a/n b/n\nnew file mode 100755\n--- /dev/null\n+++ b/n\n@@ -0,0 +1,1 @@\n+a\n"
+        );
+    }
+
+    #[test]
+    fn emptied_files_are_not_deleted() {
+        let raw = "diff --git a/f b/f\nindex 1111111..e69de29 100644\n--- a/f\n+++ b/f\n\
+                   @@ -1,2 +0,0 @@\n-a\n-b\n";
+        let change = parse_file_change(raw);
+        assert_eq!(change, FileChange::Modified);
+        let patch = build_patch("f", &change, &parse_diff(raw), |_| true, false).unwrap();
+        assert_eq!(
+            patch,
+            "diff --git a/f b/f\n--- a/f\n+++ b/f\n@@ -1,2 +0,0 @@\n-a\n-b\n"
+        );
+
+        let deleted = FileChange::Deleted {
+            mode: "100644".to_string(),
+        };
+        let rows = parse_diff(raw);
+        let patch = build_patch("f", &deleted, &rows, |_| true, false).unwrap();
+        assert!(patch.starts_with("diff --git a/f b/f\ndeleted file mode 100644\n"));
+        // a line kept back means the file stays
+        let patch = build_patch("f", &deleted, &rows, |row| row == 1, false).unwrap();
+        assert!(patch.starts_with("diff --git a/f b/f\n--- a/f\n+++ b/f\n"));
+    }
+}
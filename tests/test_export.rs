@@ -0,0 +1,116 @@
+use gpig::commit::CommitNode;
+use gpig::export::{to_ascii, to_dot, to_svg};
+use gpig::garph::GraphData;
+use gpig::layout::GraphLayout;
+use gpig::refs::{RefKind, RefLabel};
+
+#[cfg(test)]
+mod test_export {
+    use git2::{Oid, Time};
+
+    use super::*;
+
+    fn oid(s: &str) -> Oid {
+        Oid::from_str(s).unwrap()
+    }
+
+    /// Lay out `commits` in order, as the revwalk would hand them over.
+    fn graph(commits: &[(&str, &[&str], &str)]) -> GraphData {
+        let mut layout = GraphLayout::new(5);
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        for (id, parents, message) in commits {
+            let parents: Vec<Oid> = parents.iter().map(|p| oid(p)).collect();
+            let placed = layout.place(oid(id), &parents, None);
+            nodes.push(CommitNode::new(
+                oid(id),
+                message.to_string(),
+                String::new(),
+                Time::new(0, 0),
+                parents,
+                placed.cell,
+                placed.color,
+            ));
+            edges.extend(placed.edges);
+        }
+        GraphData {
+            nodes,
+            edges,
+            max_lane: layout.max_lane(),
+            rows: layout.rows(),
+            has_more: false,
+        }
+    }
+
+    fn merge() -> GraphData {
+        graph(&[
+            ("0001", &["0002", "0003"], "merge feature"),
+            ("0003", &["0002"], "feature work"),
+            ("0002", &[], "initial"),
+        ])
+    }
+
+    #[test]
+    fn ascii_draws_a_merge() {
+        let mut data = merge();
+        data.nodes[0] = data.nodes[0]
+            .clone()
+            .with_refs(vec![RefLabel::new("main", RefKind::Head)]);
+        let text = to_ascii(&data);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "*   0001000 (main) merge feature",
+                "|\\",
+                "| * 0003000 feature work",
+                "|/",
+                "*   0002000 initial",
+            ]
+        );
+    }
+
+    #[test]
+    fn ascii_of_a_line_has_no_bends() {
+        let data = graph(&[("0001", &["0002"], "second"), ("0002", &[], "first")]);
+        assert_eq!(to_ascii(&data), "* 0001000 second\n|\n* 0002000 first\n");
+    }
+
+    #[test]
+    fn dot_links_children_to_parents() {
+        let dot = to_dot(&merge(), &[0x112233]);
+        assert!(dot.starts_with("digraph commits {"));
+        assert!(dot.contains("\"0001000000000000000000000000000000000000\" -> \"0002000000000000000000000000000000000000\""));
+        assert!(dot.contains("\"0001000000000000000000000000000000000000\" -> \"0003000000000000000000000000000000000000\""));
+        assert!(dot.contains("\"0003000000000000000000000000000000000000\" -> \"0002000000000000000000000000000000000000\""));
+        assert!(dot.contains("fillcolor=\"#112233\""));
+        assert!(dot.trim_end().ends_with('}'));
+    }
+
+    #[test]
+    fn dot_escapes_quotes() {
+        let data = graph(&[("0001", &[], "say \"hi\"")]);
+        assert!(to_dot(&data, &[0]).contains("say \\\"hi\\\""));
+    }
+
+    #[test]
+    fn svg_is_deterministic_and_complete() {
+        let data = merge();
+        let svg = to_svg(&data, &[0x9B59B6, 0x3498DB]);
+        assert_eq!(svg, to_svg(&merge(), &[0x9B59B6, 0x3498DB]));
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches("<circle").count(), 3);
+        assert_eq!(svg.matches("<path").count(), data.edges.len());
+        // the merge into the side lane bends, the rest are straight
+        assert!(svg.contains(" C"));
+        assert!(svg.contains("merge feature"));
+    }
+
+    #[test]
+    fn svg_escapes_markup() {
+        let data = graph(&[("0001", &[], "fix <b> & \"quotes\"")]);
+        let svg = to_svg(&data, &[0]);
+        assert!(svg.contains("fix &lt;b&gt; &amp; &quot;quotes&quot;"));
+    }
+}
@@ -0,0 +1,260 @@
+use gpig::edge::EdgeKind;
+use gpig::layout::{GraphCell, GraphLayout, GraphMode};
+
+#[cfg(test)]
+mod test_layout {
+    use git2::Oid;
+
+    use super::*;
+
+    fn oid(s: &str) -> Oid {
+        Oid::from_str(s).unwrap()
+    }
+
+    fn layout() -> GraphLayout {
+        GraphLayout::new(5)
+    }
+
+    #[test]
+    fn straight_line() {
+        let mut layout = layout();
+        let first = layout.place(oid("0001"), &[oid("0002")], None);
+        let second = layout.place(oid("0002"), &[oid("0003")], None);
+        let third = layout.place(oid("0003"), &[], None);
+
+        assert_eq!(first.cell, GraphCell::new(0, 0));
+        assert_eq!(second.cell, GraphCell::new(1, 0));
+        assert_eq!(third.cell, GraphCell::new(2, 0));
+        assert!(first.edges.is_empty());
+        assert_eq!(second.edges.len(), 1);
+        assert_eq!(second.edges[0].kind, EdgeKind::Straight);
+        assert_eq!(second.edges[0].from, first.cell);
+        assert_eq!(second.edges[0].to, second.cell);
+        assert_eq!(layout.rows(), 3);
+        assert_eq!(layout.max_lane(), 0);
+    }
+
+    #[test]
+    fn merge_and_fork() {
+        //  1        merge of 2 and 3
+        //  | \
+        //  2  3
+        //  | /
+        //  4
+        let mut layout = layout();
+        layout.place(oid("0001"), &[oid("0002"), oid("0003")], None);
+        let two = layout.place(oid("0002"), &[oid("0004")], None);
+        let three = layout.place(oid("0003"), &[oid("0004")], None);
+        let four = layout.place(oid("0004"), &[], None);
+
+        assert_eq!(two.cell, GraphCell::new(1, 0));
+        assert_eq!(three.cell, GraphCell::new(2, 1));
+        assert_eq!(four.cell, GraphCell::new(3, 0));
+        assert_eq!(layout.max_lane(), 1);
+
+        // the merge commit reaches sideways into the second parent's lane
+        assert_eq!(three.edges.len(), 1);
+        assert_eq!(three.edges[0].kind, EdgeKind::ForkOut);
+        assert_eq!(three.edges[0].control(), Some(GraphCell::new(0, 1)));
+
+        // the side lane bends back into the base commit
+        let kinds: Vec<EdgeKind> = four.edges.iter().map(|e| e.kind).collect();
+        assert_eq!(kinds, vec![EdgeKind::Straight, EdgeKind::MergeIn]);
+        assert_eq!(four.edges[1].from, three.cell);
+        assert_eq!(four.edges[1].control(), Some(GraphCell::new(3, 1)));
+    }
+
+    #[test]
+    fn same_input_same_layout() {
+        let commits = [
+            ("0001", vec!["0002", "0003"]),
+            ("0002", vec!["0004"]),
+            ("0003", vec!["0004"]),
+            ("0004", vec![]),
+        ];
+        let run = || {
+            let mut layout = layout();
+            commits
+                .iter()
+                .map(|(c, ps)| {
+                    let parents: Vec<Oid> = ps.iter().map(|p| oid(p)).collect();
+                    let placed = layout.place(oid(c), &parents, None);
+                    (placed.cell, placed.color)
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn branch_color_follows_first_parent() {
+        //  1  feature tip
+        //  |
+        //  2
+        //  | 3  main tip, not an ancestor of 1
+        //  |/
+        //  4
+        let mut layout = layout();
+        let one = layout.place(oid("0001"), &[oid("0002")], Some("feature"));
+        let two = layout.place(oid("0002"), &[oid("0004")], None);
+        let three = layout.place(oid("0003"), &[oid("0004")], Some("main"));
+        let four = layout.place(oid("0004"), &[], None);
+
+        assert_eq!(one.color, two.color);
+        // both chains are named, the first child in walk order hands down its color
+        assert_eq!(four.color, one.color);
+
+        let mut again = GraphLayout::new(5);
+        assert_eq!(
+            again.place(oid("0003"), &[oid("0004")], Some("main")).color,
+            three.color
+        );
+    }
+
+    #[test]
+    fn named_chain_beats_unnamed() {
+        //  1  unnamed
+        //  | 2  main tip
+        //  |/
+        //  3
+        let mut layout = layout();
+        layout.place(oid("0001"), &[oid("0003")], None);
+        let main = layout.place(oid("0002"), &[oid("0003")], Some("main"));
+        let base = layout.place(oid("0003"), &[], None);
+
+        assert_eq!(base.color, main.color);
+    }
+
+    #[test]
+    fn skipped_commit_keeps_edges_connected() {
+        let mut layout = layout();
+        let one = layout.place(oid("0001"), &[oid("0002")], Some("main"));
+        layout.skip(oid("0002"), &[oid("0003")]);
+        let three = layout.place(oid("0003"), &[], None);
+
+        assert_eq!(three.cell, GraphCell::new(1, 0));
+        assert_eq!(three.color, one.color);
+        assert_eq!(three.edges.len(), 1);
+        assert_eq!(three.edges[0].from, one.cell);
+        assert_eq!(three.edges[0].kind, EdgeKind::Straight);
+        assert!(three.edges[0].elided);
+    }
+
+    #[test]
+    fn first_parent_drops_side_parents() {
+        let parents = [oid("0002"), oid("0003")];
+        assert_eq!(GraphMode::FirstParent.parents(&parents), &parents[..1]);
+        assert_eq!(GraphMode::Full.parents(&parents), &parents[..]);
+        assert!(GraphMode::FirstParent.parents(&[]).is_empty());
+    }
+
+    #[test]
+    fn first_parent_merge_opens_no_side_lane() {
+        //  1  merge of 2 and 3, 3 is never walked
+        //  |
+        //  2
+        let mode = GraphMode::FirstParent;
+        let mut layout = layout();
+        let merge = [oid("0002"), oid("0003")];
+        let one = layout.place(oid("0001"), mode.parents(&merge), None);
+        let two = layout.place(oid("0002"), mode.parents(&[]), None);
+
+        assert_eq!(two.cell, GraphCell::new(1, 0));
+        assert_eq!(two.edges.len(), 1);
+        assert_eq!(two.edges[0].from, one.cell);
+        assert_eq!(two.edges[0].kind, EdgeKind::Straight);
+        assert_eq!(layout.max_lane(), 0);
+
+        // nothing is left waiting on the side parent, as if 1 were no merge
+        let mut line = GraphLayout::new(5);
+        line.place(oid("0001"), &[oid("0002")], None);
+        line.place(oid("0002"), &[], None);
+        assert!(layout.continues(&line.frontier()));
+    }
+
+    #[test]
+    fn simplify_by_decoration_keeps_refs_and_merges() {
+        let mode = GraphMode::SimplifyByDecoration;
+        assert!(mode.shows(true, 1));
+        assert!(mode.shows(false, 2));
+        assert!(!mode.shows(false, 1));
+        assert!(GraphMode::Full.shows(false, 1));
+    }
+
+    #[test]
+    fn collapsed_merge_side_is_elided() {
+        //  1  merge
+        //  | \
+        //  |  2  hidden
+        //  |  3
+        //  | /
+        //  4
+        let mut layout = layout();
+        let one = layout.place(oid("0001"), &[oid("0004"), oid("0002")], None);
+        layout.skip(oid("0002"), &[oid("0003")]);
+        let three = layout.place(oid("0003"), &[oid("0004")], None);
+
+        assert_eq!(three.cell, GraphCell::new(1, 1));
+        assert_eq!(three.edges.len(), 1);
+        assert_eq!(three.edges[0].from, one.cell);
+        assert_eq!(three.edges[0].kind, EdgeKind::ForkOut);
+        assert!(three.edges[0].elided);
+    }
+
+    #[test]
+    fn fully_collapsed_merge_side_folds_into_the_first_parent_edge() {
+        //  1  merge
+        //  | \
+        //  |  2  hidden
+        //  |  3  hidden
+        //  | /
+        //  4
+        // The side run has no row left to bend through: its dashed edge would
+        // retrace the first-parent edge between the same two rows, so it is
+        // folded into that edge instead of being drawn on top of it.
+        let mut layout = layout();
+        layout.place(oid("0001"), &[oid("0004"), oid("0002")], None);
+        layout.skip(oid("0002"), &[oid("0003")]);
+        layout.skip(oid("0003"), &[oid("0004")]);
+        let four = layout.place(oid("0004"), &[], None);
+
+        assert_eq!(four.cell, GraphCell::new(1, 0));
+        assert_eq!(four.edges.len(), 1);
+        assert_eq!(four.edges[0].kind, EdgeKind::Straight);
+        assert!(!four.edges[0].elided);
+    }
+
+    #[test]
+    fn prepended_commit_continues_old_layout() {
+        let mut old = layout();
+        old.place(oid("0002"), &[oid("0003")], Some("main"));
+        old.place(oid("0003"), &[oid("0004")], None);
+
+        // main moved up by one commit
+        let mut new = layout();
+        new.place(oid("0001"), &[oid("0002")], Some("main"));
+        new.place(oid("0002"), &[oid("0003")], None);
+        new.place(oid("0003"), &[oid("0004")], None);
+        assert!(new.continues(&old.frontier()));
+
+        let mut kept = old.clone();
+        kept.shift_rows(1);
+        let from_kept = kept.place(oid("0004"), &[], None);
+        let from_new = new.place(oid("0004"), &[], None);
+        assert_eq!(from_kept.cell, from_new.cell);
+        assert_eq!(from_kept.color, from_new.color);
+        assert_eq!(from_kept.edges[0].from, from_new.edges[0].from);
+    }
+
+    #[test]
+    fn moved_lane_does_not_continue() {
+        let mut old = layout();
+        old.place(oid("0002"), &[oid("0003")], None);
+
+        // a new tip takes lane 0 and pushes the old chain aside
+        let mut new = layout();
+        new.place(oid("0001"), &[oid("0009")], None);
+        new.place(oid("0002"), &[oid("0003")], None);
+        assert!(!new.continues(&old.frontier()));
+    }
+}
@@ -0,0 +1,66 @@
+use gpig::query::{CommitFields, CommitQuery};
+
+#[cfg(test)]
+mod test_query {
+    use super::*;
+
+    fn fields(time: i64) -> CommitFields<'static> {
+        CommitFields {
+            sha: "abc1234def",
+            message: "Fix crash when opening an empty repo",
+            author_name: "Alice Doe",
+            author_email: "alice@example.com",
+            time,
+        }
+    }
+
+    #[test]
+    fn bare_words_are_paths() {
+        let query = CommitQuery::parse("src/garph.rs  docs/").unwrap();
+        assert_eq!(query.paths, vec!["src/garph.rs", "docs/"]);
+        assert!(!query.has_commit_terms());
+    }
+
+    #[test]
+    fn quoted_message() {
+        let query = CommitQuery::parse(r#"author:Alice msg:"fix crash" sha:ABC1"#).unwrap();
+        assert_eq!(query.authors, vec!["alice"]);
+        assert_eq!(query.messages, vec!["fix crash"]);
+        assert_eq!(query.shas, vec!["abc1"]);
+        assert!(query.matches_fields(&fields(0)));
+    }
+
+    #[test]
+    fn every_term_must_match() {
+        let query = CommitQuery::parse("author:alice msg:deadlock").unwrap();
+        assert!(!query.matches_fields(&fields(0)));
+        let query = CommitQuery::parse("author:bob").unwrap();
+        assert!(!query.matches_fields(&fields(0)));
+    }
+
+    #[test]
+    fn date_range() {
+        let query = CommitQuery::parse("since:2026-01-01 until:2026-01-31").unwrap();
+        let since = query.since.unwrap();
+        let until = query.until.unwrap();
+        assert!(since < until);
+        assert!(query.matches_fields(&fields(since)));
+        assert!(query.matches_fields(&fields(until - 1)));
+        assert!(!query.matches_fields(&fields(since - 1)));
+        assert!(!query.matches_fields(&fields(until)));
+    }
+
+    #[test]
+    fn empty_value_is_ignored() {
+        let query = CommitQuery::parse("until:").unwrap();
+        assert!(query.is_empty());
+    }
+
+    #[test]
+    fn errors() {
+        assert!(CommitQuery::parse("since:yesterday").is_err());
+        assert!(CommitQuery::parse("sha:xyz").is_err());
+        assert!(CommitQuery::parse("colour:red").is_err());
+        assert!(CommitQuery::parse(r#"msg:"open"#).is_err());
+    }
+}
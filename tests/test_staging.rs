@@ -0,0 +1,190 @@
+use gpig::diff_viewer::{SideBySideRow, build_patch, parse_diff, parse_file_change};
+use gpig::garph::compute_index_file_diff_bg;
+use gpig::panel_loader::load_status;
+use gpig::staging::{HunkAction, StageAction, apply_patch_bg, run_bg};
+use gpig::status_panel::StatusKind;
+
+mod common;
+use common::TempRepo;
+
+#[cfg(test)]
+mod test_staging {
+    use std::fs;
+
+    use git2::Status;
+
+    use super::*;
+
+    fn status(repo: &TempRepo, name: &str) -> Status {
+        repo.repo.status_file(std::path::Path::new(name)).unwrap()
+    }
+
+    fn run(repo: &TempRepo, action: StageAction, paths: &[&str]) {
+        let paths: Vec<String> = paths.iter().map(|p| p.to_string()).collect();
+        run_bg(repo.path(), action, &paths).unwrap();
+    }
+
+    /// Build and apply a patch of the changed rows of `name` that mention
+    /// `text`, from the staged or the unstaged diff.
+    fn apply_lines(repo: &TempRepo, name: &str, staged: bool, action: HunkAction, text: &str) {
+        let raw =
+            compute_index_file_diff_bg(repo.path().to_string(), staged, name.to_string()).unwrap();
+        let rows = parse_diff(&raw);
+        let picked = |row: usize| match &rows[row] {
+            SideBySideRow::Line { left, right } => {
+                rows[row].is_change()
+                    && [left, right]
+                        .into_iter()
+                        .flatten()
+                        .any(|line| line.content == text)
+            }
+            SideBySideRow::Hunk { .. } => false,
+        };
+        let change = parse_file_change(&raw);
+        let patch = build_patch(name, &change, &rows, picked, action.reverse()).unwrap();
+        apply_patch_bg(repo.path(), action, &patch).unwrap();
+    }
+
+    #[test]
+    fn stage_and_unstage_a_modification() {
+        let repo = TempRepo::new("modification");
+        repo.write("a.txt", "one\n");
+        repo.commit_all("init");
+        repo.write("a.txt", "two\n");
+        assert_eq!(status(&repo, "a.txt"), Status::WT_MODIFIED);
+
+        run(&repo, StageAction::Stage, &["a.txt"]);
+        assert_eq!(status(&repo, "a.txt"), Status::INDEX_MODIFIED);
+
+        run(&repo, StageAction::Unstage, &["a.txt"]);
+        assert_eq!(status(&repo, "a.txt"), Status::WT_MODIFIED);
+    }
+
+    #[test]
+    fn staging_a_missing_file_stages_the_deletion() {
+        let repo = TempRepo::new("deletion");
+        repo.write("a.txt", "one\n");
+        repo.commit_all("init");
+        fs::remove_file(repo.path.join("a.txt")).unwrap();
+
+        run(&repo, StageAction::Stage, &["a.txt"]);
+        assert_eq!(status(&repo, "a.txt"), Status::INDEX_DELETED);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_dangling_symlink_is_staged_as_a_link() {
+        let repo = TempRepo::new("symlink");
+        repo.write("a.txt", "one\n");
+        repo.commit_all("init");
+        std::os::unix::fs::symlink("missing", repo.path.join("link")).unwrap();
+
+        run(&repo, StageAction::Stage, &["link"]);
+        assert_eq!(status(&repo, "link"), Status::INDEX_NEW);
+    }
+
+    #[test]
+    fn unstage_before_the_first_commit() {
+        let repo = TempRepo::new("unborn");
+        repo.write("new.txt", "hi\n");
+
+        run(&repo, StageAction::Stage, &["new.txt"]);
+        assert_eq!(status(&repo, "new.txt"), Status::INDEX_NEW);
+
+        run(&repo, StageAction::Unstage, &["new.txt"]);
+        assert_eq!(status(&repo, "new.txt"), Status::WT_NEW);
+    }
+
+    #[test]
+    fn stage_unstage_and_discard_single_lines() {
+        let repo = TempRepo::new("lines");
+        repo.write("f", "1\n2\n3\n4\n5\n");
+        repo.commit_all("init");
+        repo.write("f", "1\nX\n3\n4\nY\n");
+
+        apply_lines(&repo, "f", false, HunkAction::Stage, "Y");
+        assert_eq!(repo.index_text("f"), "1\n2\n3\n4\nY\n");
+
+        apply_lines(&repo, "f", true, HunkAction::Unstage, "Y");
+        assert_eq!(repo.index_text("f"), "1\n2\n3\n4\n5\n");
+
+        apply_lines(&repo, "f", false, HunkAction::Discard, "X");
+        assert_eq!(
+            fs::read_to_string(repo.path.join("f")).unwrap(),
+            "1\n2\n3\n4\nY\n"
+        );
+    }
+
+    #[test]
+    fn stage_part_of_an_untracked_file() {
+        let repo = TempRepo::new("untracked");
+        repo.write("base", "x\n");
+        repo.commit_all("init");
+        repo.write("new", "a\nb\n");
+
+        apply_lines(&repo, "new", false, HunkAction::Stage, "a");
+        assert_eq!(repo.index_text("new"), "a\n");
+    }
+
+    #[test]
+    fn stage_lines_into_an_empty_tracked_file() {
+        let repo = TempRepo::new("empty");
+        repo.write("e", "");
+        repo.commit_all("init");
+        repo.write("e", "a\nb\n");
+
+        apply_lines(&repo, "e", false, HunkAction::Stage, "a");
+        assert_eq!(repo.index_text("e"), "a\n");
+    }
+
+    #[test]
+    fn staging_every_line_away_keeps_the_file() {
+        let repo = TempRepo::new("emptied");
+        repo.write("f", "a\n");
+        repo.commit_all("init");
+        repo.write("f", "");
+
+        apply_lines(&repo, "f", false, HunkAction::Stage, "a");
+        assert_eq!(repo.index_text("f"), "");
+        assert_eq!(status(&repo, "f"), Status::INDEX_MODIFIED);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn staged_lines_keep_the_executable_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let repo = TempRepo::new("executable");
+        repo.write("base", "x\n");
+        repo.commit_all("init");
+        repo.write("run.sh", "#!/bin/sh\necho hi\n");
+        let script = repo.path.join("run.sh");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+        apply_lines(&repo, "run.sh", false, HunkAction::Stage, "#!/bin/sh");
+        let mut index = repo.repo.index().unwrap();
+        index.read(true).unwrap();
+        let entry = index.get_path(std::path::Path::new("run.sh"), 0).unwrap();
+        assert_eq!(entry.mode, 0o100755);
+        assert_eq!(repo.index_text("run.sh"), "#!/bin/sh\n");
+    }
+
+    #[test]
+    fn unstaging_a_rename_restores_both_paths() {
+        let repo = TempRepo::new("rename");
+        repo.write("old.txt", "same text\n");
+        repo.commit_all("init");
+        fs::rename(repo.path.join("old.txt"), repo.path.join("new.txt")).unwrap();
+        run(&repo, StageAction::Stage, &["old.txt", "new.txt"]);
+
+        let entries = load_status(&repo.repo).unwrap().entries;
+        let renamed = entries.iter().find(|e| e.staged).unwrap();
+        assert_eq!(renamed.status_kind, StatusKind::Renamed);
+        assert_eq!(renamed.path, "new.txt");
+        assert_eq!(renamed.old_path.as_deref(), Some("old.txt"));
+
+        run_bg(repo.path(), StageAction::Unstage, &renamed.paths()).unwrap();
+        assert_eq!(status(&repo, "old.txt"), Status::WT_DELETED);
+        assert_eq!(status(&repo, "new.txt"), Status::WT_NEW);
+    }
+}
@@ -0,0 +1,71 @@
+use gpig::panel_loader::load_tags;
+use gpig::tags::{create_tag_bg, delete_tag_bg, validate_tag_name};
+
+mod common;
+use common::TempRepo;
+
+#[cfg(test)]
+mod test_tags {
+    use super::*;
+
+    #[test]
+    fn tag_names_follow_ref_rules() {
+        assert!(validate_tag_name("v1.0.0").is_ok());
+        assert!(validate_tag_name("release/2026").is_ok());
+        for name in ["", "-x", "a..b", "a b", "v1^", "v1.lock"] {
+            assert!(validate_tag_name(name).is_err(), "{name:?}");
+        }
+    }
+
+    #[test]
+    fn lightweight_and_annotated_tags() {
+        let repo = TempRepo::with_identity("create");
+        let old = repo.commit_at("c", 1_000);
+        let new = repo.commit_at("c", 2_000);
+
+        create_tag_bg(repo.path(), "v1", old, None).unwrap();
+        create_tag_bg(repo.path(), "v2", new, Some("Second release\n")).unwrap();
+        assert_eq!(
+            create_tag_bg(repo.path(), "v1", new, None),
+            Err("a tag named 'v1' already exists".to_string())
+        );
+        assert!(create_tag_bg(repo.path(), "v3", new, Some("  ")).is_err());
+
+        let v1 = repo.repo.find_reference("refs/tags/v1").unwrap();
+        assert_eq!(v1.target(), Some(old));
+        assert!(v1.peel_to_tag().is_err());
+
+        let v2 = repo
+            .repo
+            .find_reference("refs/tags/v2")
+            .unwrap()
+            .peel_to_tag()
+            .unwrap();
+        assert_eq!(v2.target_id(), new);
+        assert_eq!(v2.message(), Some("Second release\n"));
+        assert_eq!(v2.tagger().unwrap().name(), Some("Ann"));
+    }
+
+    #[test]
+    fn tags_load_newest_commit_first() {
+        let repo = TempRepo::with_identity("load");
+        let old = repo.commit_at("c", 1_000);
+        let new = repo.commit_at("c", 2_000);
+        create_tag_bg(repo.path(), "a-old", old, None).unwrap();
+        create_tag_bg(repo.path(), "z-new", new, Some("annotated")).unwrap();
+        let tree = repo.repo.find_commit(new).unwrap().tree_id();
+        repo.repo
+            .tag_lightweight("tree", &repo.repo.find_object(tree, None).unwrap(), false)
+            .unwrap();
+
+        let tags = load_tags(&repo.repo).unwrap().tags;
+        let names: Vec<&str> = tags.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["z-new", "a-old"]);
+        assert_eq!(tags[0].target, new);
+        assert!(tags[0].annotated);
+        assert!(!tags[1].annotated);
+
+        delete_tag_bg(repo.path(), "z-new").unwrap();
+        assert_eq!(load_tags(&repo.repo).unwrap().tags.len(), 1);
+    }
+}
@@ -0,0 +1,64 @@
+use gpig::watcher::{ChangeKind, RepoChanges, classify_bg, git_dir_change};
+
+mod common;
+use common::TempRepo;
+
+#[cfg(test)]
+mod test_watcher {
+    use std::fs;
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn refs_and_head_are_ref_changes() {
+        assert_eq!(git_dir_change(Path::new("HEAD")), Some(ChangeKind::Refs));
+        assert_eq!(
+            git_dir_change(Path::new("packed-refs")),
+            Some(ChangeKind::Refs)
+        );
+        assert_eq!(
+            git_dir_change(Path::new("refs/heads/feature/login")),
+            Some(ChangeKind::Refs)
+        );
+        assert_eq!(git_dir_change(Path::new("index")), Some(ChangeKind::Index));
+    }
+
+    #[test]
+    fn locks_objects_and_logs_are_skipped() {
+        assert_eq!(git_dir_change(Path::new("index.lock")), None);
+        assert_eq!(git_dir_change(Path::new("refs/heads/main.lock")), None);
+        assert_eq!(git_dir_change(Path::new("objects/ab/cdef")), None);
+        assert_eq!(git_dir_change(Path::new("logs/HEAD")), None);
+        assert_eq!(git_dir_change(Path::new("")), None);
+    }
+
+    #[test]
+    fn changes_collect_every_kind() {
+        let mut changes = RepoChanges::default();
+        assert!(changes.is_empty());
+        changes.add(ChangeKind::WorkTree);
+        changes.add(ChangeKind::Refs);
+        assert!(changes.refs && changes.work_tree && !changes.index);
+        assert!(!changes.is_empty());
+    }
+
+    #[test]
+    fn ignored_paths_are_no_change_and_new_dirs_are_watched() {
+        let repo = TempRepo::new("watch");
+        repo.write(".gitignore", "target/\n");
+        fs::create_dir(repo.path.join("target")).unwrap();
+        repo.write("target/out", "x");
+
+        let root = repo.repo.workdir().unwrap().to_path_buf();
+        let (changes, new_dirs) = classify_bg(&repo.path, &[root.join("target/out")]);
+        assert!(changes.is_empty());
+        assert!(new_dirs.is_empty());
+
+        fs::create_dir(repo.path.join("src")).unwrap();
+        let paths = [root.join("src"), repo.repo.path().join("HEAD")];
+        let (changes, new_dirs) = classify_bg(&repo.path, &paths);
+        assert!(changes.work_tree && changes.refs && !changes.index);
+        assert_eq!(new_dirs, vec![root.join("src")]);
+    }
+}